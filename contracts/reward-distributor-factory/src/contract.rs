@@ -0,0 +1,154 @@
+use cosmwasm_std::{
+    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Order, Reply, Response,
+    StdResult,
+};
+use cw_storage_plus::Bound;
+
+use crate::error::ContractError;
+use crate::execute::{self, DEFAULT_LIMIT, MAX_LIMIT};
+use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::reply::{self, DISTRIBUTE_FAILED_REPLY_ID, DISTRIBUTOR_INSTANTIATED_REPLY_ID};
+use crate::state::{distributors, Config, DistributorInfo, CONFIG};
+
+pub const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
+pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[entry_point]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    cw2::set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    cw_ownable::initialize_owner(deps.storage, deps.api, Some(&msg.owner))?;
+
+    let config = Config {
+        distributor_code_id: msg.distributor_code_id,
+        defaults: msg.defaults.check(deps.api)?,
+    };
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::default())
+}
+
+#[entry_point]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::UpdateOwnership(action) => {
+            let ownership = cw_ownable::update_ownership(deps, &env.block, &info.sender, action)?;
+            Ok(Response::default().add_attributes(ownership.into_attributes()))
+        }
+        ExecuteMsg::CreateDistributor { label, params } => {
+            execute::execute_create_distributor(deps, info, label, params)
+        }
+        ExecuteMsg::SetDistributorCodeId { code_id } => {
+            execute::execute_set_distributor_code_id(deps, info, code_id)
+        }
+        ExecuteMsg::SetDefaults { defaults } => execute::execute_set_defaults(deps, info, defaults),
+        ExecuteMsg::DistributeAll { start_after, limit } => {
+            execute::execute_distribute_all(deps, start_after, limit)
+        }
+        ExecuteMsg::SyncDistributor { address } => execute::execute_sync_distributor(deps, address),
+    }
+}
+
+#[entry_point]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        DISTRIBUTOR_INSTANTIATED_REPLY_ID => reply::reply_distributor_instantiated(deps, msg),
+        id if id >= DISTRIBUTE_FAILED_REPLY_ID => {
+            let index = (id - DISTRIBUTE_FAILED_REPLY_ID) as usize;
+            reply::reply_distribute_failed(deps, index, msg)
+        }
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+#[entry_point]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Ownership {} => {
+            let ownership = cw_ownable::get_ownership(deps.storage)?;
+            to_json_binary(&ownership)
+        }
+        QueryMsg::Config {} => to_json_binary(&CONFIG.load(deps.storage)?),
+        QueryMsg::Distributor { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            to_json_binary(&distributors().may_load(deps.storage, &address)?)
+        }
+        QueryMsg::Distributors { start_after, limit } => {
+            let start_after = start_after
+                .map(|addr| deps.api.addr_validate(&addr))
+                .transpose()?;
+            let distributors: Vec<DistributorInfo> = distributors()
+                .range(
+                    deps.storage,
+                    start_after.as_ref().map(Bound::exclusive),
+                    None,
+                    Order::Ascending,
+                )
+                .take(query_limit(limit))
+                .map(|item| item.map(|(_, info)| info))
+                .collect::<StdResult<_>>()?;
+            to_json_binary(&distributors)
+        }
+        QueryMsg::DistributorsByRewardToken {
+            reward_token,
+            start_after,
+            limit,
+        } => {
+            let start_after = start_after
+                .map(|addr| deps.api.addr_validate(&addr))
+                .transpose()?;
+            let distributors: Vec<DistributorInfo> = distributors()
+                .idx
+                .reward_token
+                .prefix(reward_token.check(deps.api)?.to_string())
+                .range(
+                    deps.storage,
+                    start_after.as_ref().map(Bound::exclusive),
+                    None,
+                    Order::Ascending,
+                )
+                .take(query_limit(limit))
+                .map(|item| item.map(|(_, info)| info))
+                .collect::<StdResult<_>>()?;
+            to_json_binary(&distributors)
+        }
+        QueryMsg::DistributorsByRecipient {
+            recipient,
+            start_after,
+            limit,
+        } => {
+            let recipient = deps.api.addr_validate(&recipient)?;
+            let start_after = start_after
+                .map(|addr| deps.api.addr_validate(&addr))
+                .transpose()?;
+            let distributors: Vec<DistributorInfo> = distributors()
+                .idx
+                .recipient
+                .prefix(recipient)
+                .range(
+                    deps.storage,
+                    start_after.as_ref().map(Bound::exclusive),
+                    None,
+                    Order::Ascending,
+                )
+                .take(query_limit(limit))
+                .map(|item| item.map(|(_, info)| info))
+                .collect::<StdResult<_>>()?;
+            to_json_binary(&distributors)
+        }
+    }
+}
+
+/// Returns the number of distributors to return from a registry query
+fn query_limit(limit: Option<u32>) -> usize {
+    limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize
+}
@@ -0,0 +1,23 @@
+use cosmwasm_std::StdError;
+use cw_ownable::OwnershipError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ContractError {
+    #[error(transparent)]
+    Std(#[from] StdError),
+
+    #[error(transparent)]
+    Ownership(#[from] OwnershipError),
+
+    #[error(transparent)]
+    Distributor(#[from] neutron_astroport_reward_distributor::ContractError),
+
+    #[error(transparent)]
+    ParseReply(#[from] cw_utils::ParseReplyError),
+
+    #[error("Distributor {address} is not registered by the factory")]
+    UnknownDistributor { address: String },
+
+    #[error("Unknown reply id: {id}")]
+    UnknownReplyId { id: u64 },
+}
@@ -0,0 +1,206 @@
+use cosmwasm_std::{
+    to_json_binary, Decimal, Decimal256, DepsMut, Event, MessageInfo, Order, Response, StdResult,
+    SubMsg, WasmMsg,
+};
+use cw_storage_plus::Bound;
+use neutron_astroport_reward_distributor::msg::{
+    ExecuteMsg as DistributorExecuteMsg, InstantiateMsg as DistributorInstantiateMsg,
+    QueryMsg as DistributorQueryMsg, StateResponse as DistributorStateResponse,
+};
+
+use crate::error::ContractError;
+use crate::msg::DistributorParams;
+use crate::reply::{DISTRIBUTE_FAILED_REPLY_ID, DISTRIBUTOR_INSTANTIATED_REPLY_ID};
+use crate::state::{
+    distributors, DistributorDefaults, PendingDistributor, CONFIG, DISTRIBUTING,
+    PENDING_DISTRIBUTOR,
+};
+
+/// The number of distributors triggered by `DistributeAll` or returned by a
+/// registry query if no limit is given
+pub const DEFAULT_LIMIT: u32 = 10;
+
+/// The maximum number of distributors triggered by `DistributeAll` or returned
+/// by a registry query
+pub const MAX_LIMIT: u32 = 30;
+
+pub fn execute_create_distributor(
+    deps: DepsMut,
+    info: MessageInfo,
+    label: String,
+    params: DistributorParams,
+) -> Result<Response, ContractError> {
+    // only owner can send this message
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let defaults = config.defaults;
+    let recipient = deps.api.addr_validate(&params.distribution_addr)?;
+
+    // The distributor's address is only known once it has been instantiated,
+    // so it is added to the registry in the reply
+    PENDING_DISTRIBUTOR.save(
+        deps.storage,
+        &PendingDistributor {
+            label: label.clone(),
+            recipient: recipient.clone(),
+        },
+    )?;
+
+    // Guardrails are left open, to be tightened by the distributor's owner
+    let msg = DistributorInstantiateMsg {
+        owner: defaults.owner.to_string(),
+        guardian: defaults.guardian.map(Into::into),
+        emission_per_second: params.emission_per_second,
+        reward_token_info: params.reward_token_info,
+        distribution_addr: params.distribution_addr,
+        rewards_start_time: params.rewards_start_time,
+        config_update_delay: defaults.config_update_delay,
+        instant_rate_decreases: false,
+        min_emission_per_second: Decimal256::zero(),
+        max_emission_per_second: Decimal256::MAX,
+        max_rate_change: Decimal::zero(),
+        rate_change_window: 0,
+        delivery: params.delivery,
+        destination_pool: None,
+        distribution_mode: params.distribution_mode,
+        fee_bps: defaults.fee_bps,
+        fee_recipient: defaults.fee_recipient.map(Into::into),
+        recipient_code_id: None,
+        staking_addr: params.staking_addr,
+    };
+    let instantiate_msg = WasmMsg::Instantiate {
+        admin: Some(defaults.owner.to_string()),
+        code_id: config.distributor_code_id,
+        msg: to_json_binary(&msg)?,
+        funds: info.funds,
+        label: label.clone(),
+    };
+
+    let event = Event::new(
+        "apollo/neutron-astroport-reward-distributor-factory/execute_create_distributor",
+    )
+    .add_attribute("label", label)
+    .add_attribute("recipient", recipient);
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(
+            instantiate_msg,
+            DISTRIBUTOR_INSTANTIATED_REPLY_ID,
+        ))
+        .add_event(event))
+}
+
+pub fn execute_set_distributor_code_id(
+    deps: DepsMut,
+    info: MessageInfo,
+    code_id: u64,
+) -> Result<Response, ContractError> {
+    // only owner can send this message
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    CONFIG.update(deps.storage, |mut config| -> StdResult<_> {
+        config.distributor_code_id = code_id;
+        Ok(config)
+    })?;
+
+    let event = Event::new(
+        "apollo/neutron-astroport-reward-distributor-factory/execute_set_distributor_code_id",
+    )
+    .add_attribute("code_id", code_id.to_string());
+
+    Ok(Response::new().add_event(event))
+}
+
+pub fn execute_set_defaults(
+    deps: DepsMut,
+    info: MessageInfo,
+    defaults: DistributorDefaults<String>,
+) -> Result<Response, ContractError> {
+    // only owner can send this message
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    let defaults = defaults.check(deps.api)?;
+    let mut config = CONFIG.load(deps.storage)?;
+    config.defaults = defaults;
+    CONFIG.save(deps.storage, &config)?;
+
+    let event =
+        Event::new("apollo/neutron-astroport-reward-distributor-factory/execute_set_defaults")
+            .add_attribute("defaults", format!("{:?}", config.defaults));
+
+    Ok(Response::new().add_event(event))
+}
+
+pub fn execute_distribute_all(
+    deps: DepsMut,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let start_after = start_after
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let addresses = distributors()
+        .keys(
+            deps.storage,
+            start_after.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    // Failures are handled in the reply, so that one distributor cannot block
+    // the others. The index of the distributor is added to the reply id.
+    DISTRIBUTING.save(deps.storage, &addresses)?;
+    let distribute_msgs = addresses
+        .iter()
+        .enumerate()
+        .map(|(i, addr)| {
+            Ok(SubMsg::reply_on_error(
+                WasmMsg::Execute {
+                    contract_addr: addr.to_string(),
+                    msg: to_json_binary(&DistributorExecuteMsg::Distribute {})?,
+                    funds: vec![],
+                },
+                DISTRIBUTE_FAILED_REPLY_ID + i as u64,
+            ))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut event =
+        Event::new("apollo/neutron-astroport-reward-distributor-factory/execute_distribute_all")
+            .add_attribute("count", addresses.len().to_string());
+    if let Some(last) = addresses.last() {
+        event = event.add_attribute("last", last.to_string());
+    }
+
+    Ok(Response::new()
+        .add_submessages(distribute_msgs)
+        .add_event(event))
+}
+
+pub fn execute_sync_distributor(deps: DepsMut, address: String) -> Result<Response, ContractError> {
+    let address = deps.api.addr_validate(&address)?;
+    let mut info = distributors().may_load(deps.storage, &address)?.ok_or(
+        ContractError::UnknownDistributor {
+            address: address.to_string(),
+        },
+    )?;
+
+    // Saving the entry again moves it to the current recipient in the index
+    let state: DistributorStateResponse = deps
+        .querier
+        .query_wasm_smart(&address, &DistributorQueryMsg::State {})?;
+    info.recipient = state.config.distribution_addr;
+    distributors().save(deps.storage, &address, &info)?;
+
+    let event =
+        Event::new("apollo/neutron-astroport-reward-distributor-factory/execute_sync_distributor")
+            .add_attribute("distributor", address)
+            .add_attribute("recipient", info.recipient);
+
+    Ok(Response::new().add_event(event))
+}
@@ -0,0 +1,8 @@
+pub mod contract;
+pub mod error;
+pub mod execute;
+pub mod msg;
+pub mod reply;
+pub mod state;
+
+pub use error::ContractError;
@@ -0,0 +1,108 @@
+use apollo_cw_asset::AssetInfoUnchecked;
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::Decimal256;
+use cw_ownable::{cw_ownable_execute, cw_ownable_query};
+use neutron_astroport_reward_distributor::msg::RewardInfo;
+use neutron_astroport_reward_distributor::{Delivery, DistributionMode};
+
+use crate::state::{Config, DistributorDefaults, DistributorInfo};
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// The account to be appointed the factory owner
+    pub owner: String,
+    /// The code id of the reward distributor contract
+    pub distributor_code_id: u64,
+    /// The settings shared by all distributors created by the factory
+    pub defaults: DistributorDefaults<String>,
+}
+
+/// The settings of a distributor that differ between farms
+#[cw_serde]
+pub struct DistributorParams {
+    /// The emission rate per second
+    pub emission_per_second: Decimal256,
+    /// The info needed to instantiate the distributor depending on the type of
+    /// reward token used
+    pub reward_token_info: RewardInfo,
+    /// The address that rewards are being distributed to
+    pub distribution_addr: String,
+    /// The unix timestamp at which rewards start being distributed
+    pub rewards_start_time: u64,
+    /// Whether rewards are delivered as the reward tokens themselves or as the
+    /// assets underlying them
+    pub delivery: Delivery,
+    /// How rewards reach the distribution address
+    pub distribution_mode: DistributionMode,
+    /// The cw20-stake contract whose stakers share the rewards in staked mode
+    pub staking_addr: Option<String>,
+}
+
+#[cw_ownable_execute]
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Instantiates a reward distributor with the factory's defaults and adds
+    /// it to the registry. The funds sent, such as the denom creation fee of
+    /// minted rewards, are passed on to the distributor. Can only be called by
+    /// the owner.
+    CreateDistributor {
+        /// The label of the distributor contract
+        label: String,
+        params: DistributorParams,
+    },
+    /// Sets the code id that new distributors are instantiated from. Can only
+    /// be called by the owner.
+    SetDistributorCodeId { code_id: u64 },
+    /// Sets the settings shared by distributors created from now on. Can only
+    /// be called by the owner.
+    SetDefaults {
+        defaults: DistributorDefaults<String>,
+    },
+    /// Calls `Distribute` on a page of the registered distributors, ordered by
+    /// address. A distributor that fails to distribute, for example because it
+    /// is paused, does not fail the others. Can be called by anyone.
+    DistributeAll {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Updates the registry entry of the given distributor to its current
+    /// distribution address, after its recipient changed. Can be called by
+    /// anyone.
+    SyncDistributor { address: String },
+}
+
+#[cw_ownable_query]
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    #[returns(Config)]
+    /// Returns the distributor code id and the shared defaults
+    Config {},
+    #[returns(Option<DistributorInfo>)]
+    /// Returns the registry entry of the given distributor, if any
+    Distributor { address: String },
+    #[returns(Vec<DistributorInfo>)]
+    /// Returns a page of the registered distributors, ordered by address
+    Distributors {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(Vec<DistributorInfo>)]
+    /// Returns a page of the distributors whose reward token is held as the
+    /// given asset, ordered by address. Vault rewards are found by their vault
+    /// token and pool rewards by their LP token.
+    DistributorsByRewardToken {
+        reward_token: AssetInfoUnchecked,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(Vec<DistributorInfo>)]
+    /// Returns a page of the distributors with the given distribution address,
+    /// ordered by address. Distributors whose recipient changed are found by
+    /// their new recipient once synced with `SyncDistributor`.
+    DistributorsByRecipient {
+        recipient: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
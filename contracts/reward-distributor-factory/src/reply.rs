@@ -0,0 +1,77 @@
+use cosmwasm_std::{DepsMut, Event, Reply, Response, SubMsgResult};
+use cw_utils::parse_reply_instantiate_data;
+use neutron_astroport_reward_distributor::msg::{
+    QueryMsg as DistributorQueryMsg, StateResponse as DistributorStateResponse,
+};
+
+use crate::error::ContractError;
+use crate::state::{distributors, DistributorInfo, DISTRIBUTING, PENDING_DISTRIBUTOR};
+
+/// Reply id for when a distributor has been instantiated
+pub const DISTRIBUTOR_INSTANTIATED_REPLY_ID: u64 = 1;
+
+/// Reply id offset for when a distributor failed to distribute in
+/// `DistributeAll`. The index of the distributor is added to it.
+pub const DISTRIBUTE_FAILED_REPLY_ID: u64 = 1_000;
+
+pub fn reply_distributor_instantiated(
+    deps: DepsMut,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    let address = deps
+        .api
+        .addr_validate(&parse_reply_instantiate_data(msg)?.contract_address)?;
+    let pending = PENDING_DISTRIBUTOR.load(deps.storage)?;
+    PENDING_DISTRIBUTOR.remove(deps.storage);
+
+    // The distributor is indexed by the asset its reward token is held as, so
+    // that distributors of the same token are found however it was given
+    let state: DistributorStateResponse = deps
+        .querier
+        .query_wasm_smart(&address, &DistributorQueryMsg::State {})?;
+    let reward_token = state.reward_asset.to_string();
+
+    distributors().save(
+        deps.storage,
+        &address,
+        &DistributorInfo {
+            address: address.clone(),
+            label: pending.label,
+            reward_token: reward_token.clone(),
+            recipient: pending.recipient,
+        },
+    )?;
+
+    let event = Event::new(
+        "apollo/neutron-astroport-reward-distributor-factory/reply_distributor_instantiated",
+    )
+    .add_attribute("distributor", address)
+    .add_attribute("reward_token", reward_token);
+
+    Ok(Response::new().add_event(event))
+}
+
+/// Records the failure of a distributor to distribute, without failing the
+/// other distributions
+pub fn reply_distribute_failed(
+    deps: DepsMut,
+    index: usize,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    let distributor = DISTRIBUTING
+        .load(deps.storage)?
+        .get(index)
+        .cloned()
+        .ok_or(ContractError::UnknownReplyId { id: msg.id })?;
+    let error = match msg.result {
+        SubMsgResult::Err(error) => error,
+        SubMsgResult::Ok(_) => String::new(),
+    };
+
+    let event =
+        Event::new("apollo/neutron-astroport-reward-distributor-factory/reply_distribute_failed")
+            .add_attribute("distributor", distributor)
+            .add_attribute("error", error);
+
+    Ok(Response::new().add_event(event))
+}
@@ -0,0 +1,133 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Api};
+use cw_address_like::AddressLike;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex};
+use neutron_astroport_reward_distributor::MAX_FEE_BPS;
+
+use crate::error::ContractError;
+
+/// The settings shared by all distributors created by the factory
+#[cw_serde]
+pub struct DistributorDefaults<T: AddressLike> {
+    /// The account appointed the owner of each distributor. Also set as the
+    /// admin of the distributor contracts.
+    pub owner: T,
+    /// The account granted the guardian role on each distributor, if any
+    pub guardian: Option<T>,
+    /// The fee in basis points charged on every delivery
+    pub fee_bps: u16,
+    /// The address that the fee is sent to
+    pub fee_recipient: Option<T>,
+    /// The number of seconds that must pass between proposing a config update
+    /// and applying it
+    pub config_update_delay: u64,
+}
+
+impl DistributorDefaults<String> {
+    /// Checks that the defaults are valid and returns them with validated
+    /// addresses
+    pub fn check(self, api: &dyn Api) -> Result<DistributorDefaults<Addr>, ContractError> {
+        if self.fee_bps >= MAX_FEE_BPS {
+            return Err(
+                neutron_astroport_reward_distributor::ContractError::InvalidFeeBps {
+                    fee_bps: self.fee_bps,
+                }
+                .into(),
+            );
+        }
+        if self.fee_bps > 0 && self.fee_recipient.is_none() {
+            return Err(
+                neutron_astroport_reward_distributor::ContractError::MissingFeeRecipient {}.into(),
+            );
+        }
+
+        Ok(DistributorDefaults {
+            owner: api.addr_validate(&self.owner)?,
+            guardian: self
+                .guardian
+                .map(|addr| api.addr_validate(&addr))
+                .transpose()?,
+            fee_bps: self.fee_bps,
+            fee_recipient: self
+                .fee_recipient
+                .map(|addr| api.addr_validate(&addr))
+                .transpose()?,
+            config_update_delay: self.config_update_delay,
+        })
+    }
+}
+
+/// The config state
+#[cw_serde]
+pub struct Config {
+    /// The code id that new distributors are instantiated from
+    pub distributor_code_id: u64,
+    /// The settings shared by distributors created from now on
+    pub defaults: DistributorDefaults<Addr>,
+}
+
+/// A distributor being instantiated, whose address is received in a reply
+#[cw_serde]
+pub struct PendingDistributor {
+    /// The label of the distributor contract
+    pub label: String,
+    /// The distribution address of the distributor
+    pub recipient: Addr,
+}
+
+/// The registry entry of a distributor created by the factory
+#[cw_serde]
+pub struct DistributorInfo {
+    /// The address of the distributor contract
+    pub address: Addr,
+    /// The label of the distributor contract
+    pub label: String,
+    /// The asset that the distributor's reward token is held as: the vault
+    /// token, LP token or native denom
+    pub reward_token: String,
+    /// The distribution address of the distributor. When the distributor's
+    /// recipient changes, it is updated by `SyncDistributor`.
+    pub recipient: Addr,
+}
+
+/// The secondary indexes of the distributor registry
+pub struct DistributorIndexes<'a> {
+    pub reward_token: MultiIndex<'a, String, DistributorInfo, &'a Addr>,
+    pub recipient: MultiIndex<'a, Addr, DistributorInfo, &'a Addr>,
+}
+
+impl<'a> IndexList<DistributorInfo> for DistributorIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<DistributorInfo>> + '_> {
+        let v: Vec<&dyn Index<DistributorInfo>> = vec![&self.reward_token, &self.recipient];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Stores the registry of distributors created by the factory, keyed by
+/// address and indexed by reward token and recipient
+pub fn distributors<'a>() -> IndexedMap<'a, &'a Addr, DistributorInfo, DistributorIndexes<'a>> {
+    let indexes = DistributorIndexes {
+        reward_token: MultiIndex::new(
+            |_pk, info| info.reward_token.clone(),
+            "distributors",
+            "distributors__reward_token",
+        ),
+        recipient: MultiIndex::new(
+            |_pk, info| info.recipient.clone(),
+            "distributors",
+            "distributors__recipient",
+        ),
+    };
+    IndexedMap::new("distributors", indexes)
+}
+
+/// Stores the factory's config
+pub const CONFIG: Item<Config> = Item::new("config");
+
+/// Stores the distributor being instantiated, for the reply handler to add it
+/// to the registry
+pub const PENDING_DISTRIBUTOR: Item<PendingDistributor> = Item::new("pending_distributor");
+
+/// Stores the distributors triggered by the last `DistributeAll`, for the
+/// reply handler to find the distributor that failed
+pub const DISTRIBUTING: Item<Vec<Addr>> = Item::new("distributing");
@@ -0,0 +1,276 @@
+use apollo_cw_asset::AssetInfoUnchecked;
+use cosmwasm_std::{coin, Addr, Decimal256, Uint128};
+use cw_it::cw_multi_test::ContractWrapper;
+use cw_it::multi_test::MultiTestRunner;
+use cw_it::osmosis_std::types::cosmos::bank::v1beta1::{MsgSend, QueryBalanceRequest};
+use cw_it::test_tube::{Account, Bank, Module, SigningAccount, Wasm};
+use cw_it::traits::CwItRunner;
+use cw_it::{ContractType, OwnedTestRunner};
+use cw_ownable::Ownership;
+use neutron_astroport_reward_distributor::msg::{
+    ExecuteMsg as DistributorExecuteMsg, QueryMsg as DistributorQueryMsg, RewardInfo, StateResponse,
+};
+use neutron_astroport_reward_distributor::{ConfigUpdates, Delivery, DistributionMode, Role};
+use neutron_astroport_reward_distributor_factory::contract;
+use neutron_astroport_reward_distributor_factory::msg::{
+    DistributorParams, ExecuteMsg, InstantiateMsg, QueryMsg,
+};
+use neutron_astroport_reward_distributor_factory::state::{DistributorDefaults, DistributorInfo};
+use neutron_astroport_reward_distributor_test_helpers::robot::RewardDistributorRobot;
+
+const UNOPTIMIZED_PATH: &str = "../target/wasm32-unknown-unknown/release";
+
+fn factory_contract() -> ContractType {
+    ContractType::MultiTestContract(Box::new(
+        ContractWrapper::new_with_empty(contract::execute, contract::instantiate, contract::query)
+            .with_reply(contract::reply),
+    ))
+}
+
+fn native_coin_params(denom: &str, recipient: &SigningAccount) -> DistributorParams {
+    DistributorParams {
+        emission_per_second: Decimal256::from_ratio(1_000u128, 1u128),
+        reward_token_info: RewardInfo::NativeCoin(denom.to_string()),
+        distribution_addr: recipient.address(),
+        rewards_start_time: 0,
+        delivery: Delivery::Underlying,
+        distribution_mode: DistributionMode::Push,
+        staking_addr: None,
+    }
+}
+
+#[test]
+fn factory_creates_registers_and_triggers_distributors() {
+    let owned_runner = OwnedTestRunner::MultiTest(MultiTestRunner::new("osmo"));
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let guardian = runner.init_account(&[]).unwrap();
+    let fee_recipient = runner.init_account(&[]).unwrap();
+    let alice = runner.init_account(&[]).unwrap();
+    let bob = runner.init_account(&[]).unwrap();
+    let wasm = Wasm::new(&runner);
+
+    let distributor_code_id = runner
+        .store_code(
+            RewardDistributorRobot::contract(&runner, UNOPTIMIZED_PATH),
+            &admin,
+        )
+        .unwrap();
+    let factory_code_id = runner.store_code(factory_contract(), &admin).unwrap();
+    let defaults = DistributorDefaults {
+        owner: admin.address(),
+        guardian: Some(guardian.address()),
+        fee_bps: 100,
+        fee_recipient: Some(fee_recipient.address()),
+        config_update_delay: 0,
+    };
+    let factory_addr = wasm
+        .instantiate(
+            factory_code_id,
+            &InstantiateMsg {
+                owner: admin.address(),
+                distributor_code_id,
+                defaults: defaults.clone(),
+            },
+            None,
+            None,
+            &[],
+            &admin,
+        )
+        .unwrap()
+        .data
+        .address;
+
+    // The defaults are validated
+    let invalid_defaults = ExecuteMsg::SetDefaults {
+        defaults: DistributorDefaults {
+            fee_recipient: None,
+            ..defaults
+        },
+    };
+    let err = wasm
+        .execute(&factory_addr, &invalid_defaults, &[], &admin)
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("A fee recipient must be set to charge a fee"));
+
+    // Only the owner can create distributors
+    let create_msg =
+        |label: &str, denom: &str, recipient: &SigningAccount| ExecuteMsg::CreateDistributor {
+            label: label.to_string(),
+            params: native_coin_params(denom, recipient),
+        };
+    let err = wasm
+        .execute(
+            &factory_addr,
+            &create_msg("axl", "uaxl", &alice),
+            &[],
+            &alice,
+        )
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Caller is not the contract's current owner"));
+    wasm.execute(
+        &factory_addr,
+        &create_msg("axl", "uaxl", &alice),
+        &[],
+        &admin,
+    )
+    .unwrap();
+    wasm.execute(
+        &factory_addr,
+        &create_msg("ntrn", "untrn", &bob),
+        &[],
+        &admin,
+    )
+    .unwrap();
+
+    // The registry can be queried by reward token and by recipient
+    let all: Vec<DistributorInfo> = wasm
+        .query(
+            &factory_addr,
+            &QueryMsg::Distributors {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(all.len(), 2);
+    let by_token: Vec<DistributorInfo> = wasm
+        .query(
+            &factory_addr,
+            &QueryMsg::DistributorsByRewardToken {
+                reward_token: AssetInfoUnchecked::native("uaxl"),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(by_token.len(), 1);
+    assert_eq!(by_token[0].recipient, Addr::unchecked(alice.address()));
+    let axl_distributor = by_token[0].address.to_string();
+    let by_recipient: Vec<DistributorInfo> = wasm
+        .query(
+            &factory_addr,
+            &QueryMsg::DistributorsByRecipient {
+                recipient: bob.address(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(by_recipient.len(), 1);
+    assert_eq!(by_recipient[0].label, "ntrn");
+    let ntrn_distributor = by_recipient[0].address.to_string();
+
+    // Distributors are created with the shared defaults
+    let state: StateResponse = wasm
+        .query(&axl_distributor, &DistributorQueryMsg::State {})
+        .unwrap();
+    assert_eq!(state.config.fee_bps, 100);
+    assert_eq!(
+        state.config.fee_recipient,
+        Some(Addr::unchecked(fee_recipient.address()))
+    );
+    let ownership: Ownership<Addr> = wasm
+        .query(&axl_distributor, &DistributorQueryMsg::Ownership {})
+        .unwrap();
+    assert_eq!(ownership.owner, Some(Addr::unchecked(admin.address())));
+    let roles: Vec<Role> = wasm
+        .query(
+            &axl_distributor,
+            &DistributorQueryMsg::Roles {
+                address: guardian.address(),
+            },
+        )
+        .unwrap();
+    assert_eq!(roles, vec![Role::Guardian]);
+
+    // A paused distributor does not block the others from distributing
+    Bank::new(&runner)
+        .send(
+            MsgSend {
+                from_address: admin.address(),
+                to_address: axl_distributor.clone(),
+                amount: vec![coin(1_000_000_000, "uaxl").into()],
+            },
+            &admin,
+        )
+        .unwrap();
+    wasm.execute(
+        &ntrn_distributor,
+        &DistributorExecuteMsg::Pause {},
+        &[],
+        &guardian,
+    )
+    .unwrap();
+    runner.increase_time(100).unwrap();
+    let distribute_all = ExecuteMsg::DistributeAll {
+        start_after: None,
+        limit: None,
+    };
+    wasm.execute(&factory_addr, &distribute_all, &[], &alice)
+        .unwrap();
+    let balance = |address: String, denom: &str| -> Uint128 {
+        Bank::new(&runner)
+            .query_balance(&QueryBalanceRequest {
+                address,
+                denom: denom.to_string(),
+            })
+            .unwrap()
+            .balance
+            .map(|coin| coin.amount.parse().unwrap())
+            .unwrap_or_default()
+    };
+    assert!(!balance(alice.address(), "uaxl").is_zero());
+    assert!(!balance(fee_recipient.address(), "uaxl").is_zero());
+    assert!(balance(bob.address(), "untrn").is_zero());
+
+    // Once a distributor's recipient changes, anyone can sync its registry
+    // entry, so that it is found by the new recipient
+    let mut updates = ConfigUpdates::default();
+    updates.distribution_addr(bob.address());
+    wasm.execute(
+        &axl_distributor,
+        &DistributorExecuteMsg::UpdateConfig {
+            updates,
+            ramp_duration: None,
+        },
+        &[],
+        &admin,
+    )
+    .unwrap();
+    wasm.execute(
+        &axl_distributor,
+        &DistributorExecuteMsg::AcceptRecipient {},
+        &[],
+        &bob,
+    )
+    .unwrap();
+    let sync_msg = |address: &str| ExecuteMsg::SyncDistributor {
+        address: address.to_string(),
+    };
+    wasm.execute(&factory_addr, &sync_msg(&axl_distributor), &[], &alice)
+        .unwrap();
+    let by_recipient = |recipient: String| -> Vec<DistributorInfo> {
+        wasm.query(
+            &factory_addr,
+            &QueryMsg::DistributorsByRecipient {
+                recipient,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap()
+    };
+    assert!(by_recipient(alice.address()).is_empty());
+    assert_eq!(by_recipient(bob.address()).len(), 2);
+
+    // Only registered distributors can be synced
+    let err = wasm
+        .execute(&factory_addr, &sync_msg(&alice.address()), &[], &alice)
+        .unwrap_err();
+    assert!(err.to_string().contains("is not registered by the factory"));
+}
@@ -0,0 +1,119 @@
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Addr, Deps, DepsMut, Env, Event, QueryRequest, Response, StdError, StdResult,
+    Uint128, WasmMsg, WasmQuery,
+};
+use cw20::{Cw20QueryMsg, MinterResponse};
+use cw_dex::astroport::AstroportPool;
+use cw_dex::traits::Pool as PoolTrait;
+use neutron_astroport_reward_distributor::{ContractError, ASTROPORT_NATIVE_LP_TOKENS};
+use serde::Deserialize;
+
+use crate::helpers::TOKENFACTORY_DENOM_PREFIX;
+
+/// The execute message of Astroport pairs that issue native tokenfactory LP
+/// tokens. Liquidity is withdrawn by sending the LP tokens to the pair.
+#[cw_serde]
+enum AstroportPairExecuteMsg {
+    WithdrawLiquidity {},
+}
+
+/// The query message of Astroport pairs
+#[cw_serde]
+enum AstroportPairQueryMsg {
+    Pair {},
+}
+
+/// The part of the pair info of Astroport pairs that is needed to tell native
+/// LP tokens apart from CW20 LP tokens
+#[derive(Deserialize)]
+struct AstroportPairInfo {
+    liquidity_token: String,
+}
+
+/// Returns true if the given Astroport LP token is a native tokenfactory denom
+/// rather than a CW20 address
+pub fn is_native_lp_token(lp_token: &str) -> bool {
+    lp_token.starts_with(TOKENFACTORY_DENOM_PREFIX)
+}
+
+/// Finds the Astroport pair that issued the given LP token. Native
+/// tokenfactory LP tokens are created by the pair, so the pair address is part
+/// of the denom. CW20 LP tokens are minted by the pair.
+pub fn find_astroport_pair(deps: Deps, lp_token: &str) -> StdResult<Addr> {
+    let invalid_base_token = || StdError::generic_err("Invalid base token of reward vault");
+
+    if is_native_lp_token(lp_token) {
+        let creator = lp_token.split('/').nth(1).ok_or_else(invalid_base_token)?;
+        return deps.api.addr_validate(creator);
+    }
+
+    // Query minter of LP token to get reward pool address
+    let lp_token_addr = deps
+        .api
+        .addr_validate(lp_token)
+        .map_err(|_| invalid_base_token())?;
+    let minter_res: MinterResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: lp_token_addr.to_string(),
+        msg: to_json_binary(&Cw20QueryMsg::Minter {})?,
+    }))?;
+    deps.api.addr_validate(&minter_res.minter)
+}
+
+/// Saves the LP token of the Astroport pair if it is a native tokenfactory
+/// denom. Newer Astroport pairs issue native LP tokens, which cw-dex can not
+/// represent.
+pub fn save_native_lp_token(deps: DepsMut, pair_addr: &Addr) -> StdResult<()> {
+    let pair_info: AstroportPairInfo = deps
+        .querier
+        .query_wasm_smart(pair_addr, &AstroportPairQueryMsg::Pair {})?;
+    if is_native_lp_token(&pair_info.liquidity_token) {
+        ASTROPORT_NATIVE_LP_TOKENS.save(deps.storage, pair_addr, &pair_info.liquidity_token)?;
+    }
+    Ok(())
+}
+
+/// Returns the LP token of the Astroport pool, which is either the native LP
+/// denom saved for the pair or the CW20 LP token known to cw-dex
+pub fn lp_token(deps: Deps, pool: &AstroportPool) -> StdResult<AssetInfo> {
+    match ASTROPORT_NATIVE_LP_TOKENS.may_load(deps.storage, &pool.pair_addr)? {
+        Some(denom) => Ok(AssetInfo::Native(denom)),
+        None => Ok(pool.lp_token()),
+    }
+}
+
+/// Returns a response with the messages to withdraw liquidity from the
+/// Astroport pool. CW20 LP tokens are withdrawn through cw-dex, while native LP
+/// tokens are sent to the pair directly.
+pub fn withdraw_liquidity(
+    deps: Deps,
+    env: &Env,
+    pool: &AstroportPool,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    match lp_token(deps, pool)? {
+        AssetInfo::Native(denom) => {
+            let lp_tokens = Asset::new(AssetInfo::Native(denom), amount);
+            let withdraw_msg = WasmMsg::Execute {
+                contract_addr: pool.pair_addr.to_string(),
+                msg: to_json_binary(&AstroportPairExecuteMsg::WithdrawLiquidity {})?,
+                funds: vec![lp_tokens.clone().try_into()?],
+            };
+
+            let event =
+                Event::new("apollo/neutron-astroport-reward-distributor/withdraw_liquidity")
+                    .add_attribute("pair_addr", &pool.pair_addr)
+                    .add_attribute("asset", format!("{:?}", lp_tokens))
+                    .add_attribute("token_amount", amount);
+
+            Ok(Response::new().add_message(withdraw_msg).add_event(event))
+        }
+        lp_token => Ok(pool.withdraw_liquidity(
+            deps,
+            env,
+            Asset::new(lp_token, amount),
+            AssetList::new(),
+        )?),
+    }
+}
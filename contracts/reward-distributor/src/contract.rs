@@ -1,48 +1,78 @@
 use cosmwasm_std::{
-    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, QueryRequest, Response,
-    StdError, StdResult, WasmQuery,
+    entry_point, to_json_binary, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Order, Reply,
+    Response, StdError, StdResult, Uint128,
 };
-use cw20::{Cw20QueryMsg, MinterResponse};
+#[cfg(feature = "astroport")]
 use cw_dex::astroport::AstroportPool;
+use cw_dex::Pool;
+use cw_storage_plus::Bound;
+use cw_vault_standard::extensions::lockup::UnlockingPosition;
 use cw_vault_standard::{VaultContract, VaultContractUnchecked};
 use neutron_astroport_reward_distributor::{
-    Config, ConfigUnchecked, ContractError, ExecuteMsg, InstantiateMsg, InternalMsg, QueryMsg,
-    RewardInfo, RewardType, StateResponse, CONFIG, LAST_DISTRIBUTED, REWARD_TOKEN,
+    Config, ConfigUnchecked, ContractError, DistributionMode, ExecuteMsg, InstantiateMsg,
+    MigrateMsg, QueryMsg, RewardInfo, RewardType, Role, StakerRewardsResponse, StateResponse,
+    SudoMsg, CLAIMED_LEAVES, CONFIG, DESTINATION_POOL, EMISSION_RAMP, ESCROW, FEES_COLLECTED,
+    LAST_DISTRIBUTED, MERKLE_AIRDROPS, PAUSED, PENDING_CONFIG_UPDATE, PENDING_RECIPIENT,
+    PENDING_REWARDS, PENDING_SINCE, REWARD_INDEX, REWARD_TOKEN, ROLES, TOTAL_STAKE,
+    UNCLAIMED_REWARDS, VESTING_POSITIONS, VESTING_SCHEDULES,
 };
+use osmosis_std::types::osmosis::tokenfactory::v1beta1::MsgCreateDenom;
 
-use crate::execute;
+#[cfg(feature = "astroport")]
+use crate::astroport::{self, find_astroport_pair, save_native_lp_token};
+use crate::execute::{DEFAULT_LIMIT, MAX_LIMIT};
+#[cfg(feature = "astroport")]
+use crate::helpers::find_inner_vaults;
+use crate::helpers::{
+    assert_recipient_code_matches, assert_staked_delivery_supported, check_destination_pool,
+    load_staker, projected_reward_index, query_asset_info, query_pending_unlocks,
+    reward_token_asset_info, seed_total_stake,
+};
+use crate::reply::{
+    self, GENERIC_VAULT_TOKENS_REDEEMED_REPLY_ID, INNER_VAULT_TOKENS_REDEEMED_REPLY_ID,
+    LIQUIDITY_PROVIDED_REPLY_ID, LP_REDEEMED_REPLY_ID, TRANSFER_FAILED_REPLY_ID,
+    UNLOCK_STARTED_REPLY_ID, VAULT_TOKENS_REDEEMED_REPLY_ID,
+};
+use crate::{execute, migrate, sudo};
 
 pub const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
 pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[entry_point]
+#[cfg_attr(not(feature = "astroport"), allow(unused_mut))]
 pub fn instantiate(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     cw2::set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     cw_ownable::initialize_owner(deps.storage, deps.api, Some(&msg.owner))?;
+    if let Some(guardian) = &msg.guardian {
+        let guardian = deps.api.addr_validate(guardian)?;
+        ROLES.save(
+            deps.storage,
+            (&guardian, Role::Guardian.as_str()),
+            &Empty {},
+        )?;
+    }
 
+    let mut create_denom_msg = None;
     let reward_token = match msg.reward_token_info {
+        #[cfg(feature = "astroport")]
         RewardInfo::AstroportVault(astroport_vault) => {
             let reward_vault: VaultContract =
                 VaultContractUnchecked::new(&astroport_vault.vault_addr).check(deps.api)?;
 
-            // Validate reward vault base token as CW20 Astroport LP token
-            let reward_lp_token = deps
-                .api
-                .addr_validate(&reward_vault.query_vault_info(&deps.querier)?.base_token)
-                .map_err(|_| StdError::generic_err("Invalid base token of reward vault"))?;
+            // Walk the base tokens of nested vaults down to the LP token
+            let (inner_vaults, base_token) = find_inner_vaults(deps.as_ref(), &reward_vault)?;
 
-            // Query minter of LP token to get reward pool address
-            let minter_res: MinterResponse =
-                deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-                    contract_addr: reward_lp_token.to_string(),
-                    msg: to_json_binary(&Cw20QueryMsg::Minter {})?,
-                }))?;
-            let reward_pool_addr = deps.api.addr_validate(&minter_res.minter)?;
+            // Find the Astroport pair of the reward vault's base token, unless it is
+            // given explicitly
+            let reward_pool_addr = match astroport_vault.pool_addr {
+                Some(pool_addr) => deps.api.addr_validate(&pool_addr)?,
+                None => find_astroport_pair(deps.as_ref(), &base_token)?,
+            };
 
             // Query reward pool for pool info to create pool object
             let reward_pool = AstroportPool::new(
@@ -51,12 +81,20 @@ pub fn instantiate(
                 deps.api
                     .addr_validate(&astroport_vault.liquidity_manager_addr)?,
             )?;
+            save_native_lp_token(deps.branch(), &reward_pool.pair_addr)?;
+
+            // Validate reward vault base token as the LP token of the reward pool
+            if astroport::lp_token(deps.as_ref(), &reward_pool)?.to_string() != base_token {
+                return Err(StdError::generic_err("Invalid base token of reward vault").into());
+            }
 
             RewardType::Vault {
                 vault: reward_vault,
-                pool: reward_pool,
+                inner_vaults,
+                pool: Pool::Astroport(reward_pool),
             }
         }
+        #[cfg(feature = "astroport")]
         RewardInfo::AstroportPool(astroport_pool) => {
             let reward_pool = AstroportPool::new(
                 deps.as_ref(),
@@ -64,10 +102,45 @@ pub fn instantiate(
                 deps.api
                     .addr_validate(&astroport_pool.liquidity_manager_addr)?,
             )?;
+            save_native_lp_token(deps.branch(), &reward_pool.pair_addr)?;
+
+            RewardType::LP(Pool::Astroport(reward_pool))
+        }
+        RewardInfo::LpToken(lp_token) => {
+            let liquidity_manager = lp_token
+                .liquidity_manager_addr
+                .map(|addr| deps.api.addr_validate(&addr))
+                .transpose()?;
+            let reward_pool = Pool::get_pool_for_lp_token(
+                deps.as_ref(),
+                &lp_token.lp_token.check(deps.api)?,
+                liquidity_manager,
+            )?;
 
             RewardType::LP(reward_pool)
         }
         RewardInfo::NativeCoin(reward_coin_denom) => RewardType::Coin(reward_coin_denom),
+        RewardInfo::GenericVault(vault_addr) => {
+            let vault: VaultContract = VaultContractUnchecked::new(&vault_addr).check(deps.api)?;
+            let base_token = vault.query_vault_info(&deps.querier)?.base_token;
+
+            RewardType::GenericVault {
+                base_token: query_asset_info(deps.as_ref(), &base_token),
+                vault,
+            }
+        }
+        RewardInfo::Mint(mint_reward) => {
+            // The contract creates the denom, which makes it the denom admin that is
+            // allowed to mint. The creation fee is paid from the funds sent.
+            create_denom_msg = Some(MsgCreateDenom {
+                sender: env.contract.address.to_string(),
+                subdenom: mint_reward.subdenom.clone(),
+            });
+            RewardType::Mint {
+                denom: format!("factory/{}/{}", env.contract.address, mint_reward.subdenom),
+                max_supply: mint_reward.max_supply,
+            }
+        }
     };
 
     // Create config
@@ -75,14 +148,39 @@ pub fn instantiate(
         distribution_addr: msg.distribution_addr,
         emission_per_second: msg.emission_per_second,
         rewards_start_time: msg.rewards_start_time,
+        config_update_delay: msg.config_update_delay,
+        instant_rate_decreases: msg.instant_rate_decreases,
+        min_emission_per_second: msg.min_emission_per_second,
+        max_emission_per_second: msg.max_emission_per_second,
+        max_rate_change: msg.max_rate_change,
+        rate_change_window: msg.rate_change_window,
+        delivery: msg.delivery,
+        distribution_mode: msg.distribution_mode,
+        fee_bps: msg.fee_bps,
+        fee_recipient: msg.fee_recipient,
+        recipient_code_id: msg.recipient_code_id,
+        staking_addr: msg.staking_addr,
     }
     .check(deps.api)?;
+    assert_recipient_code_matches(deps.as_ref(), &config)?;
+    assert_staked_delivery_supported(deps.as_ref(), &config, &reward_token)?;
+    if config.distribution_mode == DistributionMode::Staked {
+        seed_total_stake(deps.branch(), &config)?;
+    }
 
     CONFIG.save(deps.storage, &config)?;
     LAST_DISTRIBUTED.save(deps.storage, &env.block.time.seconds())?;
+    PENDING_REWARDS.save(deps.storage, &Uint128::zero())?;
+    PENDING_SINCE.save(deps.storage, &env.block.time.seconds())?;
+    PAUSED.save(deps.storage, &false)?;
     REWARD_TOKEN.save(deps.storage, &reward_token)?;
+    if let Some(destination_pool) = msg.destination_pool {
+        let destination_pool =
+            check_destination_pool(deps.as_ref(), &reward_token, destination_pool)?;
+        DESTINATION_POOL.save(deps.storage, &destination_pool)?;
+    }
 
-    Ok(Response::default())
+    Ok(Response::default().add_messages(create_denom_msg))
 }
 
 #[entry_point]
@@ -98,29 +196,104 @@ pub fn execute(
             Ok(Response::default().add_attributes(ownership.into_attributes()))
         }
         ExecuteMsg::Distribute {} => execute::execute_distribute(deps, env),
-        ExecuteMsg::UpdateConfig { updates } => {
-            execute::execute_update_config(deps, env, info, updates)
+        ExecuteMsg::Claim {} => execute::execute_claim(deps, env, info),
+        ExecuteMsg::AcceptRecipient {} => execute::execute_accept_recipient(deps, env, info),
+        ExecuteMsg::RetryDelivery {} => execute::execute_retry_delivery(deps),
+        ExecuteMsg::ClaimEscrow {} => execute::execute_claim_escrow(deps, info),
+        ExecuteMsg::ClaimRewards {} => execute::execute_claim_rewards(deps, env, info),
+        ExecuteMsg::StakeChangeHook(msg) => {
+            execute::execute_stake_change_hook(deps, env, info, msg)
         }
-        ExecuteMsg::Internal(msg) => {
-            // Internal messages can only be called by the contract itself
-            if info.sender != env.contract.address {
-                return Err(ContractError::Unauthorized {});
-            }
+        ExecuteMsg::RegisterMerkleAirdrop {
+            merkle_root,
+            total_amount,
+        } => execute::execute_register_merkle_airdrop(deps, env, info, merkle_root, total_amount),
+        ExecuteMsg::ClaimMerkleAirdrop {
+            airdrop_id,
+            index,
+            amount,
+            proof,
+        } => {
+            execute::execute_claim_merkle_airdrop(deps, env, info, airdrop_id, index, amount, proof)
+        }
+        ExecuteMsg::SetVestingSchedule {
+            recipient,
+            schedule,
+        } => execute::execute_set_vesting_schedule(deps, info, recipient, schedule),
+        ExecuteMsg::WithdrawVested { limit } => {
+            execute::execute_withdraw_vested(deps, env, info, limit)
+        }
+        ExecuteMsg::UpdateConfig {
+            updates,
+            ramp_duration,
+        } => execute::execute_update_config(deps, env, info, updates, ramp_duration),
+        ExecuteMsg::ProposeConfigUpdate {
+            updates,
+            ramp_duration,
+        } => execute::execute_propose_config_update(deps, env, info, updates, ramp_duration),
+        ExecuteMsg::ApplyConfigUpdate {} => execute::execute_apply_config_update(deps, env, info),
+        ExecuteMsg::CancelConfigUpdate {} => execute::execute_cancel_config_update(deps, info),
+        ExecuteMsg::GrantRole { role, address } => {
+            execute::execute_grant_role(deps, info, role, address)
+        }
+        ExecuteMsg::RevokeRole { role, address } => {
+            execute::execute_revoke_role(deps, info, role, address)
+        }
+        ExecuteMsg::Pause {} => execute::execute_pause(deps, info),
+        ExecuteMsg::Unpause {} => execute::execute_unpause(deps, info),
+        ExecuteMsg::SetDestinationPool { destination_pool } => {
+            execute::execute_set_destination_pool(deps, info, destination_pool)
+        }
+        ExecuteMsg::EmergencyWithdraw { recipient } => {
+            execute::execute_emergency_withdraw(deps, env, info, recipient)
+        }
+    }
+}
 
-            match msg {
-                InternalMsg::VaultTokensRedeemed {} => {
-                    execute::execute_internal_vault_tokens_redeemed(deps.as_ref(), env)
-                }
-                InternalMsg::LpRedeemed {} => {
-                    execute::execute_internal_lp_redeemed(deps.as_ref(), env)
-                }
-            }
+#[entry_point]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        VAULT_TOKENS_REDEEMED_REPLY_ID => reply::reply_vault_tokens_redeemed(deps, env),
+        LP_REDEEMED_REPLY_ID => reply::reply_lp_redeemed(deps, env),
+        UNLOCK_STARTED_REPLY_ID => reply::reply_unlock_started(deps, msg),
+        LIQUIDITY_PROVIDED_REPLY_ID => reply::reply_liquidity_provided(deps, env),
+        GENERIC_VAULT_TOKENS_REDEEMED_REPLY_ID => {
+            reply::reply_generic_vault_tokens_redeemed(deps, env)
+        }
+        id if id >= TRANSFER_FAILED_REPLY_ID => {
+            let index = (id - TRANSFER_FAILED_REPLY_ID) as usize;
+            reply::reply_transfer_failed(deps, index, msg)
         }
+        id if id >= INNER_VAULT_TOKENS_REDEEMED_REPLY_ID => {
+            reply::reply_inner_vault_tokens_redeemed(deps, env, id)
+        }
+        id => Err(ContractError::UnknownReplyId { id }),
     }
 }
 
 #[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::UpdateConfig {
+            updates,
+            ramp_duration,
+        } => sudo::sudo_update_config(deps, env, updates, ramp_duration),
+        SudoMsg::TransferOwnership { new_owner } => sudo::sudo_transfer_ownership(deps, new_owner),
+        SudoMsg::Pause {} => sudo::sudo_pause(deps),
+        SudoMsg::Unpause {} => sudo::sudo_unpause(deps),
+        SudoMsg::EmergencyWithdraw { recipient } => {
+            sudo::sudo_emergency_withdraw(deps, env, recipient)
+        }
+    }
+}
+
+#[entry_point]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    migrate::migrate_state(deps)
+}
+
+#[entry_point]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Ownership {} => {
             let ownership = cw_ownable::get_ownership(deps.storage)?;
@@ -129,12 +302,96 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::State {} => {
             let config = CONFIG.load(deps.storage)?;
             let last_distributed = LAST_DISTRIBUTED.load(deps.storage)?;
+            let reward_token = REWARD_TOKEN.load(deps.storage)?;
 
             to_json_binary(&StateResponse {
                 config,
-                reward_token: REWARD_TOKEN.load(deps.storage)?,
+                reward_asset: reward_token_asset_info(deps, &reward_token)?,
+                reward_token,
                 last_distributed,
+                emission_ramp: EMISSION_RAMP.may_load(deps.storage)?,
+                pending_rewards: PENDING_REWARDS.load(deps.storage)?,
+                paused: PAUSED.load(deps.storage)?,
+                destination_pool: DESTINATION_POOL.may_load(deps.storage)?,
+                fees_collected: FEES_COLLECTED.may_load(deps.storage)?.unwrap_or_default(),
+                pending_recipient: PENDING_RECIPIENT.may_load(deps.storage)?,
+                reward_index: REWARD_INDEX.may_load(deps.storage)?.unwrap_or_default(),
+                total_stake: TOTAL_STAKE.may_load(deps.storage)?.unwrap_or_default(),
+            })
+        }
+        QueryMsg::PendingConfigUpdate {} => {
+            to_json_binary(&PENDING_CONFIG_UPDATE.may_load(deps.storage)?)
+        }
+        QueryMsg::Roles { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            let roles: Vec<Role> = Role::ALL
+                .into_iter()
+                .filter(|role| ROLES.has(deps.storage, (&address, role.as_str())))
+                .collect();
+            to_json_binary(&roles)
+        }
+        QueryMsg::PendingUnlocks { start_after, limit } => match REWARD_TOKEN.load(deps.storage)? {
+            RewardType::Vault { vault, .. } => to_json_binary(&query_pending_unlocks(
+                deps,
+                &env,
+                &vault,
+                start_after,
+                limit,
+            )?),
+            _ => to_json_binary(&Vec::<UnlockingPosition>::new()),
+        },
+        QueryMsg::UnclaimedRewards { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            to_json_binary(&UNCLAIMED_REWARDS.may_load(deps.storage, &address)?)
+        }
+        QueryMsg::Escrow { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            to_json_binary(&ESCROW.may_load(deps.storage, &address)?)
+        }
+        QueryMsg::StakerRewards { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            let config = CONFIG.load(deps.storage)?;
+            let current_time = env.block.time.seconds();
+            let reward_index = projected_reward_index(deps, &config, current_time)
+                .map_err(|e| StdError::generic_err(e.to_string()))?;
+            let mut staker_info = load_staker(deps, &config, &address, None, current_time)?;
+            staker_info.accrue(reward_index)?;
+            to_json_binary(&StakerRewardsResponse {
+                stake: staker_info.stake,
+                claimable: staker_info.accrued.to_uint_floor().try_into()?,
             })
         }
+        QueryMsg::MerkleAirdrop { airdrop_id } => {
+            to_json_binary(&MERKLE_AIRDROPS.may_load(deps.storage, airdrop_id)?)
+        }
+        QueryMsg::IsLeafClaimed { airdrop_id, index } => {
+            let claimed_leaves = CLAIMED_LEAVES
+                .may_load(deps.storage, (airdrop_id, index / 64))?
+                .unwrap_or_default();
+            to_json_binary(&(claimed_leaves & (1u64 << (index % 64)) != 0))
+        }
+        QueryMsg::VestingSchedule { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            to_json_binary(&VESTING_SCHEDULES.may_load(deps.storage, &address)?)
+        }
+        QueryMsg::VestingPositions {
+            address,
+            start_after,
+            limit,
+        } => {
+            let address = deps.api.addr_validate(&address)?;
+            let positions = VESTING_POSITIONS
+                .prefix(&address)
+                .range(
+                    deps.storage,
+                    start_after.map(Bound::exclusive),
+                    None,
+                    Order::Ascending,
+                )
+                .take(limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize)
+                .map(|item| item.map(|(_, position)| position))
+                .collect::<StdResult<Vec<_>>>()?;
+            to_json_binary(&positions)
+        }
     }
 }
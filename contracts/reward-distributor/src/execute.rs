@@ -1,14 +1,51 @@
 use apollo_cw_asset::{Asset, AssetInfo, AssetList};
 use cosmwasm_std::{
-    coins, BankMsg, CosmosMsg, Deps, DepsMut, Env, Event, MessageInfo, Response, Uint128,
+    coins, to_json_binary, Decimal, Decimal256, DepsMut, Empty, Env, Event, MessageInfo, Order,
+    Response, StdResult, SubMsg, Uint128,
 };
 use cw_dex::traits::Pool as PoolTrait;
+use cw_vault_standard::extensions::lockup::LockupExecuteMsg;
 use neutron_astroport_reward_distributor::{
-    ConfigUpdates, ContractError, InternalMsg, RewardType, CONFIG, LAST_DISTRIBUTED, REWARD_TOKEN,
+    Config, ConfigUpdates, ContractError, Delivery, DeliveryInProgress, DistributionMode,
+    EmissionRamp, MerkleAirdrop, PendingConfigUpdate, RateChangeCheckpoint, RewardType, Role,
+    UnclaimedRewards, VestingSchedule, CLAIMED_LEAVES, CONFIG, DELIVERY_IN_PROGRESS,
+    DESTINATION_POOL, EMISSION_RAMP, EMISSION_REMAINDER, ESCROW, LAST_DISTRIBUTED, MERKLE_AIRDROPS,
+    PARTIALLY_CLAIMED_LEAVES, PAUSED, PENDING_CONFIG_UPDATE, PENDING_RECIPIENT, PENDING_REWARDS,
+    PENDING_UNLOCKS, RATE_CHANGE_HISTORY, REWARD_INDEX, REWARD_TOKEN, ROLES, STAKERS,
+    STAKER_REWARDS, TOTAL_STAKE, UNCLAIMED_REWARDS, VESTING_POSITIONS, VESTING_SCHEDULES,
 };
+use sha2::{Digest, Sha256};
+
+use neutron_astroport_reward_distributor::msg::{DestinationPoolInfo, StakeChangedHookMsg};
+
+use crate::helpers::{
+    accrue_rewards, assert_can_update_config, assert_no_unlock_needed, assert_owner_or_role,
+    assert_recipient_code_matches, assert_spendable_balance, assert_staked_delivery_supported,
+    assert_vault_token_balance, check_destination_pool, collect_fees, decode_merkle_hash,
+    deduct_pending_rewards, delivery_msgs, escrowed_transfer_msgs, has_pending_unlocks,
+    is_locked_vault, load_staker, lp_token, mint_msg, mintable_amount, pending_since,
+    query_asset_info, recipient_code_matches, redeem_vault_tokens, redeemed_token, release_assets,
+    reply_on_last_msg, reset_pending_rewards, reward_token_asset_info, save_balance_snapshot,
+    seed_total_stake, spendable_balance, sync_staker, update_reward_index, verify_merkle_proof,
+    withdraw_liquidity, withdraw_matured_unlocks,
+};
+use crate::reply::{
+    GENERIC_VAULT_TOKENS_REDEEMED_REPLY_ID, LP_REDEEMED_REPLY_ID, UNLOCK_STARTED_REPLY_ID,
+};
+
+/// The number of vesting positions withdrawn from by `WithdrawVested` or
+/// returned by a `VestingPositions` query if no limit is given
+pub const DEFAULT_LIMIT: u32 = 10;
+
+/// The maximum number of vesting positions withdrawn from by `WithdrawVested`
+/// or returned by a `VestingPositions` query
+pub const MAX_LIMIT: u32 = 30;
+
+pub fn execute_distribute(mut deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    if PAUSED.load(deps.storage)? {
+        return Err(ContractError::Paused {});
+    }
 
-pub fn execute_distribute(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
-    let last_distributed = LAST_DISTRIBUTED.load(deps.storage)?;
     let config = CONFIG.load(deps.storage)?;
     let current_time = env.block.time.seconds();
 
@@ -17,158 +54,1231 @@ pub fn execute_distribute(deps: DepsMut, env: Env) -> Result<Response, ContractE
         return Ok(Response::new());
     }
 
-    // Only distribute once per second
-    if current_time == last_distributed {
-        return Ok(Response::new());
+    // In staked mode the rewards are shared between the stakers, who claim them
+    // individually
+    if config.distribution_mode == DistributionMode::Staked {
+        let reward_index = update_reward_index(deps.branch(), &config, current_time)?;
+        let event = Event::new("apollo/neutron-astroport-reward-distributor/execute_distribute")
+            .add_attribute("reward_index", reward_index.to_string())
+            .add_attribute(
+                "total_stake",
+                TOTAL_STAKE.may_load(deps.storage)?.unwrap_or_default(),
+            );
+        return Ok(Response::new().add_event(event));
     }
 
     // Calculate amount of rewards to be distributed
-    let time_elapsed = current_time.saturating_sub(last_distributed.max(config.rewards_start_time));
-    let reward_amount = config.emission_per_second * Uint128::from(time_elapsed);
+    let period_start = pending_since(deps.storage, &config)?;
+    let reward_amount = accrue_rewards(deps.branch(), &config, current_time)?;
+
+    // In pull mode the rewards are only accrued, and delivered once claimed
+    if config.distribution_mode == DistributionMode::Pull {
+        let event = Event::new("apollo/neutron-astroport-reward-distributor/execute_distribute")
+            .add_attribute("rewards_accrued", reward_amount);
+        return Ok(Response::new().add_event(event));
+    }
+
+    let delivery = DeliveryInProgress {
+        recipient: config.distribution_addr.clone(),
+        period_start,
+        period_end: current_time,
+        reward_amount,
+        fee_amount: Uint128::zero(),
+    };
+    let (res, delivered) =
+        deliver_rewards(deps.branch(), &env, &config, delivery, Uint128::zero())?;
+    let Some(reward_amount) = delivered else {
+        return Ok(res);
+    };
+
+    deduct_pending_rewards(deps.storage, reward_amount, current_time)?;
+
+    let event = Event::new("apollo/neutron-astroport-reward-distributor/execute_distribute")
+        .add_attribute("reward_amount", reward_amount)
+        .add_attribute("fee_amount", config.fee_amount(reward_amount))
+        .add_attribute("period_start", period_start.to_string())
+        .add_attribute("period_end", current_time.to_string())
+        .add_attribute("delivery", config.delivery.as_str());
+
+    Ok(res.add_event(event))
+}
+
+pub fn execute_claim(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    if PAUSED.load(deps.storage)? {
+        return Err(ContractError::Paused {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let current_time = env.block.time.seconds();
+
+    // The current recipient claims the rewards accrued so far, as well as any
+    // rewards left unclaimed from an earlier period as recipient. Previous
+    // recipients can only claim their unclaimed rewards and the unlocks started
+    // for them.
+    let is_recipient = config.distribution_mode == DistributionMode::Pull
+        && info.sender == config.distribution_addr;
+    let unclaimed = UNCLAIMED_REWARDS.may_load(deps.storage, &info.sender)?;
+    if !is_recipient && unclaimed.is_none() && !has_pending_unlocks(deps.storage, &info.sender)? {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut delivery = DeliveryInProgress {
+        recipient: info.sender.clone(),
+        period_start: current_time,
+        period_end: current_time,
+        reward_amount: Uint128::zero(),
+        fee_amount: Uint128::zero(),
+    };
+    let mut accrued = Uint128::zero();
+    if is_recipient && current_time >= config.rewards_start_time {
+        delivery.period_start = pending_since(deps.storage, &config)?;
+        accrued = accrue_rewards(deps.branch(), &config, current_time)?;
+        delivery.reward_amount = accrued;
+    }
+    if let Some(unclaimed) = &unclaimed {
+        delivery.period_start = delivery.period_start.min(unclaimed.period_start);
+        if !is_recipient {
+            delivery.period_end = unclaimed.period_end;
+        }
+        delivery.reward_amount += unclaimed.amount;
+    }
+    let (period_start, period_end) = (delivery.period_start, delivery.period_end);
+    let reserved_for_recipient = unclaimed
+        .as_ref()
+        .map_or(Uint128::zero(), |unclaimed| unclaimed.amount);
+
+    let (res, delivered) = deliver_rewards(
+        deps.branch(),
+        &env,
+        &config,
+        delivery,
+        reserved_for_recipient,
+    )?;
+    let Some(reward_amount) = delivered else {
+        return Ok(res);
+    };
+
+    // The accrued rewards are delivered first. Whatever could not be delivered
+    // stays pending or unclaimed.
+    let from_accrued = reward_amount.min(accrued);
+    if is_recipient {
+        deduct_pending_rewards(deps.storage, from_accrued, current_time)?;
+    }
+    if let Some(mut unclaimed) = unclaimed {
+        unclaimed.amount -= reward_amount - from_accrued;
+        if unclaimed.amount.is_zero() {
+            UNCLAIMED_REWARDS.remove(deps.storage, &info.sender);
+        } else {
+            UNCLAIMED_REWARDS.save(deps.storage, &info.sender, &unclaimed)?;
+        }
+    }
+
+    let event = Event::new("apollo/neutron-astroport-reward-distributor/execute_claim")
+        .add_attribute("recipient", info.sender)
+        .add_attribute("reward_amount", reward_amount)
+        .add_attribute("fee_amount", config.fee_amount(reward_amount))
+        .add_attribute("period_start", period_start.to_string())
+        .add_attribute("period_end", period_end.to_string())
+        .add_attribute("delivery", config.delivery.as_str());
 
+    Ok(res.add_event(event))
+}
+
+pub fn execute_claim_rewards(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    if PAUSED.load(deps.storage)? {
+        return Err(ContractError::Paused {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let current_time = env.block.time.seconds();
+
+    assert_no_unlock_needed(deps.as_ref(), &config)?;
+
+    // Addresses without stake or accrued rewards have nothing to claim, and are
+    // not tracked as stakers
+    let staker_info = load_staker(deps.as_ref(), &config, &info.sender, None, current_time)?;
+    if staker_info.stake.is_zero() && staker_info.accrued.is_zero() {
+        return Ok(Response::default());
+    }
+
+    // Rewards accrued before leaving staked mode can still be claimed
+    let reward_index = match config.distribution_mode {
+        DistributionMode::Staked => update_reward_index(deps.branch(), &config, current_time)?,
+        _ => REWARD_INDEX.may_load(deps.storage)?.unwrap_or_default(),
+    };
+    let mut staker_info = sync_staker(
+        deps.branch(),
+        &config,
+        &info.sender,
+        None,
+        reward_index,
+        current_time,
+    )?;
+
+    // Only whole reward tokens are claimed, fractions stay accrued
+    let reward_amount = Uint128::try_from(staker_info.accrued.to_uint_floor())
+        .map_err(|_| ContractError::EmissionOverflow {})?;
+    let delivery = DeliveryInProgress {
+        recipient: info.sender.clone(),
+        period_start: staker_info.accruing_since,
+        period_end: current_time,
+        reward_amount,
+        fee_amount: Uint128::zero(),
+    };
+    let (res, delivered) = deliver_rewards(deps.branch(), &env, &config, delivery, reward_amount)?;
+    let Some(reward_amount) = delivered else {
+        return Ok(res);
+    };
+
+    let event = Event::new("apollo/neutron-astroport-reward-distributor/execute_claim_rewards")
+        .add_attribute("recipient", info.sender.clone())
+        .add_attribute("reward_amount", reward_amount)
+        .add_attribute("fee_amount", config.fee_amount(reward_amount))
+        .add_attribute("period_start", staker_info.accruing_since.to_string())
+        .add_attribute("period_end", current_time.to_string())
+        .add_attribute("delivery", config.delivery.as_str());
+
+    staker_info.accrued -= Decimal256::from_ratio(reward_amount, 1u8);
+    staker_info.accruing_since = current_time;
+    STAKERS.save(deps.storage, &info.sender, &staker_info)?;
+    let staker_rewards = STAKER_REWARDS.may_load(deps.storage)?.unwrap_or_default();
+    STAKER_REWARDS.save(deps.storage, &staker_rewards.saturating_sub(reward_amount))?;
+
+    Ok(res.add_event(event))
+}
+
+pub fn execute_stake_change_hook(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: StakeChangedHookMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.staking_addr.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // The rewards emitted up to the stake change are shared according to the
+    // stakes before it. This must not fail while paused, or staking would be
+    // blocked.
+    let current_time = env.block.time.seconds();
+    let staker = deps.api.addr_validate(msg.staker())?;
+    let reward_index = match config.distribution_mode {
+        DistributionMode::Staked => update_reward_index(deps.branch(), &config, current_time)?,
+        _ => REWARD_INDEX.may_load(deps.storage)?.unwrap_or_default(),
+    };
+    let staker_info = sync_staker(
+        deps.branch(),
+        &config,
+        &staker,
+        Some(&msg),
+        reward_index,
+        current_time,
+    )?;
+
+    let event = Event::new("apollo/neutron-astroport-reward-distributor/execute_stake_change_hook")
+        .add_attribute("staker", staker)
+        .add_attribute("stake", staker_info.stake)
+        .add_attribute("reward_index", reward_index.to_string());
+
+    Ok(Response::new().add_event(event))
+}
+
+pub fn execute_register_merkle_airdrop(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    merkle_root: String,
+    total_amount: Uint128,
+) -> Result<Response, ContractError> {
+    // only owner can send this message
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    decode_merkle_hash(&merkle_root)?;
+
+    // The airdrop must be funded with reward tokens not owed to anyone else,
+    // unless they are minted when claimed
     let reward_token = REWARD_TOKEN.load(deps.storage)?;
+    if !matches!(reward_token, RewardType::Mint { .. }) {
+        let reward_token_info = reward_token_asset_info(deps.as_ref(), &reward_token)?;
+        assert_spendable_balance(
+            deps.as_ref(),
+            &env,
+            &reward_token_info,
+            total_amount,
+            Uint128::zero(),
+        )?;
+    }
+    let airdrop_id = MERKLE_AIRDROPS
+        .keys(deps.storage, None, None, Order::Descending)
+        .next()
+        .transpose()?
+        .map_or(1, |last_id| last_id + 1);
+    MERKLE_AIRDROPS.save(
+        deps.storage,
+        airdrop_id,
+        &MerkleAirdrop {
+            merkle_root: merkle_root.clone(),
+            total_amount,
+            claimed_amount: Uint128::zero(),
+        },
+    )?;
+
+    let event =
+        Event::new("apollo/neutron-astroport-reward-distributor/execute_register_merkle_airdrop")
+            .add_attribute("airdrop_id", airdrop_id.to_string())
+            .add_attribute("merkle_root", merkle_root)
+            .add_attribute("total_amount", total_amount);
+
+    Ok(Response::new().add_event(event))
+}
+
+pub fn execute_claim_merkle_airdrop(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    airdrop_id: u64,
+    index: u64,
+    amount: Uint128,
+    proof: Vec<String>,
+) -> Result<Response, ContractError> {
+    if PAUSED.load(deps.storage)? {
+        return Err(ContractError::Paused {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let current_time = env.block.time.seconds();
+    assert_no_unlock_needed(deps.as_ref(), &config)?;
+
+    let mut airdrop = MERKLE_AIRDROPS.load(deps.storage, airdrop_id)?;
+    let (word_index, bit) = (index / 64, 1u64 << (index % 64));
+    let claimed_leaves = CLAIMED_LEAVES
+        .may_load(deps.storage, (airdrop_id, word_index))?
+        .unwrap_or_default();
+    if claimed_leaves & bit != 0 {
+        return Err(ContractError::LeafAlreadyClaimed { airdrop_id, index });
+    }
+
+    let leaf = Sha256::digest(format!("{index}:{}:{amount}", info.sender)).into();
+    verify_merkle_proof(&airdrop.merkle_root, leaf, &proof)?;
+
+    // A leaf claimed in part can be claimed again for the rest of its amount
+    let partially_claimed = PARTIALLY_CLAIMED_LEAVES
+        .may_load(deps.storage, (airdrop_id, index))?
+        .unwrap_or_default();
+    let claim_amount = amount.saturating_sub(partially_claimed);
+    airdrop
+        .claimed_amount
+        .checked_add(claim_amount)
+        .ok()
+        .filter(|claimed_amount| *claimed_amount <= airdrop.total_amount)
+        .ok_or(ContractError::AirdropExhausted { airdrop_id })?;
+
+    let delivery = DeliveryInProgress {
+        recipient: info.sender.clone(),
+        period_start: current_time,
+        period_end: current_time,
+        reward_amount: claim_amount,
+        fee_amount: Uint128::zero(),
+    };
+    let (res, delivered) = deliver_rewards(deps.branch(), &env, &config, delivery, claim_amount)?;
+    let Some(reward_amount) = delivered else {
+        return Ok(res);
+    };
+
+    airdrop.claimed_amount += reward_amount;
+    MERKLE_AIRDROPS.save(deps.storage, airdrop_id, &airdrop)?;
+    if reward_amount < claim_amount {
+        PARTIALLY_CLAIMED_LEAVES.save(
+            deps.storage,
+            (airdrop_id, index),
+            &(partially_claimed + reward_amount),
+        )?;
+    } else {
+        PARTIALLY_CLAIMED_LEAVES.remove(deps.storage, (airdrop_id, index));
+        CLAIMED_LEAVES.save(
+            deps.storage,
+            (airdrop_id, word_index),
+            &(claimed_leaves | bit),
+        )?;
+    }
+
+    let event =
+        Event::new("apollo/neutron-astroport-reward-distributor/execute_claim_merkle_airdrop")
+            .add_attribute("recipient", info.sender)
+            .add_attribute("airdrop_id", airdrop_id.to_string())
+            .add_attribute("index", index.to_string())
+            .add_attribute("reward_amount", reward_amount)
+            .add_attribute("fee_amount", config.fee_amount(reward_amount))
+            .add_attribute("delivery", config.delivery.as_str());
+
+    Ok(res.add_event(event))
+}
+
+/// Returns a response with the messages to deliver the given amount of reward
+/// tokens to the recipient, and the amount delivered if any rewards were.
+/// Rewards that would not return any underlying assets are not delivered, and
+/// stay pending until the next delivery. `reserved_for_recipient` is the amount
+/// of the reward tokens owed to the recipient, such as its unclaimed rewards,
+/// that the delivery may spend. Reward tokens owed to others are not spent.
+fn deliver_rewards(
+    mut deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    mut delivery: DeliveryInProgress,
+    reserved_for_recipient: Uint128,
+) -> Result<(Response, Option<Uint128>), ContractError> {
+    let reward_token = REWARD_TOKEN.load(deps.storage)?;
+
+    // Minted rewards are capped by the max supply of the denom. Callers leave
+    // the rewards above the cap pending.
+    if let RewardType::Mint { denom, max_supply } = &reward_token {
+        delivery.reward_amount =
+            mintable_amount(deps.as_ref(), denom, *max_supply, delivery.reward_amount)?;
+    }
+
+    // Reward tokens delivered as is pay the fee in reward tokens, and only the
+    // rest is delivered to the recipient. Rewards delivered as their underlying
+    // assets are redeemed in full, and pay the fee from the redeemed assets in
+    // the final reply.
+    let reward_amount = delivery.reward_amount;
+    delivery.fee_amount = config.fee_amount(reward_amount);
+    let net_amount = reward_amount - delivery.fee_amount;
+    let fee = match &config.fee_recipient {
+        Some(fee_recipient) if !delivery.fee_amount.is_zero() => Some((
+            fee_recipient.clone(),
+            AssetList::from(vec![Asset::new(
+                reward_token_asset_info(deps.as_ref(), &reward_token)?,
+                delivery.fee_amount,
+            )]),
+        )),
+        _ => None,
+    };
+    let recipient = &delivery.recipient;
+
+    // Pause instead of delivering to a recipient that has been migrated away
+    // from the pinned code. The rewards stay pending until the owner updates
+    // the pin and unpauses.
+    if !recipient_code_matches(deps.as_ref(), config, recipient) {
+        PAUSED.save(deps.storage, &true)?;
+        let event =
+            Event::new("apollo/neutron-astroport-reward-distributor/recipient_code_mismatch")
+                .add_attribute("recipient", recipient.to_string());
+        return Ok((Response::new().add_event(event), None));
+    }
+
+    // Unlocks of a locked reward vault must be withdrawn once matured, even if
+    // there are no new rewards to distribute
+    if reward_amount.is_zero() && !matches!(reward_token, RewardType::Vault { .. }) {
+        return Ok((Response::new(), None));
+    }
 
+    // Assets received in replies are sent to the recipient by the reply handlers
+    DELIVERY_IN_PROGRESS.save(deps.storage, &delivery)?;
+
+    let raw_delivery = config.delivery == Delivery::Raw;
+
+    // Assets that are sent directly are returned in the response data, while
+    // assets received in a reply are returned by the final reply instead
+    let mut delivered = AssetList::new();
     let mut res = Response::new();
+    let mut mint = None;
 
     match reward_token {
-        RewardType::Vault { vault, pool: _ } => {
+        RewardType::Vault { vault, .. } | RewardType::GenericVault { vault, .. }
+            if raw_delivery =>
+        {
+            // Send the vault tokens as is, without redeeming or unlocking them
+            if reward_amount.is_zero() {
+                return Ok((Response::new(), None));
+            }
+            let vault_token = vault.query_vault_info(&deps.querier)?.vault_token;
+            let vault_token = query_asset_info(deps.as_ref(), &vault_token);
+            assert_vault_token_balance(
+                deps.as_ref(),
+                env,
+                &vault_token,
+                reward_amount,
+                reserved_for_recipient,
+            )?;
+            delivered.add(&Asset::new(vault_token, net_amount))?;
+        }
+        RewardType::Vault {
+            vault,
+            inner_vaults,
+            pool,
+        } => {
+            // Base tokens are received both from withdrawing matured unlocks and from
+            // redeeming vault tokens, and are handled together in the reply. Only the
+            // unlocks started for the recipient are withdrawn.
+            let mut base_token_msgs =
+                withdraw_matured_unlocks(deps.branch(), env, &vault, recipient)?;
+
             // Query the vault to see how many base tokens would be returned after
-            // redeeming. If zero we return Ok, so that update_config does not fail when
-            // trying to distribute.
-            let base_token_amount = vault.query_convert_to_assets(&deps.querier, reward_amount)?;
-            if base_token_amount.is_zero() {
-                return Ok(Response::new());
+            // redeeming. If zero we don't redeem, so that update_config does not fail
+            // when trying to distribute. The rewards stay pending until the next
+            // distribution.
+            let distribute_rewards = !reward_amount.is_zero()
+                && !vault
+                    .query_convert_to_assets(&deps.querier, reward_amount)?
+                    .is_zero();
+            let mut unlock_msg = None;
+            if distribute_rewards {
+                let vault_token_denom = vault.query_vault_info(&deps.querier)?.vault_token;
+                let vault_token = query_asset_info(deps.as_ref(), &vault_token_denom);
+                assert_vault_token_balance(
+                    deps.as_ref(),
+                    env,
+                    &vault_token,
+                    reward_amount,
+                    reserved_for_recipient,
+                )?;
+
+                // Locked vaults only release the LP tokens once the unlock has matured,
+                // so start unlocking the rewards and withdraw them in a later
+                // distribution. Unlocked vaults can be redeemed from directly.
+                if is_locked_vault(deps.as_ref(), &vault)? {
+                    unlock_msg = Some(
+                        LockupExecuteMsg::Unlock {
+                            amount: reward_amount,
+                        }
+                        .into_cosmos_msg(
+                            vault.addr.to_string(),
+                            coins(reward_amount.u128(), vault_token_denom),
+                        )?,
+                    );
+                } else {
+                    let redeem_res = redeem_vault_tokens(&vault, &vault_token, reward_amount)?;
+                    base_token_msgs.extend(redeem_res.messages.into_iter().map(|msg| msg.msg));
+                }
             }
 
-            let vault_info = vault.query_vault_info(&deps.querier)?;
-
-            // Check contract's balance of vault tokens and error if not enough. This is
-            // just so we get a clearer error message rather than the confusing "cannot
-            // sub 0 with x".
-            let vault_token_balance = deps
-                .querier
-                .query_balance(&env.contract.address, &vault_info.vault_token)?;
-            if vault_token_balance.amount < reward_amount {
-                return Err(ContractError::InsufficientVaultTokenBalance {
-                    vault_token_balance: vault_token_balance.amount,
-                    redeem_amount: reward_amount,
-                });
+            if !base_token_msgs.is_empty() {
+                // Snapshot the base token balance so that only the base tokens received
+                // are handled in the reply. The base token is the vault token of the
+                // outermost inner vault, which is redeemed layer by layer in the
+                // replies, or the LP token, which is withdrawn from the pool.
+                let (base_token, reply_id) =
+                    redeemed_token(deps.as_ref(), &inner_vaults, &pool, 0)?;
+                save_balance_snapshot(deps.branch(), env, vec![base_token])?;
+                res = reply_on_last_msg(res.add_messages(base_token_msgs), reply_id)?;
+            }
+            if let Some(unlock_msg) = unlock_msg {
+                res = res.add_submessage(SubMsg::reply_on_success(
+                    unlock_msg,
+                    UNLOCK_STARTED_REPLY_ID,
+                ));
+            }
+            if !distribute_rewards {
+                return Ok((res, None));
+            }
+        }
+        RewardType::GenericVault { vault, base_token } => {
+            // Same as for the Astroport vault, rewards stay pending if redeeming would
+            // not return any base tokens
+            if vault
+                .query_convert_to_assets(&deps.querier, reward_amount)?
+                .is_zero()
+            {
+                return Ok((Response::new(), None));
             }
 
-            // Redeem rewards from the vault
-            let redeem_msg = vault.redeem(reward_amount, &vault_info.vault_token, None)?;
+            let vault_token = vault.query_vault_info(&deps.querier)?.vault_token;
+            let vault_token = query_asset_info(deps.as_ref(), &vault_token);
+            assert_vault_token_balance(
+                deps.as_ref(),
+                env,
+                &vault_token,
+                reward_amount,
+                reserved_for_recipient,
+            )?;
 
-            // Create internal callback msg
-            let callback_msg = InternalMsg::VaultTokensRedeemed {}.into_cosmos_msg(&env)?;
+            // Snapshot the base token balance so that only the base tokens received
+            // from the redemption are sent on in the reply
+            save_balance_snapshot(deps.branch(), env, vec![base_token])?;
 
-            res = res.add_message(redeem_msg).add_message(callback_msg);
+            res = redeem_vault_tokens(&vault, &vault_token, reward_amount)?;
+            res = reply_on_last_msg(res, GENERIC_VAULT_TOKENS_REDEEMED_REPLY_ID)?;
+        }
+        RewardType::LP(pool) if raw_delivery => {
+            // Send the LP tokens as is
+            let lp_token = lp_token(deps.as_ref(), &pool)?;
+            assert_spendable_balance(
+                deps.as_ref(),
+                env,
+                &lp_token,
+                reward_amount,
+                reserved_for_recipient,
+            )?;
+            delivered.add(&Asset::new(lp_token, net_amount))?;
         }
         RewardType::LP(pool) => {
-            // Create message to withdraw liquidity from pool
-            let lp_tokens = Asset::new(AssetInfo::Cw20(pool.lp_token_addr.clone()), reward_amount);
-            res = pool.withdraw_liquidity(deps.as_ref(), &env, lp_tokens, AssetList::new())?;
+            let lp_token = lp_token(deps.as_ref(), &pool)?;
+            assert_spendable_balance(
+                deps.as_ref(),
+                env,
+                &lp_token,
+                reward_amount,
+                reserved_for_recipient,
+            )?;
+
+            // Snapshot the pool asset balances so that only the assets received from
+            // withdrawing liquidity are sent on in the reply
+            let pool_assets = pool.pool_assets(deps.as_ref())?;
+            save_balance_snapshot(deps.branch(), env, pool_assets)?;
 
-            // Create internal callback msg
-            let callback_msg = InternalMsg::LpRedeemed {}.into_cosmos_msg(&env)?;
-            res = res.add_message(callback_msg);
+            // Create message to withdraw liquidity from pool
+            res = withdraw_liquidity(deps.as_ref(), env, &pool, reward_amount)?;
+            res = reply_on_last_msg(res, LP_REDEEMED_REPLY_ID)?;
         }
         RewardType::Coin(reward_coin_denom) => {
-            // Create message to send coins to distribution address
-            let send_msg: CosmosMsg = BankMsg::Send {
-                to_address: config.distribution_addr.to_string(),
-                amount: coins(reward_amount.u128(), reward_coin_denom),
-            }
-            .into();
-            res = res.add_message(send_msg);
+            // Send the coins to the recipient
+            let coins = AssetInfo::native(reward_coin_denom);
+            assert_spendable_balance(
+                deps.as_ref(),
+                env,
+                &coins,
+                reward_amount,
+                reserved_for_recipient,
+            )?;
+            delivered.add(&Asset::new(coins, net_amount))?;
+        }
+        RewardType::Mint { denom, .. } => {
+            // Mint the rewards, including the fee, to the contract and send them on
+            // like coin rewards
+            mint = Some(mint_msg(env, &denom, reward_amount));
+            let coins = Asset::native(denom, net_amount);
+            delivered.add(&coins)?;
         }
     }
 
-    // Set last distributed time to current time
-    LAST_DISTRIBUTED.save(deps.storage, &current_time)?;
+    // Assets delivered directly are sent along with the fee before any other
+    // messages, so that their transfers have been handled before replies send
+    // on further assets
+    if delivered.len() > 0 {
+        if let Some((_, fee)) = &fee {
+            collect_fees(deps.storage, fee)?;
+        }
+        let transfers = delivery_msgs(
+            deps.storage,
+            env.block.time.seconds(),
+            recipient,
+            &delivered,
+            fee.clone(),
+        )?;
+        res.messages = transfers.into_iter().chain(res.messages).collect();
+    }
 
-    let event = Event::new("apollo/neutron-astroport-reward-distributor/execute_distribute")
-        .add_attribute("vault_tokens_redeemed", reward_amount);
+    // Minted rewards must be minted before anything is sent
+    if let Some(mint) = mint {
+        res.messages.insert(0, SubMsg::new(mint));
+    }
 
-    Ok(res.add_event(event))
+    let data = to_json_binary(&delivery.into_response(delivered))?;
+    Ok((res.set_data(data), Some(reward_amount)))
 }
 
-pub fn execute_internal_vault_tokens_redeemed(
-    deps: Deps,
+pub fn execute_update_config(
+    deps: DepsMut,
     env: Env,
+    info: MessageInfo,
+    updates: ConfigUpdates,
+    ramp_duration: Option<u64>,
 ) -> Result<Response, ContractError> {
-    let reward_pool = REWARD_TOKEN.load(deps.storage)?.into_pool()?;
+    assert_can_update_config(deps.storage, &info.sender, &updates)?;
 
-    // Query lp token balance
-    let reward_lp_token = AssetInfo::Cw20(reward_pool.lp_token_addr.clone());
-    let lp_balance = reward_lp_token.query_balance(&deps.querier, env.contract.address.clone())?;
-    let lp_tokens = Asset::new(reward_lp_token, lp_balance);
+    let config = CONFIG.load(deps.storage)?;
+    if config.is_timelocked(&updates) {
+        return Err(ContractError::ConfigUpdateTimelocked {
+            delay: config.config_update_delay,
+        });
+    }
 
-    // Withdraw liquidity with all of contracts LP tokens
-    let withdraw_res = reward_pool.withdraw_liquidity(deps, &env, lp_tokens, AssetList::new())?;
+    apply_config_update(deps, env, updates, ramp_duration)
+}
 
-    // Create internal callback msg
-    let callback_msg = InternalMsg::LpRedeemed {}.into_cosmos_msg(&env)?;
+pub fn execute_propose_config_update(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    updates: ConfigUpdates,
+    ramp_duration: Option<u64>,
+) -> Result<Response, ContractError> {
+    assert_can_update_config(deps.storage, &info.sender, &updates)?;
 
-    let event = Event::new(
-        "apollo/neutron-astroport-reward-distributor/execute_internal_vault_tokens_redeemed",
-    )
-    .add_attribute("lp_tokens_redeemed", lp_balance);
+    // Validate the updates now, so that invalid proposals fail early
+    let config = CONFIG.load(deps.storage)?;
+    config.update(deps.api, updates.clone())?;
+
+    let pending_update = PendingConfigUpdate {
+        updates,
+        ramp_duration,
+        applicable_at: env
+            .block
+            .time
+            .seconds()
+            .checked_add(config.config_update_delay)
+            .ok_or(ContractError::TimestampOverflow {})?,
+    };
+    PENDING_CONFIG_UPDATE.save(deps.storage, &pending_update)?;
+
+    let event =
+        Event::new("apollo/neutron-astroport-reward-distributor/execute_propose_config_update")
+            .add_attribute("updates", format!("{:?}", pending_update.updates))
+            .add_attribute("applicable_at", pending_update.applicable_at.to_string());
 
-    Ok(withdraw_res.add_message(callback_msg).add_event(event))
+    Ok(Response::default().add_event(event))
 }
 
-pub fn execute_internal_lp_redeemed(deps: Deps, env: Env) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-    let reward_pool = REWARD_TOKEN.load(deps.storage)?.into_pool()?;
+pub fn execute_apply_config_update(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let pending_update = PENDING_CONFIG_UPDATE
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingConfigUpdate {})?;
+    assert_can_update_config(deps.storage, &info.sender, &pending_update.updates)?;
+    if env.block.time.seconds() < pending_update.applicable_at {
+        return Err(ContractError::ConfigUpdateNotApplicable {
+            applicable_at: pending_update.applicable_at,
+        });
+    }
+    PENDING_CONFIG_UPDATE.remove(deps.storage);
 
-    // Query contracts balances of pool assets
-    let pool_asset_balances: AssetList = AssetList::query_asset_info_balances(
-        reward_pool.pool_assets,
-        &deps.querier,
-        &env.contract.address,
-    )?;
+    apply_config_update(
+        deps,
+        env,
+        pending_update.updates,
+        pending_update.ramp_duration,
+    )
+}
 
-    // Create msg to send assets to distribution address
-    let send_msgs = pool_asset_balances.transfer_msgs(config.distribution_addr)?;
+pub fn execute_cancel_config_update(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let pending_update = PENDING_CONFIG_UPDATE
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingConfigUpdate {})?;
+    assert_can_update_config(deps.storage, &info.sender, &pending_update.updates)?;
+    PENDING_CONFIG_UPDATE.remove(deps.storage);
 
-    let mut event =
-        Event::new("apollo/neutron-astroport-reward-distributor/execute_internal_lp_redeemed");
-    for asset in pool_asset_balances.iter() {
-        event = event.add_attribute("asset_distributed", asset.to_string());
-    }
+    let event =
+        Event::new("apollo/neutron-astroport-reward-distributor/execute_cancel_config_update")
+            .add_attribute("updates", format!("{:?}", pending_update.updates));
 
-    Ok(Response::default().add_messages(send_msgs).add_event(event))
+    Ok(Response::default().add_event(event))
 }
 
-pub fn execute_update_config(
+/// Applies the given updates to the config. If `ramp_duration` is set and the
+/// emission rate changes, the rate moves linearly from its current value to
+/// the new one over that many seconds. Authorization and the config update
+/// delay must be checked by the caller.
+pub fn apply_config_update(
     mut deps: DepsMut,
     env: Env,
-    info: MessageInfo,
-    updates: ConfigUpdates,
+    mut updates: ConfigUpdates,
+    ramp_duration: Option<u64>,
 ) -> Result<Response, ContractError> {
-    // only owner can send this message
-    cw_ownable::assert_owner(deps.storage, &info.sender)?;
-
     let config = CONFIG.load(deps.storage)?;
+    let current_time = env.block.time.seconds();
+
+    // A new distribution address only becomes the recipient once it accepts,
+    // so that rewards are never sent to a mistyped address. Proposing the
+    // current distribution address cancels the pending change.
+    let pending_recipient = match updates.take_distribution_addr() {
+        Some(new_recipient) => {
+            let new_recipient = deps.api.addr_validate(&new_recipient)?;
+            if new_recipient == config.distribution_addr {
+                PENDING_RECIPIENT.remove(deps.storage);
+            } else {
+                PENDING_RECIPIENT.save(deps.storage, &new_recipient)?;
+            }
+            Some(new_recipient)
+        }
+        None => None,
+    };
     let updated_config = config.update(deps.api, updates)?;
 
-    // If we are changing the emission rate or the reward start time, we first need
-    // to distribute rewards, so that the emission rate change takes effect from
-    // the current block.
-    let res = if config.emission_per_second != updated_config.emission_per_second
+    // A new pin must match the code of the current distribution address, so
+    // that distributing does not pause right away
+    if updated_config.recipient_code_id != config.recipient_code_id {
+        assert_recipient_code_matches(deps.as_ref(), &updated_config)?;
+    }
+
+    // Stakes are tracked against the staking contract, so it cannot be swapped
+    // out from under them
+    if config.staking_addr.is_some() && updated_config.staking_addr != config.staking_addr {
+        return Err(ContractError::StakingAddrLocked {});
+    }
+
+    // Unlocks in progress are only withdrawn when delivering the underlying
+    // assets, so they must have been withdrawn before switching to raw delivery
+    if config.delivery != Delivery::Raw
+        && updated_config.delivery == Delivery::Raw
+        && !PENDING_UNLOCKS.is_empty(deps.storage)
+    {
+        return Err(ContractError::UnlocksPending {});
+    }
+
+    // Stakers can not claim rewards that have to be unlocked first
+    if config.distribution_mode != updated_config.distribution_mode
+        || config.delivery != updated_config.delivery
+    {
+        let reward_token = REWARD_TOKEN.load(deps.storage)?;
+        assert_staked_delivery_supported(deps.as_ref(), &updated_config, &reward_token)?;
+    }
+
+    // If we are changing the distribution mode, the rewards accrued so far are
+    // settled under the current mode. If we are changing the emission rate or the
+    // reward start time, we first need to distribute rewards, so that the emission
+    // rate change takes effect from the current block. While paused, the rewards
+    // are only accrued.
+    let res = if config.distribution_mode != updated_config.distribution_mode {
+        settle_accrued_rewards(deps.branch(), env, &config)?
+    } else if config.emission_per_second != updated_config.emission_per_second
         || config.rewards_start_time != updated_config.rewards_start_time
     {
-        execute_distribute(deps.branch(), env)?
+        if PAUSED.load(deps.storage)? {
+            if current_time >= config.rewards_start_time {
+                accrue_rewards(deps.branch(), &config, current_time)?;
+            }
+            Response::default()
+        } else {
+            execute_distribute(deps.branch(), env)?
+        }
     } else {
         Response::default()
     };
+    if config.distribution_mode != DistributionMode::Staked
+        && updated_config.distribution_mode == DistributionMode::Staked
+    {
+        seed_total_stake(deps.branch(), &updated_config)?;
+    }
+
+    // Limit the emission rate change compared to the rates in effect within the
+    // last rate change window, and record the replaced rate
+    if config.emission_per_second != updated_config.emission_per_second {
+        let mut history: Vec<RateChangeCheckpoint> = RATE_CHANGE_HISTORY
+            .may_load(deps.storage)?
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|checkpoint| {
+                checkpoint.time.saturating_add(config.rate_change_window) > current_time
+            })
+            .collect();
+        let (min_rate, max_rate) = history.iter().fold(
+            (config.emission_per_second, config.emission_per_second),
+            |(min, max), checkpoint| (min.min(checkpoint.rate), max.max(checkpoint.rate)),
+        );
+        config.check_rate_change(min_rate, max_rate, updated_config.emission_per_second)?;
+        history.push(RateChangeCheckpoint {
+            rate: config.emission_per_second,
+            time: current_time,
+        });
+        RATE_CHANGE_HISTORY.save(deps.storage, &history)?;
+    }
+
+    // Ramp from the current emission rate to the new one, taking into account any
+    // ongoing ramp
+    if config.emission_per_second != updated_config.emission_per_second {
+        match ramp_duration.filter(|duration| *duration > 0) {
+            Some(duration) => {
+                let current_rate = match EMISSION_RAMP.may_load(deps.storage)? {
+                    Some(ramp) => ramp.rate_at(config.emission_per_second, current_time),
+                    None => config.emission_per_second,
+                };
+                let ramp = EmissionRamp {
+                    start_rate: current_rate,
+                    start_time: current_time,
+                    end_time: current_time
+                        .checked_add(duration)
+                        .ok_or(ContractError::TimestampOverflow {})?,
+                };
+                EMISSION_RAMP.save(deps.storage, &ramp)?;
+            }
+            None => EMISSION_RAMP.remove(deps.storage),
+        }
+    }
 
     // Update config
     CONFIG.save(deps.storage, &updated_config)?;
 
-    let event = Event::new("apollo/neutron-astroport-reward-distributor/execute_update_config")
+    let mut event = Event::new("apollo/neutron-astroport-reward-distributor/execute_update_config")
         .add_attribute("old_config", format!("{:?}", config))
-        .add_attribute("new_config", format!("{:?}", updated_config));
+        .add_attribute("new_config", format!("{:?}", updated_config))
+        .add_attribute(
+            "ramp_duration",
+            ramp_duration.unwrap_or_default().to_string(),
+        );
+    if let Some(pending_recipient) = pending_recipient {
+        event = event.add_attribute("pending_recipient", pending_recipient);
+    }
 
     Ok(res.add_event(event))
 }
+
+/// Settles the rewards accrued so far under the current distribution address
+/// and mode, before either changes. In push mode they are distributed to the
+/// distribution address. In pull mode, or in push mode while paused, they are
+/// set aside for it to claim. In staked mode they are shared between the
+/// current stakers.
+fn settle_accrued_rewards(
+    mut deps: DepsMut,
+    env: Env,
+    config: &Config,
+) -> Result<Response, ContractError> {
+    let current_time = env.block.time.seconds();
+
+    match config.distribution_mode {
+        DistributionMode::Push if !PAUSED.load(deps.storage)? => execute_distribute(deps, env),
+        DistributionMode::Push | DistributionMode::Pull => {
+            let period_start = pending_since(deps.storage, config)?.min(current_time);
+            let accrued = if current_time >= config.rewards_start_time {
+                accrue_rewards(deps.branch(), config, current_time)?
+            } else {
+                PENDING_REWARDS.load(deps.storage)?
+            };
+            let unclaimed =
+                match UNCLAIMED_REWARDS.may_load(deps.storage, &config.distribution_addr)? {
+                    Some(unclaimed) => UnclaimedRewards {
+                        amount: unclaimed.amount + accrued,
+                        period_start: unclaimed.period_start.min(period_start),
+                        period_end: current_time,
+                    },
+                    None => UnclaimedRewards {
+                        amount: accrued,
+                        period_start,
+                        period_end: current_time,
+                    },
+                };
+            UNCLAIMED_REWARDS.save(deps.storage, &config.distribution_addr, &unclaimed)?;
+            reset_pending_rewards(deps.storage, current_time)?;
+            Ok(Response::default())
+        }
+        DistributionMode::Staked => {
+            update_reward_index(deps, config, current_time)?;
+            Ok(Response::default())
+        }
+    }
+}
+
+pub fn execute_accept_recipient(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let pending_recipient = PENDING_RECIPIENT
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingRecipient {})?;
+
+    // The pending recipient accepts itself, or through its admin if it is a
+    // contract that cannot send the message
+    let admin = deps
+        .querier
+        .query_wasm_contract_info(&pending_recipient)
+        .ok()
+        .and_then(|info| info.admin);
+    if info.sender != pending_recipient && admin.as_deref() != Some(info.sender.as_str()) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+
+    // The rewards accrued so far belong to the current distribution address
+    let res = settle_accrued_rewards(deps.branch(), env, &config)?;
+
+    CONFIG.save(
+        deps.storage,
+        &Config {
+            distribution_addr: pending_recipient.clone(),
+            ..config.clone()
+        },
+    )?;
+    PENDING_RECIPIENT.remove(deps.storage);
+
+    let event = Event::new("apollo/neutron-astroport-reward-distributor/execute_accept_recipient")
+        .add_attribute("old_recipient", config.distribution_addr)
+        .add_attribute("new_recipient", pending_recipient)
+        .add_attribute("sender", info.sender);
+
+    Ok(res.add_event(event))
+}
+
+pub fn execute_grant_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    role: Role,
+    address: String,
+) -> Result<Response, ContractError> {
+    // only owner can send this message
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    let address = deps.api.addr_validate(&address)?;
+    ROLES.save(deps.storage, (&address, role.as_str()), &Empty {})?;
+
+    let event = Event::new("apollo/neutron-astroport-reward-distributor/execute_grant_role")
+        .add_attribute("role", role.as_str())
+        .add_attribute("address", address);
+
+    Ok(Response::default().add_event(event))
+}
+
+pub fn execute_revoke_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    role: Role,
+    address: String,
+) -> Result<Response, ContractError> {
+    // only owner can send this message
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    let address = deps.api.addr_validate(&address)?;
+    ROLES.remove(deps.storage, (&address, role.as_str()));
+
+    let event = Event::new("apollo/neutron-astroport-reward-distributor/execute_revoke_role")
+        .add_attribute("role", role.as_str())
+        .add_attribute("address", address);
+
+    Ok(Response::default().add_event(event))
+}
+
+pub fn execute_pause(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    assert_owner_or_role(deps.storage, &info.sender, Some(Role::Guardian))?;
+
+    pause(deps, info.sender.as_str())
+}
+
+/// Pauses distribution on behalf of the given sender
+pub fn pause(deps: DepsMut, sender: &str) -> Result<Response, ContractError> {
+    PAUSED.save(deps.storage, &true)?;
+
+    let event = Event::new("apollo/neutron-astroport-reward-distributor/execute_pause")
+        .add_attribute("sender", sender);
+
+    Ok(Response::default().add_event(event))
+}
+
+pub fn execute_unpause(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    // only owner can send this message
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    unpause(deps, info.sender.as_str())
+}
+
+/// Unpauses distribution on behalf of the given sender
+pub fn unpause(deps: DepsMut, sender: &str) -> Result<Response, ContractError> {
+    PAUSED.save(deps.storage, &false)?;
+
+    let event = Event::new("apollo/neutron-astroport-reward-distributor/execute_unpause")
+        .add_attribute("sender", sender);
+
+    Ok(Response::default().add_event(event))
+}
+
+pub fn execute_emergency_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    // only owner can send this message
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    emergency_withdraw(deps, env, recipient, info.sender.as_str())
+}
+
+/// Pauses distribution and sends the reward tokens that are not owed to anyone
+/// to the recipient, on behalf of the given sender. The pending rewards are
+/// forfeited, as there is nothing left to deliver them from.
+pub fn emergency_withdraw(
+    deps: DepsMut,
+    env: Env,
+    recipient: String,
+    sender: &str,
+) -> Result<Response, ContractError> {
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let current_time = env.block.time.seconds();
+
+    PAUSED.save(deps.storage, &true)?;
+    LAST_DISTRIBUTED.save(deps.storage, &current_time)?;
+    EMISSION_REMAINDER.save(deps.storage, &Decimal::zero())?;
+    reset_pending_rewards(deps.storage, current_time)?;
+
+    let reward_token = REWARD_TOKEN.load(deps.storage)?;
+    let reward_token_info = reward_token_asset_info(deps.as_ref(), &reward_token)?;
+    let withdrawable = spendable_balance(deps.as_ref(), &env, &reward_token_info, Uint128::zero())?;
+    let withdrawn = Asset::new(reward_token_info, withdrawable);
+
+    let mut res = Response::default();
+    if !withdrawn.amount.is_zero() {
+        res = res.add_message(withdrawn.transfer_msg(&recipient)?);
+    }
+
+    let event =
+        Event::new("apollo/neutron-astroport-reward-distributor/execute_emergency_withdraw")
+            .add_attribute("sender", sender)
+            .add_attribute("recipient", recipient)
+            .add_attribute("asset_withdrawn", withdrawn.to_string());
+
+    Ok(res.add_event(event))
+}
+
+pub fn execute_set_destination_pool(
+    deps: DepsMut,
+    info: MessageInfo,
+    destination_pool: Option<DestinationPoolInfo>,
+) -> Result<Response, ContractError> {
+    // only owner can send this message
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    let mut event =
+        Event::new("apollo/neutron-astroport-reward-distributor/execute_set_destination_pool");
+    match destination_pool {
+        Some(info) => {
+            let reward_token = REWARD_TOKEN.load(deps.storage)?;
+            let destination = check_destination_pool(deps.as_ref(), &reward_token, info)?;
+            let lp_token = lp_token(deps.as_ref(), &destination.pool)?;
+            event = event.add_attribute("lp_token", lp_token.to_string());
+            DESTINATION_POOL.save(deps.storage, &destination)?;
+        }
+        None => DESTINATION_POOL.remove(deps.storage),
+    }
+
+    Ok(Response::default().add_event(event))
+}
+
+pub fn execute_retry_delivery(deps: DepsMut) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let recipient = config.distribution_addr.clone();
+
+    let escrow = ESCROW.may_load(deps.storage, &recipient)?.ok_or_else(|| {
+        ContractError::NothingInEscrow {
+            address: recipient.to_string(),
+        }
+    })?;
+    assert_recipient_code_matches(deps.as_ref(), &config)?;
+    ESCROW.remove(deps.storage, &recipient);
+    release_assets(deps.storage, &escrow)?;
+
+    // Assets that fail to be sent again are put back in escrow in the reply
+    let transfers = escrowed_transfer_msgs(
+        deps.storage,
+        escrow
+            .iter()
+            .map(|asset| (recipient.clone(), asset.clone()))
+            .collect(),
+    )?;
+
+    let mut event =
+        Event::new("apollo/neutron-astroport-reward-distributor/execute_retry_delivery")
+            .add_attribute("recipient", recipient);
+    for asset in escrow.iter() {
+        event = event.add_attribute("asset_retried", asset.to_string());
+    }
+
+    Ok(Response::new().add_submessages(transfers).add_event(event))
+}
+
+pub fn execute_set_vesting_schedule(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipient: String,
+    schedule: Option<VestingSchedule>,
+) -> Result<Response, ContractError> {
+    assert_owner_or_role(deps.storage, &info.sender, Some(Role::RecipientManager))?;
+
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let mut event =
+        Event::new("apollo/neutron-astroport-reward-distributor/execute_set_vesting_schedule")
+            .add_attribute("recipient", recipient.to_string());
+    match schedule {
+        Some(schedule) => {
+            if schedule.cliff > schedule.duration {
+                return Err(ContractError::InvalidVestingSchedule {
+                    cliff: schedule.cliff,
+                    duration: schedule.duration,
+                });
+            }
+            event = event
+                .add_attribute("cliff", schedule.cliff.to_string())
+                .add_attribute("duration", schedule.duration.to_string());
+            VESTING_SCHEDULES.save(deps.storage, &recipient, &schedule)?;
+        }
+        None => VESTING_SCHEDULES.remove(deps.storage, &recipient),
+    }
+
+    Ok(Response::new().add_event(event))
+}
+
+pub fn execute_withdraw_vested(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let current_time = env.block.time.seconds();
+    let positions = VESTING_POSITIONS
+        .prefix(&info.sender)
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    // Positions are removed once all of their assets have vested and been
+    // withdrawn
+    let mut withdrawn = AssetList::new();
+    for (start_time, mut position) in positions {
+        let vested = position.withdrawable(current_time)?;
+        if position.is_fully_vested(current_time) {
+            VESTING_POSITIONS.remove(deps.storage, (&info.sender, start_time));
+        } else {
+            position.withdrawn.add_many(&vested)?;
+            VESTING_POSITIONS.save(deps.storage, (&info.sender, start_time), &position)?;
+        }
+        withdrawn.add_many(&vested)?;
+    }
+    if withdrawn.len() == 0 {
+        return Err(ContractError::NothingVested {});
+    }
+    release_assets(deps.storage, &withdrawn)?;
+
+    let mut event =
+        Event::new("apollo/neutron-astroport-reward-distributor/execute_withdraw_vested")
+            .add_attribute("recipient", info.sender.to_string());
+    for asset in withdrawn.iter() {
+        event = event.add_attribute("asset_withdrawn", asset.to_string());
+    }
+
+    Ok(Response::new()
+        .add_messages(withdrawn.transfer_msgs(&info.sender)?)
+        .add_event(event))
+}
+
+pub fn execute_claim_escrow(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let escrow = ESCROW
+        .may_load(deps.storage, &info.sender)?
+        .ok_or_else(|| ContractError::NothingInEscrow {
+            address: info.sender.to_string(),
+        })?;
+    ESCROW.remove(deps.storage, &info.sender);
+    release_assets(deps.storage, &escrow)?;
+
+    let mut event = Event::new("apollo/neutron-astroport-reward-distributor/execute_claim_escrow")
+        .add_attribute("recipient", info.sender.to_string());
+    for asset in escrow.iter() {
+        event = event.add_attribute("asset_claimed", asset.to_string());
+    }
+
+    Ok(Response::new()
+        .add_messages(escrow.transfer_msgs(&info.sender)?)
+        .add_event(event))
+}
@@ -0,0 +1,1039 @@
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use cosmwasm_std::{
+    to_json_binary, Addr, CosmosMsg, Decimal, Decimal256, Deps, DepsMut, Empty, Env, Order,
+    ReplyOn, Response, StdError, StdResult, Storage, SubMsg, Uint128, WasmMsg,
+};
+use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, TokenInfoResponse};
+use cw_dex::traits::Pool as PoolTrait;
+use cw_dex::Pool;
+use cw_utils::Duration;
+use cw_vault_standard::extensions::lockup::{LockupExecuteMsg, LockupQueryMsg, UnlockingPosition};
+use cw_vault_standard::{
+    ExtensionQueryMsg, VaultContract, VaultStandardExecuteMsg, VaultStandardQueryMsg,
+};
+use neutron_astroport_reward_distributor::msg::{
+    DestinationPoolInfo, StakeChangedHookMsg, StakedBalanceAtHeightResponse, StakingQueryMsg,
+    TotalStakedAtHeightResponse,
+};
+use neutron_astroport_reward_distributor::{
+    emitted_at_rate, Config, ConfigUpdates, ContractError, Delivery, DestinationPool,
+    DistributionMode, PendingTransfers, RewardType, Role, StakerInfo, VestingPosition,
+    BALANCE_SNAPSHOT, EMISSION_RAMP, EMISSION_REMAINDER, FEES_COLLECTED, HELD_ASSETS,
+    LAST_DISTRIBUTED, MERKLE_AIRDROPS, PENDING_REWARDS, PENDING_SINCE, PENDING_TRANSFERS,
+    PENDING_UNLOCKS, REWARD_INDEX, REWARD_TOKEN, ROLES, STAKERS, STAKER_REWARDS, TOTAL_STAKE,
+    UNCLAIMED_REWARDS, VESTING_POSITIONS, VESTING_SCHEDULES,
+};
+use osmosis_std::types::cosmos::base::v1beta1::Coin;
+use osmosis_std::types::osmosis::tokenfactory::v1beta1::MsgMint;
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "astroport")]
+use crate::astroport;
+use crate::reply::{
+    INNER_VAULT_TOKENS_REDEEMED_REPLY_ID, TRANSFER_FAILED_REPLY_ID, VAULT_TOKENS_REDEEMED_REPLY_ID,
+};
+
+/// The prefix of native tokenfactory denoms
+pub const TOKENFACTORY_DENOM_PREFIX: &str = "factory/";
+
+/// Adds the rewards emitted since the last distribution to the pending rewards
+/// and returns the total pending rewards. Only whole tokens are added, the
+/// fraction of a token left over is carried to the next distribution.
+pub fn accrue_rewards(
+    deps: DepsMut,
+    config: &Config,
+    current_time: u64,
+) -> Result<Uint128, ContractError> {
+    let (pending_rewards, remainder) = pending_rewards_at(deps.as_ref(), config, current_time)?;
+
+    EMISSION_REMAINDER.save(deps.storage, &remainder)?;
+    PENDING_REWARDS.save(deps.storage, &pending_rewards)?;
+    LAST_DISTRIBUTED.save(deps.storage, &current_time)?;
+
+    Ok(pending_rewards)
+}
+
+/// Returns the pending rewards including the rewards emitted since the last
+/// distribution, and the fraction of a token left over
+fn pending_rewards_at(
+    deps: Deps,
+    config: &Config,
+    current_time: u64,
+) -> Result<(Uint128, Decimal), ContractError> {
+    let last_distributed = LAST_DISTRIBUTED.load(deps.storage)?;
+    let pending_rewards = PENDING_REWARDS.load(deps.storage)?;
+
+    let distribute_from = last_distributed.max(config.rewards_start_time);
+    let emitted = match EMISSION_RAMP.may_load(deps.storage)? {
+        Some(ramp) => {
+            ramp.emitted_between(config.emission_per_second, distribute_from, current_time)?
+        }
+        None => {
+            let time_elapsed = current_time.saturating_sub(distribute_from);
+            emitted_at_rate(config.emission_per_second, time_elapsed)?
+        }
+    };
+
+    let remainder = EMISSION_REMAINDER
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let emitted = emitted
+        .checked_add(remainder.into())
+        .map_err(|_| ContractError::EmissionOverflow {})?;
+    let whole_tokens = emitted.to_uint_floor();
+    let remainder = Decimal::try_from(emitted - Decimal256::from_ratio(whole_tokens, 1u8))
+        .map_err(|_| ContractError::EmissionOverflow {})?;
+    let pending_rewards = Uint128::try_from(whole_tokens)
+        .ok()
+        .and_then(|whole_tokens| pending_rewards.checked_add(whole_tokens).ok())
+        .ok_or(ContractError::EmissionOverflow {})?;
+
+    Ok((pending_rewards, remainder))
+}
+
+/// Returns the unix timestamp from which the pending rewards have been
+/// accruing
+pub fn pending_since(storage: &dyn Storage, config: &Config) -> StdResult<u64> {
+    let pending_since = PENDING_SINCE
+        .may_load(storage)?
+        .unwrap_or(config.rewards_start_time);
+    Ok(pending_since.max(config.rewards_start_time))
+}
+
+/// Sets the pending rewards to zero once they have been delivered or set aside
+pub fn reset_pending_rewards(storage: &mut dyn Storage, current_time: u64) -> StdResult<()> {
+    PENDING_REWARDS.save(storage, &Uint128::zero())?;
+    PENDING_SINCE.save(storage, &current_time)
+}
+
+/// Deducts the delivered rewards from the pending rewards. Rewards that could
+/// not be delivered stay pending, and keep accruing from the same start time.
+pub fn deduct_pending_rewards(
+    storage: &mut dyn Storage,
+    delivered: Uint128,
+    current_time: u64,
+) -> StdResult<()> {
+    let pending_rewards = PENDING_REWARDS.load(storage)?.saturating_sub(delivered);
+    if pending_rewards.is_zero() {
+        return reset_pending_rewards(storage, current_time);
+    }
+    PENDING_REWARDS.save(storage, &pending_rewards)
+}
+
+/// Asserts that the sender is the owner, or has been granted the given role.
+/// Errors with the ownership error otherwise.
+pub fn assert_owner_or_role(
+    storage: &dyn Storage,
+    sender: &Addr,
+    role: Option<Role>,
+) -> Result<(), ContractError> {
+    if let Some(role) = role {
+        if ROLES.has(storage, (sender, role.as_str())) {
+            return Ok(());
+        }
+    }
+
+    Ok(cw_ownable::assert_owner(storage, sender)?)
+}
+
+/// Asserts that the sender may apply the given config updates. The owner may
+/// update anything, operators only the emission rate and recipient managers
+/// only the distribution address.
+pub fn assert_can_update_config(
+    storage: &dyn Storage,
+    sender: &Addr,
+    updates: &ConfigUpdates,
+) -> Result<(), ContractError> {
+    let role = if updates.only_updates_emission_rate() {
+        Some(Role::Operator)
+    } else if updates.only_updates_distribution_addr() {
+        Some(Role::RecipientManager)
+    } else {
+        None
+    };
+
+    assert_owner_or_role(storage, sender, role)
+}
+
+/// Queries the contract's balances of the given assets and saves them as the
+/// balance snapshot, to be compared against in a later reply.
+pub fn save_balance_snapshot(
+    deps: DepsMut,
+    env: &Env,
+    asset_infos: Vec<AssetInfo>,
+) -> StdResult<()> {
+    let balances = asset_infos
+        .into_iter()
+        .map(|info| {
+            let amount = info.query_balance(&deps.querier, &env.contract.address)?;
+            Ok(Asset::new(info, amount))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    BALANCE_SNAPSHOT.save(deps.storage, &balances)
+}
+
+/// Returns how much the contract's balances of the snapshotted assets have
+/// increased since the snapshot was taken, and removes the snapshot.
+pub fn take_balance_diff(deps: DepsMut, env: &Env) -> StdResult<AssetList> {
+    let snapshot = BALANCE_SNAPSHOT.load(deps.storage)?;
+    BALANCE_SNAPSHOT.remove(deps.storage);
+
+    let diff = snapshot
+        .into_iter()
+        .map(|before| {
+            let after = before
+                .info
+                .query_balance(&deps.querier, &env.contract.address)?;
+            Ok(Asset::new(before.info, after.checked_sub(before.amount)?))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(diff.into())
+}
+
+/// Makes the last message of the response reply on success with the given id.
+/// Since messages are executed in order, the reply handler runs once all
+/// messages of the response have been executed.
+pub fn reply_on_last_msg(mut res: Response, id: u64) -> StdResult<Response> {
+    let last_msg = res
+        .messages
+        .last_mut()
+        .ok_or_else(|| StdError::generic_err("No message to reply on"))?;
+    last_msg.id = id;
+    last_msg.reply_on = ReplyOn::Success;
+
+    Ok(res)
+}
+
+/// Returns the asset info of a vault base token, which is a CW20 token if
+/// it is the address of a CW20 contract and a native coin otherwise.
+pub fn query_asset_info(deps: Deps, base_token: &str) -> AssetInfo {
+    let token_info: Option<TokenInfoResponse> = deps
+        .api
+        .addr_validate(base_token)
+        .and_then(|addr| {
+            deps.querier
+                .query_wasm_smart(addr, &Cw20QueryMsg::TokenInfo {})
+        })
+        .ok();
+
+    match token_info {
+        Some(_) => AssetInfo::Cw20(Addr::unchecked(base_token)),
+        None => AssetInfo::Native(base_token.to_string()),
+    }
+}
+
+/// Returns the contract's balance of the reward token that it can spend, which
+/// excludes the reward tokens owed to someone. `reserved_for_recipient` is the
+/// amount of those owed to the recipient of the delivery, which it can spend.
+pub fn spendable_balance(
+    deps: Deps,
+    env: &Env,
+    reward_token_info: &AssetInfo,
+    reserved_for_recipient: Uint128,
+) -> StdResult<Uint128> {
+    let balance = reward_token_info.query_balance(&deps.querier, &env.contract.address)?;
+    let reserved = reserved_reward_tokens(deps, reward_token_info)?;
+    Ok(balance.saturating_sub(reserved.saturating_sub(reserved_for_recipient)))
+}
+
+/// Checks that the contract can spend at least `amount` vault tokens, without
+/// spending those owed to others than the recipient. This is also so we get a
+/// clearer error message rather than the confusing "cannot sub 0 with x".
+pub fn assert_vault_token_balance(
+    deps: Deps,
+    env: &Env,
+    vault_token: &AssetInfo,
+    amount: Uint128,
+    reserved_for_recipient: Uint128,
+) -> Result<(), ContractError> {
+    let vault_token_balance = spendable_balance(deps, env, vault_token, reserved_for_recipient)?;
+    if vault_token_balance < amount {
+        return Err(ContractError::InsufficientVaultTokenBalance {
+            vault_token_balance,
+            redeem_amount: amount,
+        });
+    }
+    Ok(())
+}
+
+/// Checks that the contract can deliver `amount` reward tokens without
+/// spending those owed to others than the recipient, so that only transfers
+/// rejected by their recipient are held in escrow
+pub fn assert_spendable_balance(
+    deps: Deps,
+    env: &Env,
+    asset_info: &AssetInfo,
+    amount: Uint128,
+    reserved_for_recipient: Uint128,
+) -> Result<(), ContractError> {
+    let spendable = spendable_balance(deps, env, asset_info, reserved_for_recipient)?;
+    if spendable < amount {
+        return Err(ContractError::InsufficientSpendableBalance {
+            asset: asset_info.to_string(),
+            balance: spendable,
+            amount,
+        });
+    }
+    Ok(())
+}
+
+/// Returns the LP token of the pool
+#[cfg_attr(not(feature = "astroport"), allow(unused_variables))]
+pub fn lp_token(deps: Deps, pool: &Pool) -> StdResult<AssetInfo> {
+    match pool {
+        #[cfg(feature = "astroport")]
+        Pool::Astroport(pool) => astroport::lp_token(deps, pool),
+        #[allow(unreachable_patterns)]
+        pool => Ok(pool.lp_token()),
+    }
+}
+
+/// Returns a response with the messages to withdraw liquidity from the pool
+pub fn withdraw_liquidity(
+    deps: Deps,
+    env: &Env,
+    pool: &Pool,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    match pool {
+        #[cfg(feature = "astroport")]
+        Pool::Astroport(pool) => astroport::withdraw_liquidity(deps, env, pool, amount),
+        #[allow(unreachable_patterns)]
+        pool => Ok(pool.withdraw_liquidity(
+            deps,
+            env,
+            Asset::new(pool.lp_token(), amount),
+            AssetList::new(),
+        )?),
+    }
+}
+
+/// Returns true if vault tokens of the vault can not be redeemed directly, but
+/// must be unlocked first.
+pub fn is_locked_vault(deps: Deps, vault: &VaultContract) -> StdResult<bool> {
+    let standard_info = vault.query_vault_standard_info(&deps.querier)?;
+    if !standard_info.extensions.iter().any(|ext| ext == "lockup") {
+        return Ok(false);
+    }
+
+    let lockup_duration: Duration = deps.querier.query_wasm_smart(
+        &vault.addr,
+        &VaultStandardQueryMsg::VaultExtension(ExtensionQueryMsg::Lockup(
+            LockupQueryMsg::LockupDuration {},
+        )),
+    )?;
+    Ok(!matches!(
+        lockup_duration,
+        Duration::Time(0) | Duration::Height(0)
+    ))
+}
+
+/// The maximum number of matured unlocks withdrawn in a single delivery
+pub const MAX_UNLOCKS_WITHDRAWN: u32 = 10;
+
+/// Queries a page of the unlocking positions in the vault that have been
+/// started by the contract but not yet withdrawn. The positions are queried
+/// from the vault in a single query, unless there are no pending unlocks.
+pub fn query_pending_unlocks(
+    deps: Deps,
+    env: &Env,
+    vault: &VaultContract,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<UnlockingPosition>> {
+    if PENDING_UNLOCKS.is_empty(deps.storage) {
+        return Ok(vec![]);
+    }
+
+    let positions: Vec<UnlockingPosition> = deps.querier.query_wasm_smart(
+        &vault.addr,
+        &VaultStandardQueryMsg::VaultExtension(ExtensionQueryMsg::Lockup(
+            LockupQueryMsg::UnlockingPositions {
+                owner: env.contract.address.to_string(),
+                start_after,
+                limit,
+            },
+        )),
+    )?;
+    Ok(positions
+        .into_iter()
+        .filter(|position| PENDING_UNLOCKS.has(deps.storage, position.id))
+        .collect())
+}
+
+/// Returns true if unlocks have been started for the recipient that have not
+/// been withdrawn yet
+pub fn has_pending_unlocks(storage: &dyn Storage, recipient: &Addr) -> StdResult<bool> {
+    for item in PENDING_UNLOCKS.range(storage, None, None, Order::Ascending) {
+        if item?.1 == *recipient {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Returns messages to withdraw the pending unlocks in the vault started for
+/// the recipient that have matured, and removes them from the pending unlocks.
+/// At most `MAX_UNLOCKS_WITHDRAWN` of the recipient's oldest unlocks are
+/// checked, as unlocks mature in the order they were started. The rest are
+/// withdrawn in later deliveries to the recipient.
+pub fn withdraw_matured_unlocks(
+    deps: DepsMut,
+    env: &Env,
+    vault: &VaultContract,
+    recipient: &Addr,
+) -> StdResult<Vec<CosmosMsg>> {
+    let lockup_ids = PENDING_UNLOCKS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter_map(|item| match item {
+            Ok((lockup_id, owner)) if owner == *recipient => Some(Ok(lockup_id)),
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        })
+        .take(MAX_UNLOCKS_WITHDRAWN as usize)
+        .collect::<StdResult<Vec<u64>>>()?;
+
+    let mut msgs = vec![];
+    for lockup_id in lockup_ids {
+        let position: UnlockingPosition = deps.querier.query_wasm_smart(
+            &vault.addr,
+            &VaultStandardQueryMsg::VaultExtension(ExtensionQueryMsg::Lockup(
+                LockupQueryMsg::UnlockingPosition { lockup_id },
+            )),
+        )?;
+        if position.release_at.is_expired(&env.block) {
+            PENDING_UNLOCKS.remove(deps.storage, lockup_id);
+            msgs.push(
+                LockupExecuteMsg::WithdrawUnlocked {
+                    recipient: None,
+                    lockup_id,
+                }
+                .into_cosmos_msg(vault.addr.to_string(), vec![])?,
+            );
+        }
+    }
+    Ok(msgs)
+}
+
+/// Returns the vault that issues the given token as its vault token, if any.
+/// Native vault tokens are tokenfactory denoms created by the vault, while CW20
+/// vault tokens are issued by the vault contract itself.
+fn query_vault_of_token(deps: Deps, token: &str) -> Option<VaultContract> {
+    let vault_addr = match token.strip_prefix(TOKENFACTORY_DENOM_PREFIX) {
+        Some(denom) => denom.split('/').next()?,
+        None => token,
+    };
+    let vault = VaultContract::new(&deps.api.addr_validate(vault_addr).ok()?);
+
+    let vault_info = vault.query_vault_info(&deps.querier).ok()?;
+    (vault_info.vault_token == token).then_some(vault)
+}
+
+/// Walks the base tokens of the given vault for as long as they are vault
+/// tokens themselves. Returns the inner vaults, outermost first, and the base
+/// token of the innermost vault.
+pub fn find_inner_vaults(
+    deps: Deps,
+    vault: &VaultContract,
+) -> StdResult<(Vec<VaultContract>, String)> {
+    let mut inner_vaults = vec![];
+    let mut base_token = vault.query_vault_info(&deps.querier)?.base_token;
+
+    while let Some(inner_vault) = query_vault_of_token(deps, &base_token) {
+        base_token = inner_vault.query_vault_info(&deps.querier)?.base_token;
+        inner_vaults.push(inner_vault);
+    }
+
+    Ok((inner_vaults, base_token))
+}
+
+/// Returns the token received from redeeming the vault at the given layer,
+/// where layer 0 is the reward vault and layer `i` the `i`th inner vault,
+/// together with the id of the reply that handles the received tokens.
+pub fn redeemed_token(
+    deps: Deps,
+    inner_vaults: &[VaultContract],
+    pool: &Pool,
+    layer: usize,
+) -> StdResult<(AssetInfo, u64)> {
+    match inner_vaults.get(layer) {
+        Some(inner_vault) => {
+            let vault_token = inner_vault.query_vault_info(&deps.querier)?.vault_token;
+            Ok((
+                query_asset_info(deps, &vault_token),
+                INNER_VAULT_TOKENS_REDEEMED_REPLY_ID + layer as u64,
+            ))
+        }
+        None => Ok((lp_token(deps, pool)?, VAULT_TOKENS_REDEEMED_REPLY_ID)),
+    }
+}
+
+/// Returns a response with the messages to redeem the given amount of vault
+/// tokens from the vault. Native vault tokens are sent along with the
+/// redemption, while the vault is given an allowance to burn CW20 vault tokens.
+pub fn redeem_vault_tokens(
+    vault: &VaultContract,
+    vault_token: &AssetInfo,
+    amount: Uint128,
+) -> StdResult<Response> {
+    match vault_token {
+        AssetInfo::Native(denom) => {
+            Ok(Response::new().add_message(vault.redeem(amount, denom, None)?))
+        }
+        AssetInfo::Cw20(vault_token_addr) => {
+            let allowance_msg = WasmMsg::Execute {
+                contract_addr: vault_token_addr.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::IncreaseAllowance {
+                    spender: vault.addr.to_string(),
+                    amount,
+                    expires: None,
+                })?,
+                funds: vec![],
+            };
+            let redeem_msg = WasmMsg::Execute {
+                contract_addr: vault.addr.to_string(),
+                msg: to_json_binary(&VaultStandardExecuteMsg::<Empty>::Redeem {
+                    recipient: None,
+                    amount,
+                })?,
+                funds: vec![],
+            };
+            Ok(Response::new()
+                .add_message(allowance_msg)
+                .add_message(redeem_msg))
+        }
+    }
+}
+
+/// Returns the asset info of the reward token held by the contract
+pub fn reward_token_asset_info(deps: Deps, reward_token: &RewardType) -> StdResult<AssetInfo> {
+    match reward_token {
+        RewardType::Vault { vault, .. } | RewardType::GenericVault { vault, .. } => {
+            let vault_token = vault.query_vault_info(&deps.querier)?.vault_token;
+            Ok(query_asset_info(deps, &vault_token))
+        }
+        RewardType::LP(pool) => lp_token(deps, pool),
+        RewardType::Coin(denom) | RewardType::Mint { denom, .. } => {
+            Ok(AssetInfo::Native(denom.clone()))
+        }
+    }
+}
+
+/// Returns the amount of reward tokens held by the contract that are owed to
+/// someone: rewards left unclaimed by previous recipients or stakers, the
+/// unclaimed amounts of Merkle airdrops, and reward tokens held for recipients
+/// in escrow or vesting positions
+pub fn reserved_reward_tokens(deps: Deps, reward_token_info: &AssetInfo) -> StdResult<Uint128> {
+    let unclaimed = UNCLAIMED_REWARDS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| Ok(item?.1.amount))
+        .sum::<StdResult<Uint128>>()?;
+    let airdropped = MERKLE_AIRDROPS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let airdrop = item?.1;
+            Ok(airdrop.total_amount - airdrop.claimed_amount)
+        })
+        .sum::<StdResult<Uint128>>()?;
+    let staker_rewards = STAKER_REWARDS.may_load(deps.storage)?.unwrap_or_default();
+    let held = HELD_ASSETS
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .find(reward_token_info)
+        .map(|asset| asset.amount)
+        .unwrap_or_default();
+
+    Ok(unclaimed + airdropped + staker_rewards + held)
+}
+
+/// Returns the amount of reward tokens that can be minted without the supply
+/// of the denom exceeding the max supply
+pub fn mintable_amount(
+    deps: Deps,
+    denom: &str,
+    max_supply: Option<Uint128>,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    let Some(max_supply) = max_supply else {
+        return Ok(amount);
+    };
+    let supply = deps.querier.query_supply(denom)?.amount;
+    Ok(amount.min(max_supply.saturating_sub(supply)))
+}
+
+/// Returns a message minting the given amount of the tokenfactory denom to the
+/// contract
+pub fn mint_msg(env: &Env, denom: &str, amount: Uint128) -> CosmosMsg {
+    MsgMint {
+        sender: env.contract.address.to_string(),
+        amount: Some(Coin {
+            denom: denom.to_string(),
+            amount: amount.to_string(),
+        }),
+        mint_to_address: env.contract.address.to_string(),
+    }
+    .into()
+}
+
+/// Validates the destination pool info and finds the pool of its LP token. All
+/// assets of the pool must be received from redeeming the reward token, and
+/// have a reference rate set.
+pub fn check_destination_pool(
+    deps: Deps,
+    reward_token: &RewardType,
+    info: DestinationPoolInfo,
+) -> Result<DestinationPool, ContractError> {
+    if info.max_slippage > Decimal::one() {
+        return Err(ContractError::InvalidMaxSlippage {
+            max_slippage: info.max_slippage,
+        });
+    }
+
+    let liquidity_manager = info
+        .liquidity_manager_addr
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    let pool =
+        Pool::get_pool_for_lp_token(deps, &info.lp_token.check(deps.api)?, liquidity_manager)?;
+
+    // Only the assets withdrawn from the reward pool are provided as liquidity
+    let redeemed_assets = match reward_token {
+        RewardType::Vault { pool, .. } | RewardType::LP(pool) => pool.pool_assets(deps)?,
+        _ => vec![],
+    };
+    let lp_tokens_per_asset = info
+        .lp_tokens_per_asset
+        .into_iter()
+        .map(|(asset, rate)| Ok((asset.check(deps.api)?, rate)))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut rates = vec![];
+    for asset in pool.pool_assets(deps)? {
+        if !redeemed_assets.contains(&asset) {
+            return Err(ContractError::DestinationPoolAssetNotRedeemed {
+                asset: asset.to_string(),
+            });
+        }
+        let rate = lp_tokens_per_asset
+            .iter()
+            .find(|(info, _)| info == &asset)
+            .map(|(_, rate)| *rate)
+            .ok_or_else(|| ContractError::MissingReferenceRate {
+                asset: asset.to_string(),
+            })?;
+        rates.push((asset, rate));
+    }
+
+    Ok(DestinationPool {
+        pool,
+        max_slippage: info.max_slippage,
+        lp_tokens_per_asset: rates,
+    })
+}
+
+/// Returns a response with the messages to provide the assets of the
+/// destination pool as liquidity to it. At least the amount of LP tokens
+/// expected at the reference rates minus the maximum slippage must be received,
+/// so that the bound does not depend on the pool's current state.
+pub fn provide_liquidity(
+    deps: Deps,
+    env: &Env,
+    destination: &DestinationPool,
+    assets: &AssetList,
+) -> Result<Response, ContractError> {
+    let mut provided = AssetList::new();
+    let mut expected: Option<Uint128> = None;
+    for (info, rate) in destination.lp_tokens_per_asset.iter() {
+        let amount = assets.find(info).map(|a| a.amount).unwrap_or_default();
+        provided.add(&Asset::new(info.clone(), amount))?;
+        let lp_tokens = amount * *rate;
+        expected = Some(expected.map_or(lp_tokens, |e| e.min(lp_tokens)));
+    }
+    provided.purge();
+    let min_out = expected.unwrap_or_default() * (Decimal::one() - destination.max_slippage);
+
+    Ok(destination
+        .pool
+        .provide_liquidity(deps, env, provided, min_out)?)
+}
+
+/// Saves a snapshot of the contract's balances of the given assets, not
+/// counting the given assets that were just received. The received assets that
+/// remain unspent are therefore included in the next balance diff.
+pub fn save_balance_snapshot_without(
+    deps: DepsMut,
+    env: &Env,
+    asset_infos: Vec<AssetInfo>,
+    received: &AssetList,
+) -> StdResult<()> {
+    let balances = asset_infos
+        .into_iter()
+        .map(|info| {
+            let amount = info.query_balance(&deps.querier, &env.contract.address)?;
+            let received = received.find(&info).map(|a| a.amount).unwrap_or_default();
+            Ok(Asset::new(info, amount.checked_sub(received)?))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    BALANCE_SNAPSHOT.save(deps.storage, &balances)
+}
+
+/// Returns submessages that make the given transfers of assets to their
+/// recipients. A failed transfer does not fail the delivery, the asset is held
+/// in escrow for its recipient instead. The index of the transfer is added to
+/// the reply id.
+pub fn escrowed_transfer_msgs(
+    storage: &mut dyn Storage,
+    transfers: Vec<(Addr, Asset)>,
+) -> Result<Vec<SubMsg>, ContractError> {
+    let msgs = transfers
+        .iter()
+        .enumerate()
+        .map(|(i, (recipient, asset))| {
+            Ok(SubMsg::reply_on_error(
+                asset.transfer_msg(recipient)?,
+                TRANSFER_FAILED_REPLY_ID + i as u64,
+            ))
+        })
+        .collect::<Result<Vec<_>, ContractError>>()?;
+    PENDING_TRANSFERS.save(storage, &PendingTransfers { transfers })?;
+
+    Ok(msgs)
+}
+
+/// Splits the fee off the assets received for a delivery. Returns the fee
+/// recipient and the fee, which is deducted from the assets.
+pub fn take_fee(config: &Config, assets: &mut AssetList) -> StdResult<Option<(Addr, AssetList)>> {
+    let Some(fee_recipient) = &config.fee_recipient else {
+        return Ok(None);
+    };
+
+    let mut fee = AssetList::new();
+    for asset in assets.iter() {
+        fee.add(&Asset::new(
+            asset.info.clone(),
+            config.fee_amount(asset.amount),
+        ))?;
+    }
+    fee.purge();
+    assets.deduct_many(&fee)?;
+
+    Ok(Some((fee_recipient.clone(), fee)))
+}
+
+/// Adds the fee sent to the fee recipient to the fees collected
+pub fn collect_fees(storage: &mut dyn Storage, fee: &AssetList) -> StdResult<()> {
+    let mut fees_collected = FEES_COLLECTED.may_load(storage)?.unwrap_or_default();
+    fees_collected.add_many(fee)?;
+    FEES_COLLECTED.save(storage, &fees_collected)
+}
+
+/// Adds the given assets to the assets held for recipients
+pub fn hold_assets(storage: &mut dyn Storage, assets: &AssetList) -> StdResult<()> {
+    let mut held = HELD_ASSETS.may_load(storage)?.unwrap_or_default();
+    held.add_many(assets)?;
+    HELD_ASSETS.save(storage, &held)
+}
+
+/// Removes the given assets from the assets held for recipients, once they
+/// have been sent out of escrow or vesting
+pub fn release_assets(storage: &mut dyn Storage, assets: &AssetList) -> StdResult<()> {
+    let mut held = HELD_ASSETS.may_load(storage)?.unwrap_or_default();
+    for asset in assets.iter().filter(|asset| !asset.amount.is_zero()) {
+        held.deduct(asset)?;
+    }
+    HELD_ASSETS.save(storage, &held)
+}
+
+/// Returns submessages that deliver the given assets to the recipient, and the
+/// fee, if any, to the fee recipient. If the recipient has a vesting schedule,
+/// the assets are locked in a vesting position starting at the current time
+/// instead, and only the fee is sent.
+pub fn delivery_msgs(
+    storage: &mut dyn Storage,
+    current_time: u64,
+    recipient: &Addr,
+    assets: &AssetList,
+    fee: Option<(Addr, AssetList)>,
+) -> Result<Vec<SubMsg>, ContractError> {
+    let mut transfers: Vec<(Addr, Asset)> = match fee {
+        Some((fee_recipient, fee)) => fee
+            .iter()
+            .map(|asset| (fee_recipient.clone(), asset.clone()))
+            .collect(),
+        None => vec![],
+    };
+
+    match VESTING_SCHEDULES.may_load(storage, recipient)? {
+        Some(schedule) => {
+            let mut position = VESTING_POSITIONS
+                .may_load(storage, (recipient, current_time))?
+                .unwrap_or(VestingPosition {
+                    start_time: current_time,
+                    schedule,
+                    assets: AssetList::new(),
+                    withdrawn: AssetList::new(),
+                });
+            position.assets.add_many(assets)?;
+            VESTING_POSITIONS.save(storage, (recipient, current_time), &position)?;
+            hold_assets(storage, assets)?;
+        }
+        None => transfers.extend(
+            assets
+                .iter()
+                .map(|asset| (recipient.clone(), asset.clone())),
+        ),
+    }
+
+    escrowed_transfer_msgs(storage, transfers)
+}
+
+/// Returns whether the recipient may receive rewards. If the code of the
+/// distribution address is pinned, it must still be a contract of that code.
+/// Other recipients are not checked.
+pub fn recipient_code_matches(deps: Deps, config: &Config, recipient: &Addr) -> bool {
+    match config.recipient_code_id {
+        Some(code_id) if *recipient == config.distribution_addr => deps
+            .querier
+            .query_wasm_contract_info(recipient)
+            .map_or(false, |info| info.code_id == code_id),
+        _ => true,
+    }
+}
+
+/// Errors if the code of the distribution address is pinned, but the
+/// distribution address is not a contract of that code
+pub fn assert_recipient_code_matches(deps: Deps, config: &Config) -> Result<(), ContractError> {
+    if !recipient_code_matches(deps, config, &config.distribution_addr) {
+        return Err(ContractError::RecipientCodeMismatch {
+            recipient: config.distribution_addr.to_string(),
+            expected: config.recipient_code_id.unwrap_or_default(),
+        });
+    }
+    Ok(())
+}
+
+/// Accrues the rewards emitted so far and shares the pending rewards between
+/// the tracked stake by adding them to the reward index, which is returned.
+/// Rewards stay pending while nothing is staked.
+pub fn update_reward_index(
+    mut deps: DepsMut,
+    config: &Config,
+    current_time: u64,
+) -> Result<Decimal256, ContractError> {
+    if current_time >= config.rewards_start_time {
+        accrue_rewards(deps.branch(), config, current_time)?;
+    }
+
+    let reward_index = REWARD_INDEX.may_load(deps.storage)?.unwrap_or_default();
+    let total_stake = TOTAL_STAKE.may_load(deps.storage)?.unwrap_or_default();
+    let pending_rewards = PENDING_REWARDS.load(deps.storage)?;
+    if total_stake.is_zero() || pending_rewards.is_zero() {
+        return Ok(reward_index);
+    }
+
+    let reward_index = reward_index
+        .checked_add(Decimal256::from_ratio(pending_rewards, total_stake))
+        .map_err(|_| ContractError::EmissionOverflow {})?;
+    REWARD_INDEX.save(deps.storage, &reward_index)?;
+    let staker_rewards = STAKER_REWARDS.may_load(deps.storage)?.unwrap_or_default();
+    STAKER_REWARDS.save(deps.storage, &(staker_rewards + pending_rewards))?;
+    reset_pending_rewards(deps.storage, current_time)?;
+
+    Ok(reward_index)
+}
+
+/// Returns the reward index as it would be if it were updated at the given
+/// time, without saving it
+pub fn projected_reward_index(
+    deps: Deps,
+    config: &Config,
+    current_time: u64,
+) -> Result<Decimal256, ContractError> {
+    let reward_index = REWARD_INDEX.may_load(deps.storage)?.unwrap_or_default();
+    if config.distribution_mode != DistributionMode::Staked {
+        return Ok(reward_index);
+    }
+
+    let total_stake = TOTAL_STAKE.may_load(deps.storage)?.unwrap_or_default();
+    let pending_rewards = if current_time >= config.rewards_start_time {
+        pending_rewards_at(deps, config, current_time)?.0
+    } else {
+        PENDING_REWARDS.load(deps.storage)?
+    };
+    if total_stake.is_zero() || pending_rewards.is_zero() {
+        return Ok(reward_index);
+    }
+
+    reward_index
+        .checked_add(Decimal256::from_ratio(pending_rewards, total_stake))
+        .map_err(|_| ContractError::EmissionOverflow {})
+}
+
+/// Seeds the total stake from the staking contract when switching to staked
+/// mode, so that the stakes of stakers that have not been synced yet are
+/// counted. Stake changes are tracked from then on.
+pub fn seed_total_stake(deps: DepsMut, config: &Config) -> Result<(), ContractError> {
+    if let Some(staking_addr) = &config.staking_addr {
+        let total_stake = deps
+            .querier
+            .query_wasm_smart::<TotalStakedAtHeightResponse>(
+                staking_addr,
+                &StakingQueryMsg::TotalStakedAtHeight { height: None },
+            )?
+            .total;
+        TOTAL_STAKE.save(deps.storage, &total_stake)?;
+    }
+    Ok(())
+}
+
+/// Queries the current stake of a staker from the staking contract
+pub fn query_stake(deps: Deps, staking_addr: &Addr, staker: &Addr) -> StdResult<Uint128> {
+    Ok(deps
+        .querier
+        .query_wasm_smart::<StakedBalanceAtHeightResponse>(
+            staking_addr,
+            &StakingQueryMsg::StakedBalanceAtHeight {
+                address: staker.to_string(),
+                height: None,
+            },
+        )?
+        .balance)
+}
+
+/// Returns the staker info of a staker, or of a staker that has not been
+/// synced yet. Such a staker's stake has not changed since the total stake was
+/// seeded, except for the given stake change if it is being synced because of
+/// it, so it accrues rewards from the start with its stake before the change.
+pub fn load_staker(
+    deps: Deps,
+    config: &Config,
+    staker: &Addr,
+    stake_change: Option<&StakeChangedHookMsg>,
+    current_time: u64,
+) -> StdResult<StakerInfo> {
+    if let Some(staker_info) = STAKERS.may_load(deps.storage, staker)? {
+        return Ok(staker_info);
+    }
+
+    let stake = match &config.staking_addr {
+        Some(staking_addr) => {
+            let stake = query_stake(deps, staking_addr, staker)?;
+            match stake_change {
+                Some(StakeChangedHookMsg::Stake { amount, .. }) => stake.checked_sub(*amount)?,
+                Some(StakeChangedHookMsg::Unstake { amount, .. }) => stake.checked_add(*amount)?,
+                None => stake,
+            }
+        }
+        None => Uint128::zero(),
+    };
+    Ok(StakerInfo {
+        stake,
+        reward_index: Decimal256::zero(),
+        accrued: Decimal256::zero(),
+        accruing_since: current_time,
+    })
+}
+
+/// Accrues the staker's rewards up to the given reward index and updates its
+/// stake to its current stake in the staking contract. Returns the saved
+/// staker info.
+pub fn sync_staker(
+    deps: DepsMut,
+    config: &Config,
+    staker: &Addr,
+    stake_change: Option<&StakeChangedHookMsg>,
+    reward_index: Decimal256,
+    current_time: u64,
+) -> Result<StakerInfo, ContractError> {
+    let mut staker_info = load_staker(deps.as_ref(), config, staker, stake_change, current_time)?;
+    staker_info.accrue(reward_index)?;
+
+    // Outside staked mode the total stake is not used, and it is seeded again
+    // when switching back to staked mode
+    if let Some(staking_addr) = &config.staking_addr {
+        let stake = query_stake(deps.as_ref(), staking_addr, staker)?;
+        if config.distribution_mode == DistributionMode::Staked {
+            let total_stake =
+                TOTAL_STAKE.may_load(deps.storage)?.unwrap_or_default() + stake - staker_info.stake;
+            TOTAL_STAKE.save(deps.storage, &total_stake)?;
+        }
+        staker_info.stake = stake;
+    }
+
+    STAKERS.save(deps.storage, staker, &staker_info)?;
+    Ok(staker_info)
+}
+
+/// Errors if claimed rewards would have to be unlocked from a locked reward
+/// vault. Unlocks are only withdrawn by delivering to their recipient again,
+/// so claimants that can not claim again could never receive the rewards
+/// unlocked for them.
+pub fn assert_no_unlock_needed(deps: Deps, config: &Config) -> Result<(), ContractError> {
+    if let RewardType::Vault { vault, .. } = REWARD_TOKEN.load(deps.storage)? {
+        if config.delivery == Delivery::Underlying && is_locked_vault(deps, &vault)? {
+            return Err(ContractError::LockedVaultClaim {});
+        }
+    }
+    Ok(())
+}
+
+/// Errors if rewards from a locked reward vault would be shared between
+/// stakers as underlying assets. Stakers claim rewards from the vault as they
+/// please, which can not wait for unlocks to mature.
+pub fn assert_staked_delivery_supported(
+    deps: Deps,
+    config: &Config,
+    reward_token: &RewardType,
+) -> Result<(), ContractError> {
+    if let RewardType::Vault { vault, .. } = reward_token {
+        if config.distribution_mode == DistributionMode::Staked
+            && config.delivery == Delivery::Underlying
+            && is_locked_vault(deps, vault)?
+        {
+            return Err(ContractError::LockedVaultStaked {});
+        }
+    }
+    Ok(())
+}
+
+/// Decodes a hex-encoded sha256 hash of a Merkle tree
+pub fn decode_merkle_hash(hash: &str) -> Result<[u8; 32], ContractError> {
+    hex::decode(hash)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| ContractError::InvalidMerkleHash {
+            hash: hash.to_string(),
+        })
+}
+
+/// Verifies that the leaf is part of the Merkle tree with the given root, by
+/// hashing it together with each hash of the proof in sorted order
+pub fn verify_merkle_proof(
+    merkle_root: &str,
+    leaf: [u8; 32],
+    proof: &[String],
+) -> Result<(), ContractError> {
+    let root = proof.iter().try_fold(leaf, |hash, sibling| {
+        let sibling = decode_merkle_hash(sibling)?;
+        let (first, second) = if hash <= sibling {
+            (hash, sibling)
+        } else {
+            (sibling, hash)
+        };
+        Ok::<_, ContractError>(
+            Sha256::new()
+                .chain_update(first)
+                .chain_update(second)
+                .finalize()
+                .into(),
+        )
+    })?;
+
+    if root != decode_merkle_hash(merkle_root)? {
+        return Err(ContractError::InvalidMerkleProof {});
+    }
+    Ok(())
+}
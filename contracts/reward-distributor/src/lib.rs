@@ -1,2 +1,8 @@
+#[cfg(feature = "astroport")]
+pub mod astroport;
 pub mod contract;
 pub mod execute;
+pub mod helpers;
+pub mod migrate;
+pub mod reply;
+pub mod sudo;
@@ -0,0 +1,97 @@
+use cosmwasm_std::{DepsMut, Event, Response, StdError, Storage, Uint128};
+use neutron_astroport_reward_distributor::{
+    ContractError, LAST_DISTRIBUTED, PAUSED, PENDING_REWARDS, PENDING_SINCE,
+};
+
+use crate::contract::{CONTRACT_NAME, CONTRACT_VERSION};
+
+/// The reward token as saved before reward pools were stored as cw-dex pools.
+/// Only Astroport pools were supported, which were saved as is.
+#[cfg(feature = "astroport")]
+mod legacy {
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::{StdResult, Storage};
+    use cw_dex::astroport::AstroportPool;
+    use cw_dex::Pool;
+    use cw_storage_plus::Item;
+    use cw_vault_standard::VaultContract;
+    use neutron_astroport_reward_distributor::{RewardType, REWARD_TOKEN};
+
+    #[cw_serde]
+    enum LegacyRewardType {
+        Vault {
+            vault: VaultContract,
+            pool: AstroportPool,
+        },
+        LP(AstroportPool),
+        Coin(String),
+    }
+
+    const LEGACY_REWARD_TOKEN: Item<LegacyRewardType> = Item::new("reward_token");
+
+    /// Rewrites a reward token saved in the legacy format. Returns true if the
+    /// reward token was rewritten.
+    pub fn migrate_reward_token(storage: &mut dyn Storage) -> StdResult<bool> {
+        if REWARD_TOKEN.load(storage).is_ok() {
+            return Ok(false);
+        }
+
+        let reward_token = match LEGACY_REWARD_TOKEN.load(storage)? {
+            LegacyRewardType::Vault { vault, pool } => RewardType::Vault {
+                vault,
+                inner_vaults: vec![],
+                pool: Pool::Astroport(pool),
+            },
+            LegacyRewardType::LP(pool) => RewardType::LP(Pool::Astroport(pool)),
+            LegacyRewardType::Coin(denom) => RewardType::Coin(denom),
+        };
+        REWARD_TOKEN.save(storage, &reward_token)?;
+
+        Ok(true)
+    }
+}
+
+/// Migrates state saved by earlier versions of the contract. Reward pools are
+/// rewritten as cw-dex pools, and state that earlier versions did not save is
+/// initialized as it would have been on instantiation.
+pub fn migrate_state(deps: DepsMut) -> Result<Response, ContractError> {
+    let stored_version = cw2::get_contract_version(deps.storage)?;
+    if stored_version.contract != CONTRACT_NAME {
+        return Err(StdError::generic_err(format!(
+            "Cannot migrate from contract {}",
+            stored_version.contract
+        ))
+        .into());
+    }
+
+    #[cfg(feature = "astroport")]
+    let reward_token_migrated = legacy::migrate_reward_token(deps.storage)?;
+    #[cfg(not(feature = "astroport"))]
+    let reward_token_migrated = false;
+
+    initialize_missing_state(deps.storage)?;
+    cw2::set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let event = Event::new("apollo/neutron-astroport-reward-distributor/migrate")
+        .add_attribute("from_version", stored_version.version)
+        .add_attribute("to_version", CONTRACT_VERSION)
+        .add_attribute("reward_token_migrated", reward_token_migrated.to_string());
+
+    Ok(Response::default().add_event(event))
+}
+
+/// Initializes the state that is loaded on every distribution, but was not
+/// saved by earlier versions of the contract
+fn initialize_missing_state(storage: &mut dyn Storage) -> Result<(), ContractError> {
+    if !PENDING_REWARDS.exists(storage) {
+        PENDING_REWARDS.save(storage, &Uint128::zero())?;
+    }
+    if !PENDING_SINCE.exists(storage) {
+        let last_distributed = LAST_DISTRIBUTED.load(storage)?;
+        PENDING_SINCE.save(storage, &last_distributed)?;
+    }
+    if !PAUSED.exists(storage) {
+        PAUSED.save(storage, &false)?;
+    }
+    Ok(())
+}
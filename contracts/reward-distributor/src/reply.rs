@@ -0,0 +1,266 @@
+use apollo_cw_asset::AssetList;
+use cosmwasm_std::{to_json_binary, DepsMut, Env, Event, Reply, Response, StdError};
+use cw_dex::traits::Pool as PoolTrait;
+use cw_vault_standard::extensions::lockup::{
+    UNLOCKING_POSITION_ATTR_KEY, UNLOCKING_POSITION_CREATED_EVENT_TYPE,
+};
+use neutron_astroport_reward_distributor::{
+    ContractError, RewardType, CONFIG, DELIVERY_IN_PROGRESS, DESTINATION_POOL, ESCROW,
+    PENDING_TRANSFERS, PENDING_UNLOCKS, REWARD_TOKEN,
+};
+
+use crate::helpers::{
+    collect_fees, delivery_msgs, hold_assets, lp_token, provide_liquidity, query_asset_info,
+    redeem_vault_tokens, redeemed_token, reply_on_last_msg, save_balance_snapshot,
+    save_balance_snapshot_without, take_balance_diff, take_fee, withdraw_liquidity,
+};
+
+/// Reply id for when LP tokens have been received from the reward vault, by
+/// either redeeming vault tokens or withdrawing matured unlocks
+pub const VAULT_TOKENS_REDEEMED_REPLY_ID: u64 = 1;
+
+/// Reply id for when liquidity has been withdrawn from the reward pool
+pub const LP_REDEEMED_REPLY_ID: u64 = 2;
+
+/// Reply id for when unlocking vault tokens in a locked reward vault has been
+/// started
+pub const UNLOCK_STARTED_REPLY_ID: u64 = 3;
+
+/// Reply id for when the redeemed assets have been provided as liquidity to the
+/// destination pool
+pub const LIQUIDITY_PROVIDED_REPLY_ID: u64 = 4;
+
+/// Reply id for when base tokens have been redeemed from a generic reward vault
+pub const GENERIC_VAULT_TOKENS_REDEEMED_REPLY_ID: u64 = 5;
+
+/// Offset of the reply ids for when sending a delivered asset to the recipient
+/// has failed. The index of the asset in the pending transfers is added to the
+/// offset.
+pub const TRANSFER_FAILED_REPLY_ID: u64 = 1_000_000;
+
+/// Offset of the reply ids for when vault tokens of an inner vault have been
+/// received by redeeming from the vault wrapping it. The index of the inner
+/// vault is added to the offset.
+pub const INNER_VAULT_TOKENS_REDEEMED_REPLY_ID: u64 = 100;
+
+pub fn reply_vault_tokens_redeemed(mut deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let reward_pool = REWARD_TOKEN.load(deps.storage)?.into_pool()?;
+
+    // Only withdraw the LP tokens received from the redemption
+    let lp_tokens_received = take_balance_diff(deps.branch(), &env)?;
+    let lp_amount = lp_tokens_received
+        .find(&lp_token(deps.as_ref(), &reward_pool)?)
+        .map(|asset| asset.amount)
+        .unwrap_or_default();
+
+    // Snapshot the pool asset balances so that only the assets received from
+    // withdrawing liquidity are sent on in the next reply
+    let pool_assets = reward_pool.pool_assets(deps.as_ref())?;
+    save_balance_snapshot(deps.branch(), &env, pool_assets)?;
+
+    // Withdraw liquidity with the received LP tokens
+    let withdraw_res = withdraw_liquidity(deps.as_ref(), &env, &reward_pool, lp_amount)?;
+    let withdraw_res = reply_on_last_msg(withdraw_res, LP_REDEEMED_REPLY_ID)?;
+
+    let event =
+        Event::new("apollo/neutron-astroport-reward-distributor/reply_vault_tokens_redeemed")
+            .add_attribute("lp_tokens_redeemed", lp_amount);
+
+    Ok(withdraw_res.add_event(event))
+}
+
+pub fn reply_inner_vault_tokens_redeemed(
+    mut deps: DepsMut,
+    env: Env,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let (inner_vaults, pool) = match REWARD_TOKEN.load(deps.storage)? {
+        RewardType::Vault {
+            inner_vaults, pool, ..
+        } => (inner_vaults, pool),
+        _ => return Err(StdError::generic_err("Reward token has no inner vaults").into()),
+    };
+    let layer = (id - INNER_VAULT_TOKENS_REDEEMED_REPLY_ID) as usize;
+    let inner_vault = inner_vaults
+        .get(layer)
+        .ok_or(ContractError::UnknownReplyId { id })?;
+    let vault_token = inner_vault.query_vault_info(&deps.querier)?.vault_token;
+    let vault_token = query_asset_info(deps.as_ref(), &vault_token);
+
+    // Only redeem the inner vault tokens received from the redemption
+    let vault_tokens_received = take_balance_diff(deps.branch(), &env)?;
+    let vault_token_amount = vault_tokens_received
+        .find(&vault_token)
+        .map(|asset| asset.amount)
+        .unwrap_or_default();
+
+    let event =
+        Event::new("apollo/neutron-astroport-reward-distributor/reply_inner_vault_tokens_redeemed")
+            .add_attribute("inner_vault", inner_vault.addr.to_string())
+            .add_attribute("vault_tokens_redeemed", vault_token_amount);
+
+    // Vaults reject redeeming nothing, so the delivery ends here if no inner vault
+    // tokens were received
+    if vault_token_amount.is_zero() {
+        let delivery = DELIVERY_IN_PROGRESS.load(deps.storage)?;
+        return Ok(Response::new()
+            .add_event(event)
+            .set_data(to_json_binary(&delivery.into_response(AssetList::new()))?));
+    }
+
+    // Snapshot the token received from redeeming the inner vault, which is either
+    // the vault token of the next inner vault or the LP token
+    let (next_token, next_reply_id) =
+        redeemed_token(deps.as_ref(), &inner_vaults, &pool, layer + 1)?;
+    save_balance_snapshot(deps.branch(), &env, vec![next_token])?;
+
+    let redeem_res = redeem_vault_tokens(inner_vault, &vault_token, vault_token_amount)?;
+    let redeem_res = reply_on_last_msg(redeem_res, next_reply_id)?;
+
+    Ok(redeem_res.add_event(event))
+}
+
+pub fn reply_lp_redeemed(mut deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    // Only send on the assets received from withdrawing liquidity
+    let mut assets_received = take_balance_diff(deps.branch(), &env)?;
+    assets_received.purge();
+
+    // Provide the assets as liquidity to the destination pool, if any, and send on
+    // the LP tokens received in the next reply instead. The assets received are
+    // left out of the snapshot, so that any of them left over after providing
+    // liquidity are sent on as well.
+    if let Some(destination) = DESTINATION_POOL.may_load(deps.storage)? {
+        let mut snapshot_assets = vec![lp_token(deps.as_ref(), &destination.pool)?];
+        snapshot_assets.extend(assets_received.iter().map(|asset| asset.info.clone()));
+        save_balance_snapshot_without(deps.branch(), &env, snapshot_assets, &assets_received)?;
+
+        let provide_res = provide_liquidity(deps.as_ref(), &env, &destination, &assets_received)?;
+        let provide_res = reply_on_last_msg(provide_res, LIQUIDITY_PROVIDED_REPLY_ID)?;
+
+        let mut event = Event::new("apollo/neutron-astroport-reward-distributor/reply_lp_redeemed");
+        for asset in assets_received.iter() {
+            event = event.add_attribute("asset_provided", asset.to_string());
+        }
+
+        return Ok(provide_res.add_event(event));
+    }
+
+    let event = Event::new("apollo/neutron-astroport-reward-distributor/reply_lp_redeemed");
+    deliver_received_assets(deps, &env, event, assets_received)
+}
+
+pub fn reply_generic_vault_tokens_redeemed(
+    mut deps: DepsMut,
+    env: Env,
+) -> Result<Response, ContractError> {
+    // Only send on the base tokens received from the redemption. They are sent as
+    // is, as a single base token can not be provided as liquidity to the
+    // destination pool.
+    let mut assets_received = take_balance_diff(deps.branch(), &env)?;
+    assets_received.purge();
+
+    let event = Event::new(
+        "apollo/neutron-astroport-reward-distributor/reply_generic_vault_tokens_redeemed",
+    );
+    deliver_received_assets(deps, &env, event, assets_received)
+}
+
+pub fn reply_liquidity_provided(mut deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    // Send on the LP tokens received from providing liquidity, along with the
+    // withdrawn assets that were not provided
+    let mut assets_received = take_balance_diff(deps.branch(), &env)?;
+    assets_received.purge();
+
+    let event = Event::new("apollo/neutron-astroport-reward-distributor/reply_liquidity_provided");
+    deliver_received_assets(deps, &env, event, assets_received)
+}
+
+/// Ends the delivery in progress by sending the assets received in its final
+/// reply to the recipient. The fee is taken from the received assets and sent
+/// to the fee recipient.
+fn deliver_received_assets(
+    deps: DepsMut,
+    env: &Env,
+    mut event: Event,
+    mut assets_received: AssetList,
+) -> Result<Response, ContractError> {
+    let delivery = DELIVERY_IN_PROGRESS.load(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    let fee = take_fee(&config, &mut assets_received)?;
+    if let Some((_, fee)) = &fee {
+        collect_fees(deps.storage, fee)?;
+        for asset in fee.iter() {
+            event = event.add_attribute("fee_distributed", asset.to_string());
+        }
+    }
+    for asset in assets_received.iter() {
+        event = event.add_attribute("asset_distributed", asset.to_string());
+    }
+
+    let send_msgs = delivery_msgs(
+        deps.storage,
+        env.block.time.seconds(),
+        &delivery.recipient,
+        &assets_received,
+        fee,
+    )?;
+
+    Ok(Response::default()
+        .add_submessages(send_msgs)
+        .add_event(event)
+        .set_data(to_json_binary(&delivery.into_response(assets_received))?))
+}
+
+pub fn reply_transfer_failed(
+    deps: DepsMut,
+    index: usize,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    let pending = PENDING_TRANSFERS.load(deps.storage)?;
+    let (recipient, asset) = pending
+        .transfers
+        .get(index)
+        .ok_or_else(|| StdError::generic_err("No pending transfer for reply"))?;
+
+    // Hold the asset in escrow for the recipient, so that the delivery succeeds
+    let mut escrow = ESCROW
+        .may_load(deps.storage, recipient)?
+        .unwrap_or_default();
+    escrow.add(asset)?;
+    ESCROW.save(deps.storage, recipient, &escrow)?;
+    hold_assets(deps.storage, &AssetList::from(vec![asset.clone()]))?;
+
+    let error = msg.result.into_result().err().unwrap_or_default();
+    let event = Event::new("apollo/neutron-astroport-reward-distributor/reply_transfer_failed")
+        .add_attribute("recipient", recipient.to_string())
+        .add_attribute("asset_escrowed", asset.to_string())
+        .add_attribute("error", error);
+
+    Ok(Response::new().add_event(event))
+}
+
+pub fn reply_unlock_started(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let delivery = DELIVERY_IN_PROGRESS.load(deps.storage)?;
+
+    // Find the id of the created unlocking position in the vault's events
+    let res = msg.result.into_result().map_err(StdError::generic_err)?;
+    let lockup_id = res
+        .events
+        .iter()
+        .filter(|event| event.ty == format!("wasm-{}", UNLOCKING_POSITION_CREATED_EVENT_TYPE))
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == UNLOCKING_POSITION_ATTR_KEY)
+        .ok_or_else(|| StdError::generic_err("No lockup id in unlock response"))?
+        .value
+        .parse::<u64>()
+        .map_err(|e| StdError::generic_err(e.to_string()))?;
+
+    PENDING_UNLOCKS.save(deps.storage, lockup_id, &delivery.recipient)?;
+
+    let event = Event::new("apollo/neutron-astroport-reward-distributor/reply_unlock_started")
+        .add_attribute("lockup_id", lockup_id.to_string())
+        .add_attribute("recipient", delivery.recipient);
+
+    Ok(Response::new().add_event(event))
+}
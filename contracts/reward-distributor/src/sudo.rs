@@ -0,0 +1,42 @@
+use cosmwasm_std::{DepsMut, Env, Response};
+use neutron_astroport_reward_distributor::{ConfigUpdates, ContractError};
+
+use crate::execute::{apply_config_update, emergency_withdraw, pause, unpause};
+
+/// The sender recorded in events for actions taken through sudo
+pub const SUDO_SENDER: &str = "sudo";
+
+pub fn sudo_update_config(
+    deps: DepsMut,
+    env: Env,
+    updates: ConfigUpdates,
+    ramp_duration: Option<u64>,
+) -> Result<Response, ContractError> {
+    // Governance proposals are delayed by the voting period already, so the
+    // config update delay does not apply
+    apply_config_update(deps, env, updates, ramp_duration)
+}
+
+pub fn sudo_transfer_ownership(
+    deps: DepsMut,
+    new_owner: String,
+) -> Result<Response, ContractError> {
+    let ownership = cw_ownable::initialize_owner(deps.storage, deps.api, Some(&new_owner))?;
+    Ok(Response::default().add_attributes(ownership.into_attributes()))
+}
+
+pub fn sudo_pause(deps: DepsMut) -> Result<Response, ContractError> {
+    pause(deps, SUDO_SENDER)
+}
+
+pub fn sudo_unpause(deps: DepsMut) -> Result<Response, ContractError> {
+    unpause(deps, SUDO_SENDER)
+}
+
+pub fn sudo_emergency_withdraw(
+    deps: DepsMut,
+    env: Env,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    emergency_withdraw(deps, env, recipient, SUDO_SENDER)
+}
@@ -1,12 +1,14 @@
 use common::get_test_runner;
-use cosmwasm_std::Uint128;
+use cosmwasm_std::{coin, Decimal256, Uint128};
 use cw_it::helpers::Unwrap;
 use cw_it::robot::TestRobot;
 use cw_it::test_tube::Account;
 use cw_it::traits::CwItRunner;
 
+use locked_astroport_vault_test_helpers::cw_vault_standard_test_helpers::traits::CwVaultStandardRobot;
 use locked_astroport_vault_test_helpers::robot::LockedAstroportVaultRobot;
-use neutron_astroport_reward_distributor::{ConfigUpdates, ExecuteMsg, InternalMsg};
+use neutron_astroport_reward_distributor::msg::SudoMsg;
+use neutron_astroport_reward_distributor::{ConfigUpdates, DistributionMode, Role};
 use neutron_astroport_reward_distributor_test_helpers as test_helpers;
 
 use test_helpers::robot::{RewardDistributorRobot, TestRewardType};
@@ -77,7 +79,7 @@ fn update_config_can_only_be_called_by_admin() {
     let user = runner.init_default_account().unwrap();
 
     let mut config_updates = ConfigUpdates::default();
-    config_updates.emission_per_second(Uint128::new(420));
+    config_updates.emission_per_second(Decimal256::from_ratio(420u128, 1u128));
 
     // Try calling update_config as non-admin, should fail. Then try calling as
     // admin, should work.
@@ -91,7 +93,7 @@ fn update_config_can_only_be_called_by_admin() {
 }
 
 #[test]
-fn internal_msg_can_only_be_called_by_contract() {
+fn config_update_proposals_can_only_be_managed_by_admin() {
     let owned_runner = get_test_runner();
     let runner = owned_runner.as_ref();
     let admin = RewardDistributorRobot::default_account(&runner);
@@ -111,25 +113,449 @@ fn internal_msg_can_only_be_called_by_contract() {
         rewards_start_time,
     );
 
-    let err = robot
-        .wasm()
-        .execute(
-            &robot.reward_distributor_addr,
-            &ExecuteMsg::Internal(InternalMsg::LpRedeemed {}),
-            &[],
-            &admin,
+    let user = runner.init_default_account().unwrap();
+
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::from_ratio(420u128, 1u128));
+
+    // Try proposing, applying and cancelling as non-admin, should fail. Then try
+    // as admin, should work.
+    robot
+        .propose_config_update(
+            config_updates.clone(),
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &user,
+        )
+        .propose_config_update(config_updates.clone(), Unwrap::Ok, &admin)
+        .apply_config_update(
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &user,
+        )
+        .apply_config_update(Unwrap::Ok, &admin)
+        .propose_config_update(config_updates, Unwrap::Ok, &admin)
+        .cancel_config_update(
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &user,
         )
-        .unwrap_err();
-    assert!(err.to_string().contains("Unauthorized"));
+        .cancel_config_update(Unwrap::Ok, &admin);
+}
 
-    let err = robot
-        .wasm()
-        .execute(
-            &robot.reward_distributor_addr,
-            &ExecuteMsg::Internal(InternalMsg::VaultTokensRedeemed {}),
-            &[],
+#[test]
+fn roles_can_only_be_granted_and_revoked_by_admin() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+
+    let user = runner.init_default_account().unwrap();
+
+    robot
+        .grant_role(
+            Role::Operator,
+            user.address(),
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &user,
+        )
+        .grant_role(Role::Operator, user.address(), Unwrap::Ok, &admin)
+        .grant_role(Role::Guardian, user.address(), Unwrap::Ok, &admin);
+    assert_eq!(
+        robot.query_roles(user.address()),
+        vec![Role::Operator, Role::Guardian]
+    );
+
+    robot
+        .revoke_role(
+            Role::Operator,
+            user.address(),
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &user,
+        )
+        .revoke_role(Role::Operator, user.address(), Unwrap::Ok, &admin);
+    assert_eq!(robot.query_roles(user.address()), vec![Role::Guardian]);
+}
+
+#[test]
+fn roles_can_only_update_their_config_fields() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100u128;
+    let init_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let rewards_start_time = init_time + 1_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+
+    let operator = runner.init_default_account().unwrap();
+    let recipient_manager = runner.init_default_account().unwrap();
+    robot
+        .grant_role(Role::Operator, operator.address(), Unwrap::Ok, &admin)
+        .grant_role(
+            Role::RecipientManager,
+            recipient_manager.address(),
+            Unwrap::Ok,
             &admin,
+        );
+
+    let mut rate_update = ConfigUpdates::default();
+    rate_update.emission_per_second(Decimal256::from_ratio(420u128, 1u128));
+    let mut recipient_update = ConfigUpdates::default();
+    recipient_update.distribution_addr(recipient_manager.address());
+    let mut rate_and_recipient_update = rate_update.clone();
+    rate_and_recipient_update.distribution_addr(recipient_manager.address());
+
+    // The operator can only update the emission rate and the recipient manager
+    // only the distribution address
+    robot
+        .update_config(
+            recipient_update.clone(),
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &operator,
+        )
+        .update_config(
+            rate_and_recipient_update.clone(),
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &operator,
+        )
+        .update_config(rate_update.clone(), Unwrap::Ok, &operator)
+        .update_config(
+            rate_update,
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &recipient_manager,
+        )
+        .update_config(
+            rate_and_recipient_update,
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &recipient_manager,
+        )
+        .update_config(recipient_update, Unwrap::Ok, &recipient_manager)
+        .accept_recipient(Unwrap::Ok, &recipient_manager);
+
+    let config = robot.query_config();
+    assert_eq!(
+        config.emission_per_second,
+        Decimal256::from_ratio(420u128, 1u128)
+    );
+    assert_eq!(config.distribution_addr, recipient_manager.address());
+}
+
+#[test]
+fn guardian_can_pause_but_only_admin_can_unpause() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+
+    let guardian = runner.init_default_account().unwrap();
+    let user = runner.init_default_account().unwrap();
+    robot
+        .pause(
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &guardian,
+        )
+        .grant_role(Role::Guardian, guardian.address(), Unwrap::Ok, &admin)
+        .pause(
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &user,
+        )
+        .pause(Unwrap::Ok, &guardian)
+        .unpause(
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &guardian,
         )
-        .unwrap_err();
-    assert!(err.to_string().contains("Unauthorized"));
+        .unpause(Unwrap::Ok, &admin);
+}
+
+#[test]
+fn governance_can_act_through_sudo_after_owner_renounced() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+
+    // Renounce ownership, so that the owner paths can no longer be used
+    robot
+        .update_ownership(cw_ownable::Action::RenounceOwnership, Unwrap::Ok, &admin)
+        .pause(Unwrap::Err("Contract ownership has been renounced"), &admin);
+
+    // Governance can still pause and unpause
+    robot.sudo(&SudoMsg::Pause {}, Unwrap::Ok);
+    assert!(robot.query_state().paused);
+    robot.sudo(&SudoMsg::Unpause {}, Unwrap::Ok);
+    assert!(!robot.query_state().paused);
+
+    // Config updates through sudo are validated like the owner's
+    let mut invalid_updates = ConfigUpdates::default();
+    invalid_updates.min_emission_per_second(Decimal256::from_ratio(1000u128, 1u128));
+    let mut updates = ConfigUpdates::default();
+    updates.emission_per_second(Decimal256::from_ratio(200u128, 1u128));
+    robot
+        .sudo(
+            &SudoMsg::UpdateConfig {
+                updates: invalid_updates,
+                ramp_duration: None,
+            },
+            Unwrap::Err("is outside of the bounds"),
+        )
+        .sudo(
+            &SudoMsg::UpdateConfig {
+                updates,
+                ramp_duration: None,
+            },
+            Unwrap::Ok,
+        );
+    assert_eq!(
+        robot.query_config().emission_per_second,
+        Decimal256::from_ratio(200u128, 1u128)
+    );
+
+    // Governance can appoint a new owner, who can use the owner paths again
+    let user = runner.init_default_account().unwrap();
+    robot.sudo(
+        &SudoMsg::TransferOwnership {
+            new_owner: user.address(),
+        },
+        Unwrap::Ok,
+    );
+    assert_eq!(robot.query_ownership().owner.unwrap(), user.address());
+    robot.pause(Unwrap::Ok, &user).unpause(Unwrap::Ok, &user);
+}
+
+#[test]
+fn emergency_withdraw_through_sudo_sends_reward_tokens_and_pauses() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+
+    let vault_robot = &robot.reward_vault_robot;
+    let deposit_amount = vault_robot.query_base_token_balance(admin.address()) / Uint128::new(10);
+    let vault_token_balance = robot
+        .deposit_to_distributor(deposit_amount, Unwrap::Ok, &admin)
+        .reward_vault_robot
+        .query_vault_token_balance(&robot.reward_distributor_addr);
+
+    let recipient = runner.init_account(&[]).unwrap();
+    robot.sudo(
+        &SudoMsg::EmergencyWithdraw {
+            recipient: recipient.address(),
+        },
+        Unwrap::Ok,
+    );
+
+    vault_robot
+        .assert_vault_token_balance_eq(&robot.reward_distributor_addr, Uint128::zero())
+        .assert_vault_token_balance_eq(recipient.address(), vault_token_balance);
+    assert!(robot.query_state().paused);
+}
+
+#[test]
+fn emergency_withdraw_keeps_rewards_owed_to_previous_recipients() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::NativeCoin("uaxl".to_string()),
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    let deposit_amount = 1_000_000u128;
+    robot.deposit_to_distributor(Uint128::new(deposit_amount), Unwrap::Ok, &admin);
+
+    // The rewards accrued in pull mode are left unclaimed by the previous
+    // recipient once the recipient changes
+    let time_elapsed = 1000u64;
+    let accrued = emission_per_second * time_elapsed as u128;
+    let new_recipient = runner.init_account(&[]).unwrap();
+    let mut updates = ConfigUpdates::default();
+    updates.distribution_mode(DistributionMode::Pull);
+    robot.update_config(updates, Unwrap::Ok, &admin);
+    let mut updates = ConfigUpdates::default();
+    updates.distribution_addr(new_recipient.address());
+    robot
+        .increase_time(time_elapsed)
+        .update_config(updates, Unwrap::Ok, &admin)
+        .accept_recipient(Unwrap::Ok, &new_recipient)
+        .increase_time(time_elapsed);
+
+    // Only the owner can withdraw, and the unclaimed rewards are not withdrawn.
+    // The rewards accrued for the new recipient are forfeited.
+    let user = runner.init_default_account().unwrap();
+    let withdrawal_recipient = runner.init_account(&[]).unwrap();
+    robot
+        .emergency_withdraw(
+            withdrawal_recipient.address(),
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &user,
+        )
+        .emergency_withdraw(withdrawal_recipient.address(), Unwrap::Ok, &admin);
+    let state = robot.query_state();
+    assert!(state.paused);
+    assert!(state.pending_rewards.is_zero());
+    robot.assert_native_token_balance_eq(
+        withdrawal_recipient.address(),
+        "uaxl",
+        Uint128::new(deposit_amount - accrued),
+    );
+
+    // The previous recipient can still claim its rewards once unpaused
+    robot
+        .unpause(Unwrap::Ok, &admin)
+        .claim(Unwrap::Ok, &robot.distribution_acc)
+        .assert_distribution_acc_balances_eq(&[coin(accrued, "uaxl")]);
+}
+
+#[test]
+fn recipient_change_only_takes_effect_once_accepted() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::NativeCoin("uaxl".to_string()),
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    robot.deposit_to_distributor(Uint128::new(1_000_000), Unwrap::Ok, &admin);
+    let current_recipient = robot.distribution_acc.address();
+    let new_recipient = runner.init_default_account().unwrap();
+    let user = runner.init_default_account().unwrap();
+
+    // Proposing a new recipient does not change the distribution address
+    let mut recipient_update = ConfigUpdates::default();
+    recipient_update.distribution_addr(new_recipient.address());
+    robot.update_config(recipient_update.clone(), Unwrap::Ok, &admin);
+    let state = robot.query_state();
+    assert_eq!(state.config.distribution_addr, current_recipient);
+    assert_eq!(
+        state.pending_recipient.unwrap().to_string(),
+        new_recipient.address()
+    );
+
+    // Proposing the current recipient cancels the change
+    let mut cancel_update = ConfigUpdates::default();
+    cancel_update.distribution_addr(current_recipient.clone());
+    robot
+        .update_config(cancel_update, Unwrap::Ok, &admin)
+        .accept_recipient(
+            Unwrap::Err("There is no pending distribution address to accept"),
+            &new_recipient,
+        );
+    assert_eq!(robot.query_state().pending_recipient, None);
+
+    // Only the pending recipient can accept. Rewards accrued until then go to the
+    // current recipient.
+    robot
+        .update_config(recipient_update, Unwrap::Ok, &admin)
+        .increase_time(100)
+        .accept_recipient(Unwrap::Err("Unauthorized"), &user)
+        .accept_recipient(Unwrap::Err("Unauthorized"), &admin)
+        .accept_recipient(Unwrap::Ok, &new_recipient)
+        .assert_distribution_acc_balances_eq(&[coin(100 * emission_per_second, "uaxl")]);
+    let state = robot.query_state();
+    assert_eq!(state.config.distribution_addr, new_recipient.address());
+    assert_eq!(state.pending_recipient, None);
+
+    // Rewards accrued while paused are set aside for the previous recipient to
+    // claim once unpaused
+    let mut recipient_update = ConfigUpdates::default();
+    recipient_update.distribution_addr(current_recipient.clone());
+    let balance_before = robot.query_native_token_balance(new_recipient.address(), "uaxl");
+    robot
+        .pause(Unwrap::Ok, &admin)
+        .increase_time(100)
+        .update_config(recipient_update, Unwrap::Ok, &admin)
+        .accept_recipient(Unwrap::Ok, &robot.distribution_acc)
+        .unpause(Unwrap::Ok, &admin)
+        .claim(Unwrap::Ok, &new_recipient);
+    assert_eq!(
+        robot.query_native_token_balance(new_recipient.address(), "uaxl"),
+        balance_before + Uint128::new(100 * emission_per_second)
+    );
+    assert_eq!(robot.query_state().pending_rewards, Uint128::zero());
 }
@@ -1,16 +1,34 @@
+use apollo_cw_asset::{Asset, AssetInfo, AssetInfoUnchecked, AssetList};
 use common::get_test_runner;
-use cosmwasm_std::{coin, Uint128};
+use cosmwasm_std::{
+    coin, from_json, to_json_binary, to_json_string, Addr, Binary, CosmosMsg, Decimal, Decimal256,
+    Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use cw_dex::Pool;
+use cw_it::astroport::astroport::asset::{AssetInfo as AstroportAssetInfo, PairInfo};
+use cw_it::astroport::astroport::factory::QueryMsg as FactoryQueryMsg;
 use cw_it::helpers::Unwrap;
+use cw_it::osmosis_std::types::cosmwasm::wasm::v1::MsgMigrateContractResponse;
 use cw_it::robot::TestRobot;
-use cw_it::test_tube::Account;
+use cw_it::test_tube::{Account, Module, Runner, SigningAccount, Wasm};
 use cw_it::traits::CwItRunner;
 use locked_astroport_vault::helpers::INITIAL_VAULT_TOKENS_PER_BASE_TOKEN;
 use locked_astroport_vault_test_helpers::cw_vault_standard_test_helpers::traits::CwVaultStandardRobot;
 use locked_astroport_vault_test_helpers::robot::LockedAstroportVaultRobot;
-use neutron_astroport_reward_distributor::RewardType;
+use neutron_astroport_reward_distributor::msg::{
+    AstroportVault, DestinationPoolInfo, DistributeResponse, LpToken, StakeChangedHookMsg,
+    StateResponse,
+};
+use neutron_astroport_reward_distributor::{
+    ConfigUpdates, Delivery, DistributionMode, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg,
+    RewardInfo, RewardType, VestingPosition, VestingSchedule,
+};
 use neutron_astroport_reward_distributor_test_helpers as test_helpers;
+use sha2::{Digest, Sha256};
 
 use test_helpers::robot::{RewardDistributorRobot, TestRewardType};
+use test_helpers::{mock_cw20, mock_legacy_state, mock_staking, mock_vault};
 
 use crate::common::{DEPS_PATH, UNOPTIMIZED_PATH};
 
@@ -39,11 +57,14 @@ fn test_initialization() {
     // Query contract state
     let state = robot.query_state();
     let config = state.config;
-    assert_eq!(config.emission_per_second, Uint128::from(1000000u128));
+    assert_eq!(
+        config.emission_per_second,
+        Decimal256::from_ratio(1000000u128, 1u128)
+    );
     assert_eq!(config.distribution_addr, robot.distribution_acc.address());
     assert!(matches!(
         state.reward_token,
-        RewardType::Vault { vault, pool } if vault.addr == robot.reward_vault_robot.vault_addr && pool.lp_token_addr == robot.reward_vault_robot.base_token()
+        RewardType::Vault { vault, inner_vaults, pool: Pool::Astroport(pool) } if vault.addr == robot.reward_vault_robot.vault_addr && inner_vaults.is_empty() && pool.lp_token_addr == robot.reward_vault_robot.base_token()
     ));
 
     // Query ownership
@@ -310,3 +331,2293 @@ fn distribute_does_not_error_when_distributed_vault_token_amount_would_give_zero
     // no rewards were distributed
     vault_robot.assert_vault_token_balance_eq(robot.reward_distributor_addr, vault_token_balance);
 }
+
+#[test]
+fn distribute_only_sends_assets_received_from_redeeming_rewards() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100_000u128;
+    let init_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let rewards_start_time = init_time + 5;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+
+    let vault_robot = &robot.reward_vault_robot;
+
+    // Deposit to vault and send vault tokens to reward distributor
+    let base_token_balance = vault_robot.query_base_token_balance(admin.address());
+    let deposit_amount = base_token_balance / Uint128::new(10);
+    robot.deposit_to_distributor(deposit_amount, Unwrap::Ok, &admin);
+
+    // Send unrelated LP tokens and pool assets to the reward distributor. These
+    // should not be swept up when distributing.
+    let unrelated_amount = Uint128::new(1_000_000);
+    robot
+        .wasm()
+        .execute(
+            &vault_robot.base_token(),
+            &Cw20ExecuteMsg::Transfer {
+                recipient: robot.reward_distributor_addr.clone(),
+                amount: unrelated_amount,
+            },
+            &[],
+            &admin,
+        )
+        .unwrap();
+    robot
+        .send_native_tokens(
+            &admin,
+            &robot.reward_distributor_addr,
+            unrelated_amount,
+            "uaxl",
+        )
+        .send_native_tokens(
+            &admin,
+            &robot.reward_distributor_addr,
+            unrelated_amount,
+            "untrn",
+        );
+
+    // Distribute rewards and check that only the redeemed rewards were sent
+    let time_elapsed = 1000u64;
+    let expected_amount =
+        (emission_per_second * time_elapsed as u128) / INITIAL_VAULT_TOKENS_PER_BASE_TOKEN.u128();
+    robot
+        .increase_time(5 + time_elapsed)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[
+            coin(expected_amount, "uaxl"),
+            coin(expected_amount, "untrn"),
+        ]);
+
+    // The unrelated funds should still be in the reward distributor
+    vault_robot
+        .assert_base_token_balance_eq(&robot.reward_distributor_addr, unrelated_amount)
+        .assert_native_token_balance_eq(&robot.reward_distributor_addr, "uaxl", unrelated_amount)
+        .assert_native_token_balance_eq(&robot.reward_distributor_addr, "untrn", unrelated_amount);
+}
+
+#[test]
+fn rewards_emitted_while_paused_are_distributed_after_unpausing() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100_000u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let reward_token_denom = "uaxl";
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::NativeCoin(reward_token_denom.to_string()),
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    robot.deposit_to_distributor(Uint128::new(1_000_000_000), Unwrap::Ok, &admin);
+
+    // Distribution fails while paused
+    robot
+        .pause(Unwrap::Ok, &admin)
+        .increase_time(100)
+        .distribute(Unwrap::Err("Distribution is paused"), &admin)
+        .assert_distribution_acc_balances_eq(&[]);
+
+    // Changing the emission rate while paused accrues the rewards emitted at the
+    // old rate
+    let new_emission_per_second = 2 * emission_per_second;
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::from_ratio(new_emission_per_second, 1u128));
+    robot.update_config(config_updates, Unwrap::Ok, &admin);
+    assert_eq!(
+        robot.query_state().pending_rewards,
+        Uint128::new(100 * emission_per_second)
+    );
+
+    // All rewards are distributed after unpausing
+    robot
+        .increase_time(100)
+        .unpause(Unwrap::Ok, &admin)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[coin(
+            100 * emission_per_second + 100 * new_emission_per_second,
+            reward_token_denom,
+        )]);
+    assert_eq!(robot.query_state().pending_rewards, Uint128::zero());
+}
+
+#[test]
+fn fractional_emission_rate_carries_remainder_between_distributions() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let reward_token_denom = "uaxl";
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::NativeCoin(reward_token_denom.to_string()),
+        &admin,
+        0u128,
+        rewards_start_time,
+    );
+    robot.deposit_to_distributor(Uint128::new(1_000_000), Unwrap::Ok, &admin);
+
+    // Emit 0.3 tokens per second
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::permille(300));
+    robot.update_config(config_updates, Unwrap::Ok, &admin);
+
+    // Only whole tokens are distributed, the remaining half token is carried over
+    robot
+        .increase_time(5)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[coin(1, reward_token_denom)]);
+
+    // The carried over half token is distributed together with the next rewards
+    robot
+        .increase_time(5)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[coin(3, reward_token_denom)]);
+
+    // Distributing more often does not lose any rewards
+    for _ in 0..10 {
+        robot.increase_time(1).distribute(Unwrap::Ok, &admin);
+    }
+    robot.assert_distribution_acc_balances_eq(&[coin(6, reward_token_denom)]);
+}
+
+#[test]
+fn instantiate_with_explicit_reward_pool_checks_vault_base_token() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        1_000_000u128,
+        rewards_start_time,
+    );
+
+    // Find a pool that the reward vault does not hold liquidity in
+    let wasm = Wasm::new(&runner);
+    let other_pair: PairInfo = wasm
+        .query(
+            &robot.astroport_contracts.factory.address,
+            &FactoryQueryMsg::Pair {
+                asset_infos: vec![
+                    AstroportAssetInfo::NativeToken {
+                        denom: "uastro".to_string(),
+                    },
+                    AstroportAssetInfo::NativeToken {
+                        denom: "untrn".to_string(),
+                    },
+                ],
+            },
+        )
+        .unwrap();
+
+    let code_id = runner
+        .store_code(
+            RewardDistributorRobot::contract(&runner, UNOPTIMIZED_PATH),
+            &admin,
+        )
+        .unwrap();
+    let instantiate_msg = |pool_addr: String| InstantiateMsg {
+        owner: admin.address(),
+        guardian: None,
+        emission_per_second: Decimal256::from_ratio(1_000_000u128, 1u128),
+        reward_token_info: RewardInfo::AstroportVault(AstroportVault {
+            vault_addr: robot.reward_vault_robot.vault_addr(),
+            pool_addr: Some(pool_addr),
+            liquidity_manager_addr: robot.astroport_contracts.liquidity_manager.address.clone(),
+        }),
+        distribution_addr: robot.distribution_acc.address(),
+        rewards_start_time,
+        config_update_delay: 0,
+        instant_rate_decreases: false,
+        min_emission_per_second: Decimal256::zero(),
+        max_emission_per_second: Decimal256::MAX,
+        max_rate_change: Decimal::zero(),
+        rate_change_window: 0,
+        delivery: Delivery::Underlying,
+        distribution_mode: DistributionMode::Push,
+        fee_bps: 0,
+        fee_recipient: None,
+        recipient_code_id: None,
+        staking_addr: None,
+        destination_pool: None,
+    };
+
+    // Instantiating with the pool of the vault's base token succeeds
+    wasm.instantiate(
+        code_id,
+        &instantiate_msg(robot.reward_pool.pair_addr.to_string()),
+        None,
+        None,
+        &[],
+        &admin,
+    )
+    .unwrap();
+
+    // Instantiating with any other pool fails
+    let err = wasm
+        .instantiate(
+            code_id,
+            &instantiate_msg(other_pair.contract_addr.to_string()),
+            None,
+            None,
+            &[],
+            &admin,
+        )
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Invalid base token of reward vault"));
+}
+
+#[test]
+fn distribute_unlocks_rewards_from_locked_vault_and_withdraws_matured_unlocks() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100_000u128;
+    let lock_duration = 1_000u64;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::LockedVaultToken(lock_duration),
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    let base_token_balance = robot
+        .reward_vault_robot
+        .query_base_token_balance(admin.address());
+    robot.deposit_to_distributor(base_token_balance / Uint128::new(10), Unwrap::Ok, &admin);
+
+    // Distributing starts unlocking the rewards, but nothing is sent yet
+    let first_unlock_time = 100u64;
+    let first_unlock_amount = emission_per_second * first_unlock_time as u128
+        / INITIAL_VAULT_TOKENS_PER_BASE_TOKEN.u128();
+    robot
+        .increase_time(first_unlock_time)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[]);
+    let pending_unlocks = robot.query_pending_unlocks();
+    let first_unlock_id = pending_unlocks[0].id;
+    assert_eq!(pending_unlocks.len(), 1);
+    assert_eq!(
+        pending_unlocks[0].base_token_amount,
+        Uint128::new(first_unlock_amount)
+    );
+    assert_eq!(robot.query_state().pending_rewards, Uint128::zero());
+
+    // Distributing before the unlock has matured does not withdraw it
+    robot
+        .increase_time(lock_duration / 2)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[]);
+    assert_eq!(robot.query_pending_unlocks().len(), 2);
+
+    // Once matured, the unlock is withdrawn and sent on, while a new unlock is
+    // started for the rewards emitted since the last distribution
+    robot
+        .increase_time(lock_duration / 2)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[
+            coin(first_unlock_amount, "uaxl"),
+            coin(first_unlock_amount, "untrn"),
+        ]);
+    let pending_unlocks = robot.query_pending_unlocks();
+    assert_eq!(pending_unlocks.len(), 2);
+    assert!(pending_unlocks
+        .iter()
+        .all(|position| position.id != first_unlock_id));
+
+    // Unlocks are only withdrawn when delivering the underlying assets, so raw
+    // delivery can not be switched to while they are pending
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.delivery(Delivery::Raw);
+    robot.update_config(
+        config_updates,
+        Unwrap::Err("Cannot switch to raw delivery while unlocks of the reward vault are pending"),
+        &admin,
+    );
+
+    // Unlocks are withdrawn to the recipient they were started for. After the
+    // recipient changes, the previous recipient claims its unclaimed rewards by
+    // unlocking them, and withdraws its unlocks once matured.
+    let new_recipient = runner.init_account(&[]).unwrap();
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.distribution_mode(DistributionMode::Pull);
+    robot.update_config(config_updates, Unwrap::Ok, &admin);
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.distribution_addr(new_recipient.address());
+    robot
+        .increase_time(first_unlock_time)
+        .update_config(config_updates, Unwrap::Ok, &admin)
+        .accept_recipient(Unwrap::Ok, &new_recipient)
+        .claim(Unwrap::Ok, &robot.distribution_acc);
+    assert_eq!(robot.query_pending_unlocks().len(), 3);
+    robot
+        .increase_time(lock_duration)
+        .claim(Unwrap::Ok, &new_recipient);
+    assert!(robot
+        .query_native_token_balance(new_recipient.address(), "uaxl")
+        .is_zero());
+    assert_eq!(robot.query_pending_unlocks().len(), 4);
+    // The withdrawn pool assets are rounded down, so the balances are just
+    // below the twelve first unlocks' worth of rewards
+    robot
+        .claim(Unwrap::Ok, &robot.distribution_acc)
+        .assert_distribution_acc_balances_gt(&[
+            coin(first_unlock_amount * 11, "uaxl"),
+            coin(first_unlock_amount * 11, "untrn"),
+        ])
+        .claim(Unwrap::Err("Unauthorized"), &robot.distribution_acc);
+    assert_eq!(robot.query_pending_unlocks().len(), 1);
+}
+
+#[test]
+fn generic_vault_distributes_base_tokens_directly() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100_000u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::GenericVaultToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    let vault_robot = &robot.reward_vault_robot;
+
+    // The vault's CW20 LP token is treated as a plain CW20 base token
+    let state = robot.query_state();
+    assert!(matches!(
+        state.reward_token,
+        RewardType::GenericVault { vault, base_token: AssetInfo::Cw20(base_token) }
+            if vault.addr == vault_robot.vault_addr && base_token == vault_robot.base_token()
+    ));
+
+    let base_token_balance = vault_robot.query_base_token_balance(admin.address());
+    robot.deposit_to_distributor(base_token_balance / Uint128::new(10), Unwrap::Ok, &admin);
+
+    // The redeemed LP tokens are sent to the distribution address without
+    // withdrawing liquidity
+    let time_elapsed = 1000u64;
+    robot
+        .increase_time(time_elapsed)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[]);
+    vault_robot.assert_base_token_balance_eq(
+        robot.distribution_acc.address(),
+        emission_per_second * time_elapsed as u128 / INITIAL_VAULT_TOKENS_PER_BASE_TOKEN.u128(),
+    );
+}
+
+#[test]
+fn instantiate_with_lp_token_finds_pool() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::LpToken,
+        &admin,
+        1_000_000u128,
+        rewards_start_time,
+    );
+
+    let code_id = runner
+        .store_code(
+            RewardDistributorRobot::contract(&runner, UNOPTIMIZED_PATH),
+            &admin,
+        )
+        .unwrap();
+    let msg = InstantiateMsg {
+        owner: admin.address(),
+        guardian: None,
+        emission_per_second: Decimal256::from_ratio(1_000_000u128, 1u128),
+        reward_token_info: RewardInfo::LpToken(LpToken {
+            lp_token: AssetInfoUnchecked::Cw20(robot.reward_pool.lp_token_addr.to_string()),
+            liquidity_manager_addr: Some(
+                robot.astroport_contracts.liquidity_manager.address.clone(),
+            ),
+        }),
+        distribution_addr: robot.distribution_acc.address(),
+        rewards_start_time,
+        config_update_delay: 0,
+        instant_rate_decreases: false,
+        min_emission_per_second: Decimal256::zero(),
+        max_emission_per_second: Decimal256::MAX,
+        max_rate_change: Decimal::zero(),
+        rate_change_window: 0,
+        delivery: Delivery::Underlying,
+        distribution_mode: DistributionMode::Push,
+        fee_bps: 0,
+        fee_recipient: None,
+        recipient_code_id: None,
+        staking_addr: None,
+        destination_pool: None,
+    };
+    let wasm = Wasm::new(&runner);
+    let contract_addr = wasm
+        .instantiate(code_id, &msg, None, None, &[], &admin)
+        .unwrap()
+        .data
+        .address;
+
+    // The pool is found from the LP token and stored the same as when given the
+    // Astroport pool directly
+    let state: StateResponse = wasm.query(&contract_addr, &QueryMsg::State {}).unwrap();
+    assert_eq!(
+        state.reward_token,
+        RewardType::LP(Pool::Astroport(robot.reward_pool.clone()))
+    );
+}
+
+#[test]
+fn migrate_rewrites_legacy_reward_token() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        1_000u128,
+        rewards_start_time,
+    );
+    let vault = match robot.query_state().reward_token {
+        RewardType::Vault { vault, .. } => vault,
+        reward_token => panic!("Unexpected reward token {:?}", reward_token),
+    };
+
+    // Set up state as saved before reward pools were stored as cw-dex pools
+    let legacy_config = format!(
+        r#"{{"emission_per_second":"1000","distribution_addr":"{}","rewards_start_time":{}}}"#,
+        robot.distribution_acc.address(),
+        rewards_start_time
+    );
+    let legacy_reward_token = format!(
+        r#"{{"vault":{{"vault":{},"pool":{}}}}}"#,
+        to_json_string(&vault).unwrap(),
+        to_json_string(&robot.reward_pool).unwrap()
+    );
+    let contract_info = format!(
+        r#"{{"contract":"{}","version":"0.1.0"}}"#,
+        neutron_astroport_reward_distributor_contract::contract::CONTRACT_NAME
+    );
+    let entries = [
+        ("contract_info", contract_info),
+        ("config", legacy_config),
+        ("reward_token", legacy_reward_token),
+        ("last_distributed", rewards_start_time.to_string()),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), Binary::from(value.into_bytes())))
+    .collect();
+    let wasm = Wasm::new(&runner);
+    let legacy_code_id = runner
+        .store_code(mock_legacy_state::contract(), &admin)
+        .unwrap();
+    let contract_addr = wasm
+        .instantiate(
+            legacy_code_id,
+            &mock_legacy_state::InstantiateMsg { entries },
+            Some(&admin.address()),
+            None,
+            &[],
+            &admin,
+        )
+        .unwrap()
+        .data
+        .address;
+
+    // Migrating rewrites the reward pool and initializes the missing state
+    let code_id = runner
+        .store_code(
+            RewardDistributorRobot::contract(&runner, UNOPTIMIZED_PATH),
+            &admin,
+        )
+        .unwrap();
+    runner
+        .execute_cosmos_msgs::<MsgMigrateContractResponse>(
+            &[CosmosMsg::Wasm(WasmMsg::Migrate {
+                contract_addr: contract_addr.clone(),
+                new_code_id: code_id,
+                msg: to_json_binary(&MigrateMsg {}).unwrap(),
+            })],
+            &admin,
+        )
+        .unwrap();
+
+    let state: StateResponse = wasm.query(&contract_addr, &QueryMsg::State {}).unwrap();
+    assert_eq!(
+        state.reward_token,
+        RewardType::Vault {
+            vault,
+            inner_vaults: vec![],
+            pool: Pool::Astroport(robot.reward_pool.clone()),
+        }
+    );
+    assert_eq!(
+        state.config.emission_per_second,
+        Decimal256::from_ratio(1_000u128, 1u128)
+    );
+    assert_eq!(state.config.max_emission_per_second, Decimal256::MAX);
+    assert_eq!(state.pending_rewards, Uint128::zero());
+    assert!(!state.paused);
+}
+
+#[test]
+fn distribute_redeems_nested_vaults_layer_by_layer() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100_000u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    let inner_vault_robot = &robot.reward_vault_robot;
+    let inner_vault_token = inner_vault_robot.vault_token();
+
+    // Create an outer vault wrapping the reward vault and fund it with inner vault
+    // tokens to redeem
+    let wasm = Wasm::new(&runner);
+    let mock_vault_code_id = runner.store_code(mock_vault::contract(), &admin).unwrap();
+    let outer_vault_token = "uosmo";
+    let outer_vault_addr = wasm
+        .instantiate(
+            mock_vault_code_id,
+            &mock_vault::InstantiateMsg {
+                base_token: inner_vault_token.clone(),
+                vault_token: outer_vault_token.to_string(),
+            },
+            None,
+            None,
+            &[],
+            &admin,
+        )
+        .unwrap()
+        .data
+        .address;
+    let base_token_balance = inner_vault_robot.query_base_token_balance(admin.address());
+    let deposit_amount = base_token_balance / Uint128::new(10);
+    inner_vault_robot
+        .deposit_cw20(deposit_amount, None, Unwrap::Ok, &admin)
+        .send_native_tokens(
+            &admin,
+            &outer_vault_addr,
+            deposit_amount * INITIAL_VAULT_TOKENS_PER_BASE_TOKEN,
+            &inner_vault_token,
+        );
+
+    // Instantiate a reward distributor for the outer vault
+    let code_id = runner
+        .store_code(
+            RewardDistributorRobot::contract(&runner, UNOPTIMIZED_PATH),
+            &admin,
+        )
+        .unwrap();
+    let distribution_acc = runner.init_account(&[]).unwrap();
+    let msg = InstantiateMsg {
+        owner: admin.address(),
+        guardian: None,
+        emission_per_second: Decimal256::from_ratio(emission_per_second, 1u128),
+        reward_token_info: RewardInfo::AstroportVault(AstroportVault {
+            vault_addr: outer_vault_addr.clone(),
+            pool_addr: None,
+            liquidity_manager_addr: robot.astroport_contracts.liquidity_manager.address.clone(),
+        }),
+        distribution_addr: distribution_acc.address(),
+        rewards_start_time,
+        config_update_delay: 0,
+        instant_rate_decreases: false,
+        min_emission_per_second: Decimal256::zero(),
+        max_emission_per_second: Decimal256::MAX,
+        max_rate_change: Decimal::zero(),
+        rate_change_window: 0,
+        delivery: Delivery::Underlying,
+        distribution_mode: DistributionMode::Push,
+        fee_bps: 0,
+        fee_recipient: None,
+        recipient_code_id: None,
+        staking_addr: None,
+        destination_pool: None,
+    };
+    let distributor_addr = wasm
+        .instantiate(code_id, &msg, None, None, &[], &admin)
+        .unwrap()
+        .data
+        .address;
+
+    // The reward vault is found to wrap the inner vault
+    let state: StateResponse = wasm.query(&distributor_addr, &QueryMsg::State {}).unwrap();
+    assert!(matches!(
+        state.reward_token,
+        RewardType::Vault { vault, inner_vaults, pool: Pool::Astroport(pool) }
+            if vault.addr == outer_vault_addr
+                && inner_vaults.len() == 1
+                && inner_vaults[0].addr == inner_vault_robot.vault_addr
+                && pool == robot.reward_pool
+    ));
+
+    // Distributing redeems from both vaults before withdrawing liquidity
+    robot.send_native_tokens(
+        &admin,
+        &distributor_addr,
+        Uint128::new(1_000_000_000_000),
+        outer_vault_token,
+    );
+    let time_elapsed = 1000u64;
+    robot.increase_time(time_elapsed);
+    wasm.execute(&distributor_addr, &ExecuteMsg::Distribute {}, &[], &admin)
+        .unwrap();
+    let expected_amount =
+        emission_per_second * time_elapsed as u128 / INITIAL_VAULT_TOKENS_PER_BASE_TOKEN.u128();
+    inner_vault_robot
+        .assert_native_token_balance_eq(distribution_acc.address(), "uaxl", expected_amount)
+        .assert_native_token_balance_eq(distribution_acc.address(), "untrn", expected_amount);
+}
+
+#[test]
+fn raw_delivery_sends_vault_tokens_without_redeeming() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100_000u128;
+    let init_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        emission_per_second,
+        init_time,
+    );
+
+    let vault_robot = &robot.reward_vault_robot;
+    let base_token_balance = vault_robot.query_base_token_balance(admin.address());
+    robot.deposit_to_distributor(base_token_balance / Uint128::new(10), Unwrap::Ok, &admin);
+
+    let mut updates = ConfigUpdates::default();
+    updates.delivery(Delivery::Raw);
+
+    // The vault tokens are sent as is, rather than the assets of the pool
+    let time_elapsed = 1000u64;
+    robot
+        .update_config(updates, Unwrap::Ok, &admin)
+        .increase_time(time_elapsed)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[coin(
+            emission_per_second * time_elapsed as u128,
+            vault_robot.vault_token(),
+        )]);
+}
+
+#[test]
+fn raw_delivery_sends_lp_tokens_without_withdrawing_liquidity() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100_000u128;
+    let init_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let rewards_start_time = init_time + 5;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::LpToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+
+    let vault_robot = &robot.reward_vault_robot;
+    let base_token_balance = vault_robot.query_base_token_balance(admin.address());
+    robot.deposit_to_distributor(base_token_balance / Uint128::new(10), Unwrap::Ok, &admin);
+
+    let mut updates = ConfigUpdates::default();
+    updates.delivery(Delivery::Raw);
+
+    let time_elapsed = 1000u64;
+    robot
+        .update_config(updates, Unwrap::Ok, &admin)
+        .increase_time(5) // Rewards have started
+        .increase_time(time_elapsed)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[]);
+
+    // The LP tokens are sent as is, rather than the assets of the pool
+    vault_robot.assert_base_token_balance_eq(
+        robot.distribution_acc.address(),
+        emission_per_second * time_elapsed as u128,
+    );
+}
+
+#[test]
+fn distribute_provides_redeemed_assets_to_destination_pool() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100_000u128;
+    let init_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::LpToken,
+        &admin,
+        emission_per_second,
+        init_time,
+    );
+
+    let vault_robot = &robot.reward_vault_robot;
+    let base_token_balance = vault_robot.query_base_token_balance(admin.address());
+    robot.deposit_to_distributor(base_token_balance / Uint128::new(10), Unwrap::Ok, &admin);
+
+    // Provide the withdrawn assets back to the reward pool
+    let destination_pool =
+        |max_slippage, lp_tokens_per_asset: Decimal, denoms: &[&str]| DestinationPoolInfo {
+            lp_token: AssetInfoUnchecked::Cw20(robot.reward_pool.lp_token_addr.to_string()),
+            liquidity_manager_addr: Some(
+                robot.astroport_contracts.liquidity_manager.address.clone(),
+            ),
+            max_slippage,
+            lp_tokens_per_asset: denoms
+                .iter()
+                .map(|denom| (AssetInfoUnchecked::native(*denom), lp_tokens_per_asset))
+                .collect(),
+        };
+    let user = runner.init_default_account().unwrap();
+    robot
+        .set_destination_pool(
+            Some(destination_pool(
+                Decimal::percent(1),
+                Decimal::one(),
+                &["uaxl", "untrn"],
+            )),
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &user,
+        )
+        .set_destination_pool(
+            Some(destination_pool(
+                Decimal::percent(101),
+                Decimal::one(),
+                &["uaxl", "untrn"],
+            )),
+            Unwrap::Err("Maximum slippage 1.01 must not be greater than 1"),
+            &admin,
+        )
+        .set_destination_pool(
+            Some(destination_pool(
+                Decimal::percent(1),
+                Decimal::one(),
+                &["uaxl"],
+            )),
+            Unwrap::Err("No reference rate set for destination pool asset untrn"),
+            &admin,
+        );
+
+    // The slippage is bounded by the reference rates rather than the pool's
+    // current state, so providing liquidity fails if they are not met
+    let time_elapsed = 1000u64;
+    robot
+        .set_destination_pool(
+            Some(destination_pool(
+                Decimal::percent(1),
+                Decimal::percent(110),
+                &["uaxl", "untrn"],
+            )),
+            Unwrap::Ok,
+            &admin,
+        )
+        .increase_time(time_elapsed)
+        .distribute(Unwrap::Err("slippage"), &admin)
+        .set_destination_pool(
+            Some(destination_pool(
+                Decimal::percent(1),
+                Decimal::one(),
+                &["uaxl", "untrn"],
+            )),
+            Unwrap::Ok,
+            &admin,
+        );
+    assert!(robot.query_state().destination_pool.is_some());
+
+    // The LP tokens received from providing liquidity are distributed instead
+    // of the pool assets
+    let expected = Uint128::from(emission_per_second * 2 * time_elapsed as u128);
+    robot
+        .increase_time(time_elapsed)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[]);
+    let lp_tokens_received = vault_robot.query_base_token_balance(robot.distribution_acc.address());
+    assert!(lp_tokens_received <= expected);
+    assert!(lp_tokens_received >= expected * Decimal::percent(99));
+
+    // Unsetting the destination pool distributes the pool assets again
+    robot
+        .set_destination_pool(None, Unwrap::Ok, &admin)
+        .increase_time(time_elapsed)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[
+            coin(emission_per_second * time_elapsed as u128, "uaxl"),
+            coin(emission_per_second * time_elapsed as u128, "untrn"),
+        ]);
+}
+
+#[test]
+fn pull_mode_rewards_accrue_until_claimed_by_recipient() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100_000u128;
+    let init_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::LpToken,
+        &admin,
+        emission_per_second,
+        init_time,
+    );
+
+    let vault_robot = &robot.reward_vault_robot;
+    let base_token_balance = vault_robot.query_base_token_balance(admin.address());
+    robot.deposit_to_distributor(base_token_balance / Uint128::new(10), Unwrap::Ok, &admin);
+
+    let mut updates = ConfigUpdates::default();
+    updates.distribution_mode(DistributionMode::Pull);
+
+    // Distributing only accrues the rewards
+    let time_elapsed = 1000u64;
+    let accrued = emission_per_second * time_elapsed as u128;
+    robot
+        .update_config(updates, Unwrap::Ok, &admin)
+        .increase_time(time_elapsed)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[])
+        .claim(Unwrap::Err("Unauthorized"), &admin);
+    assert_eq!(robot.query_state().pending_rewards.u128(), accrued);
+
+    // The recipient claims the rewards and learns the delivered assets from the
+    // response data
+    let res = robot
+        .wasm()
+        .execute(
+            &robot.reward_distributor_addr,
+            &ExecuteMsg::Claim {},
+            &[],
+            &robot.distribution_acc,
+        )
+        .unwrap();
+    let data: DistributeResponse = from_json(res.data.data).unwrap();
+    let expected = vec![coin(accrued, "uaxl"), coin(accrued, "untrn")];
+    assert_eq!(data.reward_amount.u128(), accrued);
+    assert_eq!(data.period_end - data.period_start, time_elapsed);
+    assert_eq!(data.delivered, AssetList::from(expected.clone()));
+    robot.assert_distribution_acc_balances_eq(&expected);
+    assert!(robot.query_state().pending_rewards.is_zero());
+
+    // Rewards accrued before the recipient changes can still be claimed by the
+    // previous recipient
+    let new_recipient = runner.init_account(&[]).unwrap();
+    let mut updates = ConfigUpdates::default();
+    updates.distribution_addr(new_recipient.address());
+    robot
+        .increase_time(time_elapsed)
+        .update_config(updates, Unwrap::Ok, &admin)
+        .accept_recipient(Unwrap::Ok, &new_recipient);
+    let unclaimed = robot
+        .query_unclaimed_rewards(robot.distribution_acc.address())
+        .unwrap();
+    assert_eq!(unclaimed.amount.u128(), accrued);
+    assert_eq!(unclaimed.period_end - unclaimed.period_start, time_elapsed);
+    robot
+        .claim(Unwrap::Ok, &robot.distribution_acc)
+        .assert_distribution_acc_balances_gt(&[
+            coin(accrued * 2 - 2, "uaxl"),
+            coin(accrued * 2 - 2, "untrn"),
+        ])
+        .claim(Unwrap::Err("Unauthorized"), &robot.distribution_acc);
+    assert!(robot
+        .query_unclaimed_rewards(robot.distribution_acc.address())
+        .is_none());
+}
+
+#[test]
+fn unclaimed_rewards_are_not_spent_by_the_new_recipient() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let new_recipient = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 1_000u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let reward_token_denom = "uaxl";
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::NativeCoin(reward_token_denom.to_string()),
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    robot.deposit_to_distributor(Uint128::new(150_000), Unwrap::Ok, &admin);
+
+    // The rewards accrued before the recipient changes are set aside for the
+    // previous recipient
+    let mut updates = ConfigUpdates::default();
+    updates.distribution_mode(DistributionMode::Pull);
+    updates.distribution_addr(new_recipient.address());
+    robot
+        .update_config(updates, Unwrap::Ok, &admin)
+        .increase_time(100)
+        .accept_recipient(Unwrap::Ok, &new_recipient);
+
+    // The new recipient can not claim them, but the previous recipient can
+    robot
+        .increase_time(100)
+        .claim(
+            Unwrap::Err("Insufficient spendable balance"),
+            &new_recipient,
+        )
+        .claim(Unwrap::Ok, &robot.distribution_acc)
+        .assert_distribution_acc_balances_eq(&[coin(100_000, reward_token_denom)]);
+
+    // The new recipient claims once the contract holds its rewards
+    robot
+        .claim(
+            Unwrap::Err("Insufficient spendable balance"),
+            &new_recipient,
+        )
+        .deposit_to_distributor(Uint128::new(50_000), Unwrap::Ok, &admin)
+        .claim(Unwrap::Ok, &new_recipient);
+    assert_eq!(
+        robot.query_native_token_balance(new_recipient.address(), reward_token_denom),
+        Uint128::new(100_000)
+    );
+}
+
+#[test]
+fn distribute_returns_distributed_amounts_in_response_data() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100_000u128;
+    let init_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let rewards_start_time = init_time + 5;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+
+    let vault_robot = &robot.reward_vault_robot;
+    let base_token_balance = vault_robot.query_base_token_balance(admin.address());
+    robot.deposit_to_distributor(base_token_balance / Uint128::new(10), Unwrap::Ok, &admin);
+
+    // The assets are received in the replies, which set the response data
+    let time_elapsed = 1000u64;
+    robot.increase_time(5 + time_elapsed);
+    let res = robot
+        .wasm()
+        .execute(
+            &robot.reward_distributor_addr,
+            &ExecuteMsg::Distribute {},
+            &[],
+            &admin,
+        )
+        .unwrap();
+    let data: DistributeResponse = from_json(res.data.data).unwrap();
+
+    let reward_amount = emission_per_second * time_elapsed as u128;
+    let base_token_amount = reward_amount / INITIAL_VAULT_TOKENS_PER_BASE_TOKEN.u128();
+    let expected = vec![
+        coin(base_token_amount, "uaxl"),
+        coin(base_token_amount, "untrn"),
+    ];
+    assert_eq!(data.period_start, rewards_start_time);
+    assert_eq!(data.period_end, rewards_start_time + time_elapsed);
+    assert_eq!(data.reward_amount.u128(), reward_amount);
+    assert_eq!(data.delivered, AssetList::from(expected.clone()));
+    robot.assert_distribution_acc_balances_eq(&expected);
+}
+
+#[test]
+fn distribute_sends_fee_to_fee_recipient() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let fee_recipient = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 1_000u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let reward_token_denom = "uaxl";
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::NativeCoin(reward_token_denom.to_string()),
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    robot.deposit_to_distributor(Uint128::new(1_000_000_000), Unwrap::Ok, &admin);
+
+    // A fee needs a fee recipient, and must be lower than the entire delivery
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.fee_bps(250);
+    robot.update_config(
+        config_updates.clone(),
+        Unwrap::Err("A fee recipient must be set to charge a fee"),
+        &admin,
+    );
+    config_updates.fee_recipient(Some(fee_recipient.address()));
+    let mut full_fee = config_updates.clone();
+    full_fee.fee_bps(10_000);
+    robot
+        .update_config(
+            full_fee,
+            Unwrap::Err("Fee of 10000 bps must be lower than 10000 bps"),
+            &admin,
+        )
+        .update_config(config_updates, Unwrap::Ok, &admin);
+
+    // 2.5% of the rewards go to the fee recipient, the rest to the distribution
+    // address
+    let reward_amount = 100 * emission_per_second;
+    let fee_amount = reward_amount / 40;
+    robot
+        .increase_time(100)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[coin(
+            reward_amount - fee_amount,
+            reward_token_denom,
+        )]);
+    let fee_balance = robot
+        .reward_vault_robot
+        .query_native_token_balance(fee_recipient.address(), reward_token_denom);
+    assert_eq!(fee_balance, Uint128::new(fee_amount));
+    assert_eq!(
+        robot.query_state().fees_collected,
+        AssetList::from(vec![Asset::native(reward_token_denom, fee_amount)])
+    );
+}
+
+#[test]
+fn distribute_takes_fee_from_underlying_assets() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let fee_recipient = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100_000u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::LpToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    let vault_robot = &robot.reward_vault_robot;
+    let base_token_balance = vault_robot.query_base_token_balance(admin.address());
+    robot.deposit_to_distributor(base_token_balance / Uint128::new(10), Unwrap::Ok, &admin);
+
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.fee_bps(250);
+    config_updates.fee_recipient(Some(fee_recipient.address()));
+    robot.update_config(config_updates, Unwrap::Ok, &admin);
+
+    // All LP tokens are withdrawn, and 2.5% of the pool assets received go to
+    // the fee recipient instead of LP tokens
+    let reward_amount = 1000 * emission_per_second;
+    let fee_amount = reward_amount / 40;
+    robot
+        .increase_time(1000)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[
+            coin(reward_amount - fee_amount, "uaxl"),
+            coin(reward_amount - fee_amount, "untrn"),
+        ]);
+    for denom in ["uaxl", "untrn"] {
+        robot.assert_native_token_balance_eq(
+            fee_recipient.address(),
+            denom,
+            Uint128::new(fee_amount),
+        );
+    }
+    vault_robot.assert_base_token_balance_eq(fee_recipient.address(), Uint128::zero());
+    assert_eq!(
+        robot.query_state().fees_collected,
+        AssetList::from(vec![
+            Asset::native("uaxl", fee_amount),
+            Asset::native("untrn", fee_amount),
+        ])
+    );
+}
+
+#[test]
+fn rejected_deliveries_are_held_in_escrow_until_released() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let user = runner.init_default_account().unwrap();
+    let distribution_acc = runner.init_account(&[]).unwrap();
+    let distribution_addr = distribution_acc.address();
+    let emission_per_second = 1_000u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let wasm = Wasm::new(&runner);
+
+    // Distribute the tokens of a CW20 token that can reject transfers to the
+    // distribution address, as the vault tokens of a generic vault sent as is
+    let token_code_id = runner.store_code(mock_cw20::contract(), &admin).unwrap();
+    let token_addr = wasm
+        .instantiate(
+            token_code_id,
+            &mock_cw20::InstantiateMsg {
+                initial_balances: vec![(admin.address(), Uint128::new(1_000_000))],
+            },
+            None,
+            None,
+            &[],
+            &admin,
+        )
+        .unwrap()
+        .data
+        .address;
+    let mock_vault_code_id = runner.store_code(mock_vault::contract(), &admin).unwrap();
+    let vault_addr = wasm
+        .instantiate(
+            mock_vault_code_id,
+            &mock_vault::InstantiateMsg {
+                base_token: "uaxl".to_string(),
+                vault_token: token_addr.clone(),
+            },
+            None,
+            None,
+            &[],
+            &admin,
+        )
+        .unwrap()
+        .data
+        .address;
+    let code_id = runner
+        .store_code(
+            RewardDistributorRobot::contract(&runner, UNOPTIMIZED_PATH),
+            &admin,
+        )
+        .unwrap();
+    let msg = InstantiateMsg {
+        owner: admin.address(),
+        guardian: None,
+        emission_per_second: Decimal256::from_ratio(emission_per_second, 1u128),
+        reward_token_info: RewardInfo::GenericVault(vault_addr),
+        distribution_addr: distribution_addr.clone(),
+        rewards_start_time,
+        config_update_delay: 0,
+        instant_rate_decreases: false,
+        min_emission_per_second: Decimal256::zero(),
+        max_emission_per_second: Decimal256::MAX,
+        max_rate_change: Decimal::zero(),
+        rate_change_window: 0,
+        delivery: Delivery::Raw,
+        distribution_mode: DistributionMode::Push,
+        fee_bps: 0,
+        fee_recipient: None,
+        recipient_code_id: None,
+        staking_addr: None,
+        destination_pool: None,
+    };
+    let distributor_addr = wasm
+        .instantiate(code_id, &msg, None, None, &[], &admin)
+        .unwrap()
+        .data
+        .address;
+
+    let fund_distributor = |amount: u128| {
+        let msg = mock_cw20::ExecuteMsg::Transfer {
+            recipient: distributor_addr.clone(),
+            amount: Uint128::new(amount),
+        };
+        wasm.execute(&token_addr, &msg, &[], &admin).unwrap();
+    };
+    let set_rejected = |rejected: bool| {
+        let msg = mock_cw20::ExecuteMsg::SetRejected {
+            address: distribution_addr.clone(),
+            rejected,
+        };
+        wasm.execute(&token_addr, &msg, &[], &admin).unwrap();
+    };
+    let execute = |msg: &ExecuteMsg, signer: &SigningAccount| {
+        wasm.execute(&distributor_addr, msg, &[], signer)
+            .map_err(|e| e.to_string())
+    };
+    let balance = |address: &str| -> Uint128 {
+        let res: cw20::BalanceResponse = wasm
+            .query(
+                &token_addr,
+                &cw20::Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    };
+    let escrow = || -> Option<AssetList> {
+        wasm.query(
+            &distributor_addr,
+            &QueryMsg::Escrow {
+                address: distribution_addr.clone(),
+            },
+        )
+        .unwrap()
+    };
+    let pending_rewards = || {
+        let state: StateResponse = wasm.query(&distributor_addr, &QueryMsg::State {}).unwrap();
+        state.pending_rewards
+    };
+
+    // Rewards that the contract does not hold are not delivered
+    fund_distributor(50_000);
+    runner.increase_time(100).unwrap();
+    let err = execute(&ExecuteMsg::Distribute {}, &admin).unwrap_err();
+    assert!(err.contains("Insufficient vault token balance"), "{err}");
+
+    // Rewards rejected by the recipient are held in escrow, and the delivery
+    // succeeds
+    fund_distributor(100_000);
+    set_rejected(true);
+    execute(&ExecuteMsg::Distribute {}, &admin).unwrap();
+    let escrowed = AssetList::from(vec![Asset::cw20(Addr::unchecked(&token_addr), 100_000u128)]);
+    assert_eq!(escrow(), Some(escrowed.clone()));
+    assert_eq!(pending_rewards(), Uint128::zero());
+    assert!(balance(&distribution_addr).is_zero());
+
+    // Escrowed tokens are not spent on later deliveries
+    set_rejected(false);
+    runner.increase_time(100).unwrap();
+    let err = execute(&ExecuteMsg::Distribute {}, &admin).unwrap_err();
+    assert!(err.contains("Insufficient vault token balance"), "{err}");
+
+    // Anyone can retry the delivery, which keeps the tokens in escrow while
+    // the recipient still rejects them
+    set_rejected(true);
+    execute(&ExecuteMsg::RetryDelivery {}, &user).unwrap();
+    assert_eq!(escrow(), Some(escrowed));
+    set_rejected(false);
+    execute(&ExecuteMsg::RetryDelivery {}, &user).unwrap();
+    assert_eq!(escrow(), None);
+    assert_eq!(balance(&distribution_addr), Uint128::new(100_000));
+
+    // Escrowed tokens can also be claimed by their recipient
+    fund_distributor(200_000);
+    set_rejected(true);
+    execute(&ExecuteMsg::Distribute {}, &admin).unwrap();
+    let err = execute(&ExecuteMsg::ClaimEscrow {}, &user).unwrap_err();
+    assert!(err.contains("No assets held in escrow"), "{err}");
+    set_rejected(false);
+    execute(&ExecuteMsg::ClaimEscrow {}, &distribution_acc).unwrap();
+    assert_eq!(escrow(), None);
+    assert_eq!(balance(&distribution_addr), Uint128::new(200_000));
+}
+
+#[test]
+fn distribution_pauses_when_recipient_code_does_not_match_pin() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 1_000u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let reward_token_denom = "uaxl";
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::NativeCoin(reward_token_denom.to_string()),
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    robot.deposit_to_distributor(Uint128::new(1_000_000_000), Unwrap::Ok, &admin);
+
+    // The pin must match the code of the current distribution address
+    let mut pin_update = ConfigUpdates::default();
+    pin_update.recipient_code_id(Some(1));
+    robot.update_config(
+        pin_update,
+        Unwrap::Err("is not a contract of the pinned code id"),
+        &admin,
+    );
+
+    // Distribute to a contract, pinned to its current code
+    let wasm = Wasm::new(&runner);
+    let code_id = runner
+        .store_code(mock_legacy_state::contract(), &admin)
+        .unwrap();
+    let new_code_id = runner
+        .store_code(mock_legacy_state::contract(), &admin)
+        .unwrap();
+    let recipient = wasm
+        .instantiate(
+            code_id,
+            &mock_legacy_state::InstantiateMsg { entries: vec![] },
+            Some(&admin.address()),
+            None,
+            &[],
+            &admin,
+        )
+        .unwrap()
+        .data
+        .address;
+    let mut recipient_update = ConfigUpdates::default();
+    recipient_update.distribution_addr(recipient.clone());
+    let mut pin_update = ConfigUpdates::default();
+    pin_update.recipient_code_id(Some(new_code_id));
+    robot
+        .update_config(recipient_update, Unwrap::Ok, &admin)
+        .accept_recipient(Unwrap::Ok, &admin)
+        .update_config(
+            pin_update,
+            Unwrap::Err("is not a contract of the pinned code id"),
+            &admin,
+        );
+    let mut pin_update = ConfigUpdates::default();
+    pin_update.recipient_code_id(Some(code_id));
+    robot
+        .update_config(pin_update, Unwrap::Ok, &admin)
+        .increase_time(100)
+        .distribute(Unwrap::Ok, &admin);
+    let balance = |robot: &RewardDistributorRobot| {
+        robot
+            .reward_vault_robot
+            .query_native_token_balance(&recipient, reward_token_denom)
+    };
+    assert_eq!(balance(&robot), Uint128::new(100 * emission_per_second));
+
+    // Once the recipient is migrated to another code, distributing pauses
+    // instead of delivering, and the rewards stay pending
+    runner
+        .execute_cosmos_msgs::<MsgMigrateContractResponse>(
+            &[CosmosMsg::Wasm(WasmMsg::Migrate {
+                contract_addr: recipient.clone(),
+                new_code_id,
+                msg: to_json_binary(&mock_legacy_state::InstantiateMsg { entries: vec![] })
+                    .unwrap(),
+            })],
+            &admin,
+        )
+        .unwrap();
+    robot.increase_time(100).distribute(Unwrap::Ok, &admin);
+    let state = robot.query_state();
+    assert!(state.paused);
+    assert_eq!(
+        state.pending_rewards,
+        Uint128::new(100 * emission_per_second)
+    );
+    assert_eq!(balance(&robot), Uint128::new(100 * emission_per_second));
+    robot.distribute(Unwrap::Err("Distribution is paused"), &admin);
+
+    // The owner approves the recipient's new code and unpauses
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.recipient_code_id(Some(new_code_id));
+    robot
+        .update_config(config_updates, Unwrap::Ok, &admin)
+        .unpause(Unwrap::Ok, &admin)
+        .distribute(Unwrap::Ok, &admin);
+    assert_eq!(balance(&robot), Uint128::new(200 * emission_per_second));
+}
+
+#[test]
+fn staked_mode_shares_rewards_between_stakers() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let alice = runner.init_account(&[coin(1_000, "uosmo")]).unwrap();
+    let bob = runner.init_account(&[coin(1_000, "uosmo")]).unwrap();
+    let carol = runner.init_account(&[coin(1_000, "uosmo")]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 1_000u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let reward_token_denom = "uaxl";
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::NativeCoin(reward_token_denom.to_string()),
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    robot.deposit_to_distributor(Uint128::new(1_000_000_000), Unwrap::Ok, &admin);
+    let reward_balance = |address: String| {
+        robot
+            .reward_vault_robot
+            .query_native_token_balance(address, reward_token_denom)
+    };
+
+    // Create a staking contract that reports stake changes to the distributor,
+    // with a staker that staked before
+    let wasm = Wasm::new(&runner);
+    let mock_staking_code_id = runner.store_code(mock_staking::contract(), &admin).unwrap();
+    let staking_addr = wasm
+        .instantiate(
+            mock_staking_code_id,
+            &mock_staking::InstantiateMsg {
+                denom: "uosmo".to_string(),
+            },
+            None,
+            None,
+            &[],
+            &admin,
+        )
+        .unwrap()
+        .data
+        .address;
+    let stake = |amount: u128, staker: &SigningAccount| {
+        wasm.execute(
+            &staking_addr,
+            &mock_staking::ExecuteMsg::Stake {},
+            &[coin(amount, "uosmo")],
+            staker,
+        )
+        .unwrap();
+    };
+    stake(400, &carol);
+    wasm.execute(
+        &staking_addr,
+        &mock_staking::ExecuteMsg::AddHook {
+            addr: robot.reward_distributor_addr.clone(),
+        },
+        &[],
+        &admin,
+    )
+    .unwrap();
+
+    // Staked mode needs a staking contract. Switching to it counts the stakes
+    // made before.
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.distribution_mode(DistributionMode::Staked);
+    robot.update_config(
+        config_updates.clone(),
+        Unwrap::Err("A staking contract must be set to distribute to stakers"),
+        &admin,
+    );
+    config_updates.staking_addr(Some(staking_addr.clone()));
+    robot.update_config(config_updates, Unwrap::Ok, &admin);
+    assert_eq!(robot.query_state().total_stake, Uint128::new(400));
+
+    // Only the staking contract can report stake changes
+    let hook = ExecuteMsg::StakeChangeHook(StakeChangedHookMsg::Stake {
+        addr: alice.address(),
+        amount: Uint128::new(1_000),
+    });
+    let res = wasm.execute(&robot.reward_distributor_addr, &hook, &[], &alice);
+    assert!(res.unwrap_err().to_string().contains("Unauthorized"));
+
+    // Rewards are shared in proportion to the stakes, and the claimable rewards
+    // include the rewards emitted since the last distribution
+    stake(100, &alice);
+    stake(300, &bob);
+    robot.increase_time(100);
+    assert_eq!(
+        robot.query_staker_rewards(alice.address()).claimable,
+        Uint128::new(12_500)
+    );
+    robot.distribute(Unwrap::Ok, &admin);
+    let state = robot.query_state();
+    assert_eq!(state.total_stake, Uint128::new(800));
+    assert_eq!(state.pending_rewards, Uint128::zero());
+    let alice_rewards = robot.query_staker_rewards(alice.address());
+    assert_eq!(alice_rewards.stake, Uint128::new(100));
+    assert_eq!(alice_rewards.claimable, Uint128::new(12_500));
+    robot.claim_rewards(Unwrap::Ok, &bob);
+    assert_eq!(reward_balance(bob.address()), Uint128::new(37_500));
+
+    // Rewards emitted before a stake change are shared according to the stakes
+    // before it
+    wasm.execute(
+        &staking_addr,
+        &mock_staking::ExecuteMsg::Unstake {
+            amount: Uint128::new(300),
+        },
+        &[],
+        &bob,
+    )
+    .unwrap();
+    robot
+        .increase_time(100)
+        .claim_rewards(Unwrap::Ok, &alice)
+        .claim_rewards(Unwrap::Ok, &bob);
+    assert_eq!(reward_balance(alice.address()), Uint128::new(32_500));
+    assert_eq!(reward_balance(bob.address()), Uint128::new(37_500));
+    assert_eq!(
+        robot.query_staker_rewards(alice.address()).claimable,
+        Uint128::zero()
+    );
+
+    // The staker that staked before switching to staked mode earned its share
+    // from the start
+    let carol_rewards = robot.query_staker_rewards(carol.address());
+    assert_eq!(carol_rewards.stake, Uint128::new(400));
+    assert_eq!(carol_rewards.claimable, Uint128::new(130_000));
+    robot.claim_rewards(Unwrap::Ok, &carol);
+    assert_eq!(reward_balance(carol.address()), Uint128::new(130_000));
+
+    // Addresses that never staked have nothing to claim
+    let dave = runner.init_account(&[]).unwrap();
+    robot.claim_rewards(Unwrap::Ok, &dave);
+    assert!(reward_balance(dave.address()).is_zero());
+
+    // The staking contract cannot be swapped out once set
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.staking_addr(Some(treasury_addr.address()));
+    robot.update_config(
+        config_updates,
+        Unwrap::Err("The staking contract cannot be changed once set"),
+        &admin,
+    );
+}
+
+#[test]
+fn staked_mode_requires_raw_delivery_from_locked_vault() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::LockedVaultToken(1_000),
+        &admin,
+        1_000u128,
+        rewards_start_time,
+    );
+    let wasm = Wasm::new(&runner);
+    let mock_staking_code_id = runner.store_code(mock_staking::contract(), &admin).unwrap();
+    let staking_addr = wasm
+        .instantiate(
+            mock_staking_code_id,
+            &mock_staking::InstantiateMsg {
+                denom: "uosmo".to_string(),
+            },
+            None,
+            None,
+            &[],
+            &admin,
+        )
+        .unwrap()
+        .data
+        .address;
+
+    // Stakers can not claim rewards that have to be unlocked first, so staked
+    // mode can not be instantiated with the underlying assets delivered
+    let code_id = runner
+        .store_code(
+            RewardDistributorRobot::contract(&runner, UNOPTIMIZED_PATH),
+            &admin,
+        )
+        .unwrap();
+    let instantiate_msg = |delivery: Delivery| InstantiateMsg {
+        owner: admin.address(),
+        guardian: None,
+        emission_per_second: Decimal256::from_ratio(1_000u128, 1u128),
+        reward_token_info: RewardInfo::AstroportVault(AstroportVault {
+            vault_addr: robot.reward_vault_robot.vault_addr(),
+            pool_addr: None,
+            liquidity_manager_addr: robot.astroport_contracts.liquidity_manager.address.clone(),
+        }),
+        distribution_addr: robot.distribution_acc.address(),
+        rewards_start_time,
+        config_update_delay: 0,
+        instant_rate_decreases: false,
+        min_emission_per_second: Decimal256::zero(),
+        max_emission_per_second: Decimal256::MAX,
+        max_rate_change: Decimal::zero(),
+        rate_change_window: 0,
+        delivery,
+        distribution_mode: DistributionMode::Staked,
+        fee_bps: 0,
+        fee_recipient: None,
+        recipient_code_id: None,
+        staking_addr: Some(staking_addr.clone()),
+        destination_pool: None,
+    };
+    let err = wasm
+        .instantiate(
+            code_id,
+            &instantiate_msg(Delivery::Underlying),
+            None,
+            None,
+            &[],
+            &admin,
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains(
+        "Rewards from a locked vault can only be distributed to stakers as raw vault tokens"
+    ));
+    wasm.instantiate(
+        code_id,
+        &instantiate_msg(Delivery::Raw),
+        None,
+        None,
+        &[],
+        &admin,
+    )
+    .unwrap();
+
+    // Neither can the config be updated to it, whether switching to staked mode
+    // or to delivering the underlying assets
+    let mut config_updates = ConfigUpdates::default();
+    config_updates
+        .distribution_mode(DistributionMode::Staked)
+        .staking_addr(Some(staking_addr));
+    robot.update_config(
+        config_updates.clone(),
+        Unwrap::Err(
+            "Rewards from a locked vault can only be distributed to stakers as raw vault tokens",
+        ),
+        &admin,
+    );
+    config_updates.delivery(Delivery::Raw);
+    robot.update_config(config_updates, Unwrap::Ok, &admin);
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.delivery(Delivery::Underlying);
+    robot.update_config(
+        config_updates,
+        Unwrap::Err(
+            "Rewards from a locked vault can only be distributed to stakers as raw vault tokens",
+        ),
+        &admin,
+    );
+}
+
+#[test]
+fn merkle_airdrop_delivers_proven_claims() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let alice = runner.init_account(&[]).unwrap();
+    let bob = runner.init_account(&[]).unwrap();
+    let carol = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let reward_token_denom = "uaxl";
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::NativeCoin(reward_token_denom.to_string()),
+        &admin,
+        0u128,
+        rewards_start_time,
+    );
+    robot.deposit_to_distributor(Uint128::new(1_000_000), Unwrap::Ok, &admin);
+    let reward_balance = |address: String| {
+        robot
+            .reward_vault_robot
+            .query_native_token_balance(address, reward_token_denom)
+    };
+
+    // Build a Merkle tree of three claims, hashing each pair in sorted order
+    let hash_pair = |a: [u8; 32], b: [u8; 32]| -> [u8; 32] {
+        let (first, second) = if a <= b { (a, b) } else { (b, a) };
+        Sha256::new()
+            .chain_update(first)
+            .chain_update(second)
+            .finalize()
+            .into()
+    };
+    let leaf = |index: u64, address: String, amount: u128| -> [u8; 32] {
+        Sha256::digest(format!("{index}:{address}:{amount}")).into()
+    };
+    let leaves = [
+        leaf(0, alice.address(), 1_000),
+        leaf(1, bob.address(), 2_000),
+        leaf(2, carol.address(), 500),
+    ];
+    let node = hash_pair(leaves[0], leaves[1]);
+    let merkle_root = hex::encode(hash_pair(node, leaves[2]));
+
+    // Only the owner can register a valid root
+    robot
+        .register_merkle_airdrop(
+            &merkle_root,
+            3_000u128,
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &alice,
+        )
+        .register_merkle_airdrop(
+            "not a hash",
+            3_000u128,
+            Unwrap::Err("Invalid Merkle hash"),
+            &admin,
+        )
+        .register_merkle_airdrop(&merkle_root, 3_000u128, Unwrap::Ok, &admin);
+    assert_eq!(
+        robot.query_merkle_airdrop(1).unwrap().total_amount,
+        Uint128::new(3_000)
+    );
+
+    // Claims must be proven, and each leaf can only be claimed once
+    let alice_proof = vec![hex::encode(leaves[1]), hex::encode(leaves[2])];
+    robot
+        .claim_merkle_airdrop(
+            1,
+            0,
+            2_000u128,
+            alice_proof.clone(),
+            Unwrap::Err("Invalid Merkle proof"),
+            &alice,
+        )
+        .claim_merkle_airdrop(
+            1,
+            0,
+            1_000u128,
+            alice_proof.clone(),
+            Unwrap::Err("Invalid Merkle proof"),
+            &bob,
+        )
+        .claim_merkle_airdrop(1, 0, 1_000u128, alice_proof.clone(), Unwrap::Ok, &alice)
+        .claim_merkle_airdrop(
+            1,
+            0,
+            1_000u128,
+            alice_proof,
+            Unwrap::Err("Leaf 0 of airdrop 1 has already been claimed"),
+            &alice,
+        );
+    assert_eq!(reward_balance(alice.address()), Uint128::new(1_000));
+    assert!(robot.query_is_leaf_claimed(1, 0));
+    assert!(!robot.query_is_leaf_claimed(1, 1));
+
+    // Claims are limited to the registered total
+    let bob_proof = vec![hex::encode(leaves[0]), hex::encode(leaves[2])];
+    robot
+        .claim_merkle_airdrop(1, 1, 2_000u128, bob_proof, Unwrap::Ok, &bob)
+        .claim_merkle_airdrop(
+            1,
+            2,
+            500u128,
+            vec![hex::encode(node)],
+            Unwrap::Err("Claim exceeds the remaining amount of airdrop 1"),
+            &carol,
+        );
+    assert_eq!(reward_balance(bob.address()), Uint128::new(2_000));
+    assert_eq!(reward_balance(carol.address()), Uint128::zero());
+    assert_eq!(
+        robot.query_merkle_airdrop(1).unwrap().claimed_amount,
+        Uint128::new(3_000)
+    );
+}
+
+#[test]
+fn merkle_airdrop_is_funded_and_not_spent_by_distribution() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let alice = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 1_000u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let reward_token_denom = "uaxl";
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::NativeCoin(reward_token_denom.to_string()),
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    robot.deposit_to_distributor(Uint128::new(150_000), Unwrap::Ok, &admin);
+
+    // The contract must hold the airdropped reward tokens. A single leaf is its
+    // own root.
+    let leaf: [u8; 32] = Sha256::digest(format!("0:{}:100000", alice.address())).into();
+    let merkle_root = hex::encode(leaf);
+    robot
+        .register_merkle_airdrop(
+            &merkle_root,
+            200_000u128,
+            Unwrap::Err("Insufficient spendable balance"),
+            &admin,
+        )
+        .register_merkle_airdrop(&merkle_root, 100_000u128, Unwrap::Ok, &admin);
+
+    // The unclaimed airdrop is not spent on distributions
+    robot
+        .increase_time(100)
+        .distribute(Unwrap::Err("Insufficient spendable balance"), &admin);
+
+    // The claimant can spend its own reservation
+    robot.claim_merkle_airdrop(1, 0, 100_000u128, vec![], Unwrap::Ok, &alice);
+    assert_eq!(
+        robot
+            .reward_vault_robot
+            .query_native_token_balance(alice.address(), reward_token_denom),
+        Uint128::new(100_000)
+    );
+
+    // Distribution resumes once the contract holds the emitted rewards
+    robot
+        .distribute(Unwrap::Err("Insufficient spendable balance"), &admin)
+        .deposit_to_distributor(Uint128::new(50_000), Unwrap::Ok, &admin)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[coin(100_000, reward_token_denom)]);
+}
+
+#[test]
+fn vesting_schedule_locks_delivered_coins_until_vested() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let user = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 1_000u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let reward_token_denom = "uaxl";
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::NativeCoin(reward_token_denom.to_string()),
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    robot.deposit_to_distributor(Uint128::new(1_000_000_000), Unwrap::Ok, &admin);
+    let distribution_addr = robot.distribution_acc.address();
+
+    // Only the owner or a recipient manager can set a valid vesting schedule
+    let schedule = VestingSchedule {
+        cliff: 100,
+        duration: 1_000,
+    };
+    robot
+        .set_vesting_schedule(
+            &distribution_addr,
+            Some(schedule),
+            Unwrap::Err("Caller is not the contract's current owner"),
+            &user,
+        )
+        .set_vesting_schedule(
+            &distribution_addr,
+            Some(VestingSchedule {
+                cliff: 200,
+                duration: 100,
+            }),
+            Unwrap::Err(
+                "Vesting cliff of 200 seconds must not be longer than the vesting duration",
+            ),
+            &admin,
+        )
+        .set_vesting_schedule(&distribution_addr, Some(schedule), Unwrap::Ok, &admin);
+
+    // Delivered rewards are locked in a vesting position
+    robot
+        .increase_time(100)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[]);
+    let positions = robot.query_vesting_positions(&distribution_addr);
+    assert_eq!(positions.len(), 1);
+    assert_eq!(
+        positions[0].assets,
+        AssetList::from(vec![coin(100_000, reward_token_denom)])
+    );
+
+    // Nothing vests before the cliff, then the rewards vest linearly
+    robot
+        .withdraw_vested(
+            Unwrap::Err("No vested assets to withdraw"),
+            &robot.distribution_acc,
+        )
+        .increase_time(50)
+        .withdraw_vested(
+            Unwrap::Err("No vested assets to withdraw"),
+            &robot.distribution_acc,
+        )
+        .increase_time(200)
+        .withdraw_vested(Unwrap::Ok, &robot.distribution_acc)
+        .assert_distribution_acc_balances_eq(&[coin(25_000, reward_token_denom)])
+        .increase_time(1_000)
+        .withdraw_vested(Unwrap::Ok, &robot.distribution_acc)
+        .assert_distribution_acc_balances_eq(&[coin(100_000, reward_token_denom)]);
+    assert!(robot.query_vesting_positions(&distribution_addr).is_empty());
+
+    // Once the schedule is unset, rewards are sent directly again
+    robot
+        .set_vesting_schedule(&distribution_addr, None, Unwrap::Ok, &admin)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[coin(1_350_000, reward_token_denom)]);
+}
+
+#[test]
+fn vesting_schedule_locks_underlying_pool_assets() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100_000u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::LpToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    let base_token_balance = robot
+        .reward_vault_robot
+        .query_base_token_balance(admin.address());
+    robot.deposit_to_distributor(base_token_balance / Uint128::new(10), Unwrap::Ok, &admin);
+    let distribution_addr = robot.distribution_acc.address();
+
+    // The pool assets received from withdrawing liquidity are locked
+    let schedule = VestingSchedule {
+        cliff: 0,
+        duration: 1_000,
+    };
+    robot
+        .set_vesting_schedule(&distribution_addr, Some(schedule), Unwrap::Ok, &admin)
+        .increase_time(1_000)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[]);
+    let positions = robot.query_vesting_positions(&distribution_addr);
+    assert_eq!(positions.len(), 1);
+    assert_eq!(positions[0].assets.len(), 2);
+
+    // All of them are released once vested
+    let expected: Vec<_> = positions[0]
+        .assets
+        .iter()
+        .map(|asset| coin(asset.amount.u128(), asset.info.to_string()))
+        .collect();
+    robot
+        .increase_time(1_000)
+        .withdraw_vested(Unwrap::Ok, &robot.distribution_acc)
+        .assert_distribution_acc_balances_eq(&expected);
+}
+
+#[test]
+fn vested_assets_are_withdrawn_in_pages_and_not_spent_by_later_deliveries() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 1_000u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let reward_token_denom = "uaxl";
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::NativeCoin(reward_token_denom.to_string()),
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    robot.deposit_to_distributor(Uint128::new(350_000), Unwrap::Ok, &admin);
+    let distribution_addr = robot.distribution_acc.address();
+
+    // Every delivery is locked in its own vesting position
+    let schedule = VestingSchedule {
+        cliff: 0,
+        duration: 100,
+    };
+    robot
+        .set_vesting_schedule(&distribution_addr, Some(schedule), Unwrap::Ok, &admin)
+        .increase_time(100)
+        .distribute(Unwrap::Ok, &admin)
+        .increase_time(100)
+        .distribute(Unwrap::Ok, &admin)
+        .increase_time(100)
+        .distribute(Unwrap::Ok, &admin);
+    let positions = robot.query_vesting_positions(&distribution_addr);
+    assert_eq!(positions.len(), 3);
+
+    // Positions are queried in pages by start time
+    let wasm = Wasm::new(&runner);
+    let page: Vec<VestingPosition> = wasm
+        .query(
+            &robot.reward_distributor_addr,
+            &QueryMsg::VestingPositions {
+                address: distribution_addr.clone(),
+                start_after: Some(positions[0].start_time),
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+    assert_eq!(page, vec![positions[1].clone()]);
+
+    // The assets locked in vesting positions cannot be delivered to anyone else
+    robot
+        .set_vesting_schedule(&distribution_addr, None, Unwrap::Ok, &admin)
+        .increase_time(100)
+        .distribute(
+            Unwrap::Err("Insufficient spendable balance of uaxl"),
+            &admin,
+        );
+
+    // Only the oldest positions up to the limit are withdrawn from
+    wasm.execute(
+        &robot.reward_distributor_addr,
+        &ExecuteMsg::WithdrawVested { limit: Some(2) },
+        &[],
+        &robot.distribution_acc,
+    )
+    .unwrap();
+    robot.assert_distribution_acc_balances_eq(&[coin(200_000, reward_token_denom)]);
+    assert_eq!(robot.query_vesting_positions(&distribution_addr).len(), 1);
+    robot
+        .withdraw_vested(Unwrap::Ok, &robot.distribution_acc)
+        .assert_distribution_acc_balances_eq(&[coin(300_000, reward_token_denom)]);
+    assert!(robot.query_vesting_positions(&distribution_addr).is_empty());
+}
+
+#[test]
+fn mint_rewards_are_minted_up_to_max_supply() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 1_000u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let max_supply = Some(Uint128::new(250_000));
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::Mint {
+            subdenom: "reward".to_string(),
+            max_supply,
+        },
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+
+    // The contract creates the denom, and is its admin
+    let denom = format!("factory/{}/reward", robot.reward_distributor_addr);
+    assert_eq!(
+        robot.query_state().reward_token,
+        RewardType::Mint {
+            denom: denom.clone(),
+            max_supply,
+        }
+    );
+
+    // Rewards are minted on distribution, without funding the contract
+    robot
+        .increase_time(100)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[coin(100_000, &denom)])
+        .increase_time(100)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[coin(200_000, &denom)]);
+
+    // Minting stops at the max supply, and the rewards above it stay pending
+    robot
+        .increase_time(100)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[coin(250_000, &denom)]);
+    assert_eq!(robot.query_state().pending_rewards, Uint128::new(50_000));
+    robot
+        .increase_time(100)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[coin(250_000, &denom)]);
+    assert_eq!(robot.query_state().pending_rewards, Uint128::new(150_000));
+}
+
+#[test]
+fn merkle_claims_of_minted_rewards_are_capped_by_max_supply() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let alice = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::Mint {
+            subdenom: "reward".to_string(),
+            max_supply: Some(Uint128::new(250_000)),
+        },
+        &admin,
+        0u128,
+        rewards_start_time,
+    );
+    let denom = format!("factory/{}/reward", robot.reward_distributor_addr);
+
+    // A single leaf is its own root
+    let leaf: [u8; 32] = Sha256::digest(format!("0:{}:300000", alice.address())).into();
+    robot.register_merkle_airdrop(hex::encode(leaf), 300_000u128, Unwrap::Ok, &admin);
+
+    // Only the amount up to the max supply is claimed, and the leaf can be
+    // claimed again for the rest
+    robot.claim_merkle_airdrop(1, 0, 300_000u128, vec![], Unwrap::Ok, &alice);
+    assert_eq!(
+        robot
+            .reward_vault_robot
+            .query_native_token_balance(alice.address(), &denom),
+        Uint128::new(250_000)
+    );
+    assert!(!robot.query_is_leaf_claimed(1, 0));
+    assert_eq!(
+        robot.query_merkle_airdrop(1).unwrap().claimed_amount,
+        Uint128::new(250_000)
+    );
+    robot.claim_merkle_airdrop(1, 0, 300_000u128, vec![], Unwrap::Ok, &alice);
+    assert_eq!(
+        robot.query_merkle_airdrop(1).unwrap().claimed_amount,
+        Uint128::new(250_000)
+    );
+}
@@ -1,12 +1,14 @@
 use common::get_test_runner;
-use cosmwasm_std::{coin, Addr, Uint128};
+use cosmwasm_std::{coin, Addr, Decimal, Decimal256, Uint128};
 use cw_it::helpers::Unwrap;
 use cw_it::test_tube::Account;
 use cw_it::traits::CwItRunner;
 
 use locked_astroport_vault_test_helpers::cw_vault_standard_test_helpers::traits::CwVaultStandardRobot;
 use locked_astroport_vault_test_helpers::robot::LockedAstroportVaultRobot;
-use neutron_astroport_reward_distributor::{Config, ConfigUpdates};
+use neutron_astroport_reward_distributor::{
+    Config, ConfigUpdates, Delivery, DistributionMode, EmissionRamp, PendingConfigUpdate,
+};
 use neutron_astroport_reward_distributor_test_helpers as test_helpers;
 
 use test_helpers::robot::{RewardDistributorRobot, TestRewardType};
@@ -38,16 +40,30 @@ fn update_config_works_correctly() {
     );
 
     let mut config_updates = ConfigUpdates::default();
-    let new_distr_addr = runner.init_account(&[]).unwrap().address();
+    let new_distr_acc = runner.init_account(&[]).unwrap();
+    let new_distr_addr = new_distr_acc.address();
     config_updates.distribution_addr(new_distr_addr.clone());
-    config_updates.emission_per_second(Uint128::new(420_000_000));
+    config_updates.emission_per_second(Decimal256::from_ratio(420_000_000u128, 1u128));
     config_updates.rewards_start_time(rewards_start_time + 100);
     robot
         .update_config(config_updates, Unwrap::Ok, &admin)
+        .accept_recipient(Unwrap::Ok, &new_distr_acc)
         .assert_config_eq(&Config {
-            emission_per_second: Uint128::new(420_000_000),
+            emission_per_second: Decimal256::from_ratio(420_000_000u128, 1u128),
             distribution_addr: Addr::unchecked(new_distr_addr),
             rewards_start_time: rewards_start_time + 100,
+            config_update_delay: 0,
+            instant_rate_decreases: false,
+            min_emission_per_second: Decimal256::zero(),
+            max_emission_per_second: Decimal256::MAX,
+            max_rate_change: Decimal::zero(),
+            rate_change_window: 0,
+            delivery: Delivery::Underlying,
+            distribution_mode: DistributionMode::Push,
+            fee_bps: 0,
+            fee_recipient: None,
+            recipient_code_id: None,
+            staking_addr: None,
         });
 }
 
@@ -84,7 +100,7 @@ fn update_config_distributes_rewards_if_emission_rate_or_start_time_changes() {
     // Update emission rate and check that rewards are distributed
     let time_elapsed = 100u64;
     let mut config_updates = ConfigUpdates::default();
-    config_updates.emission_per_second(Uint128::new(420_000_000));
+    config_updates.emission_per_second(Decimal256::from_ratio(420_000_000u128, 1u128));
     let balances_after_distribution = robot
         .increase_time(time_elapsed)
         .assert_distribution_acc_balances_eq(&[])
@@ -124,14 +140,494 @@ fn update_config_does_not_distribute_rewards_when_not_changing_emission_rate_or_
     );
 
     let mut config_updates = ConfigUpdates::default();
-    let new_distr_addr = runner.init_account(&[]).unwrap().address();
+    let new_distr_acc = runner.init_account(&[]).unwrap();
+    let new_distr_addr = new_distr_acc.address();
     config_updates.distribution_addr(new_distr_addr.clone());
     robot
         .update_config(config_updates, Unwrap::Ok, &admin)
+        .accept_recipient(Unwrap::Ok, &new_distr_acc)
         .assert_config_eq(&Config {
-            emission_per_second: emission_per_second.into(),
+            emission_per_second: Decimal256::from_ratio(emission_per_second, 1u128),
             distribution_addr: Addr::unchecked(new_distr_addr),
             rewards_start_time,
+            config_update_delay: 0,
+            instant_rate_decreases: false,
+            min_emission_per_second: Decimal256::zero(),
+            max_emission_per_second: Decimal256::MAX,
+            max_rate_change: Decimal::zero(),
+            rate_change_window: 0,
+            delivery: Delivery::Underlying,
+            distribution_mode: DistributionMode::Push,
+            fee_bps: 0,
+            fee_recipient: None,
+            recipient_code_id: None,
+            staking_addr: None,
         })
         .assert_distribution_acc_balances_eq(&[]);
 }
+
+#[test]
+fn timelocked_config_update_must_be_proposed_and_applied_after_delay() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100_000_000u128;
+    let init_time = runner.query_block_time_nanos() / 1_000_000_000;
+    // Start rewards after the delay, so that applying the emission rate change does
+    // not need to distribute any rewards
+    let rewards_start_time = init_time + 1_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+
+    // Enable the config update delay
+    let delay = 100u64;
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.config_update_delay(delay);
+    robot.update_config(config_updates, Unwrap::Ok, &admin);
+
+    // Updating the config directly should now fail
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::from_ratio(420_000_000u128, 1u128));
+    robot.update_config(
+        config_updates.clone(),
+        Unwrap::Err("Config update is timelocked"),
+        &admin,
+    );
+
+    // Propose the update. It can not be applied before the delay has passed.
+    robot
+        .propose_config_update(config_updates.clone(), Unwrap::Ok, &admin)
+        .apply_config_update(
+            Unwrap::Err("Pending config update can not be applied until"),
+            &admin,
+        );
+    let block_time = runner.query_block_time_nanos() / 1_000_000_000;
+    assert_eq!(
+        robot.query_pending_config_update(),
+        Some(PendingConfigUpdate {
+            updates: config_updates,
+            ramp_duration: None,
+            applicable_at: block_time + delay,
+        })
+    );
+
+    // Apply the update after the delay has passed
+    robot
+        .increase_time(delay)
+        .apply_config_update(Unwrap::Ok, &admin)
+        .assert_config_eq(&Config {
+            emission_per_second: Decimal256::from_ratio(420_000_000u128, 1u128),
+            distribution_addr: Addr::unchecked(robot.distribution_acc.address()),
+            rewards_start_time,
+            config_update_delay: delay,
+            instant_rate_decreases: false,
+            min_emission_per_second: Decimal256::zero(),
+            max_emission_per_second: Decimal256::MAX,
+            max_rate_change: Decimal::zero(),
+            rate_change_window: 0,
+            delivery: Delivery::Underlying,
+            distribution_mode: DistributionMode::Push,
+            fee_bps: 0,
+            fee_recipient: None,
+            recipient_code_id: None,
+            staking_addr: None,
+        })
+        .apply_config_update(Unwrap::Err("No pending config update"), &admin);
+    assert_eq!(robot.query_pending_config_update(), None);
+}
+
+#[test]
+fn cancel_config_update_removes_pending_update() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100_000_000u128;
+    let init_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let rewards_start_time = init_time + 5;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::from_ratio(420_000_000u128, 1u128));
+    robot
+        .cancel_config_update(Unwrap::Err("No pending config update"), &admin)
+        .propose_config_update(config_updates, Unwrap::Ok, &admin)
+        .cancel_config_update(Unwrap::Ok, &admin)
+        .apply_config_update(Unwrap::Err("No pending config update"), &admin);
+    assert_eq!(robot.query_pending_config_update(), None);
+    assert_eq!(
+        robot.query_config().emission_per_second,
+        Decimal256::from_ratio(emission_per_second, 1u128)
+    );
+}
+
+#[test]
+fn rate_decreases_skip_config_update_delay_when_enabled() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100_000_000u128;
+    let init_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let rewards_start_time = init_time + 5;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.config_update_delay(100);
+    config_updates.instant_rate_decreases(true);
+    robot.update_config(config_updates, Unwrap::Ok, &admin);
+
+    // Rate increases and updates of other fields are still timelocked
+    let mut rate_increase = ConfigUpdates::default();
+    rate_increase.emission_per_second(Decimal256::from_ratio(emission_per_second + 1, 1u128));
+    let mut decrease_with_start_time = ConfigUpdates::default();
+    decrease_with_start_time
+        .emission_per_second(Decimal256::from_ratio(emission_per_second - 1, 1u128));
+    decrease_with_start_time.rewards_start_time(rewards_start_time + 10);
+    robot
+        .update_config(
+            rate_increase,
+            Unwrap::Err("Config update is timelocked"),
+            &admin,
+        )
+        .update_config(
+            decrease_with_start_time,
+            Unwrap::Err("Config update is timelocked"),
+            &admin,
+        );
+
+    // A rate decrease on its own is applied immediately
+    let mut rate_decrease = ConfigUpdates::default();
+    rate_decrease.emission_per_second(Decimal256::from_ratio(emission_per_second / 2, 1u128));
+    robot.update_config(rate_decrease, Unwrap::Ok, &admin);
+    assert_eq!(
+        robot.query_config().emission_per_second,
+        Decimal256::from_ratio(emission_per_second / 2, 1u128)
+    );
+}
+
+#[test]
+fn update_config_with_ramp_changes_emission_rate_linearly() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 1_000u128;
+    let rewards_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let reward_token_denom = "uaxl";
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::NativeCoin(reward_token_denom.to_string()),
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+    robot.deposit_to_distributor(Uint128::new(1_000_000_000), Unwrap::Ok, &admin);
+
+    // Ramp the emission rate from 1000 to 3000 over 100 seconds
+    let ramp_duration = 100u64;
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::from_ratio(3_000u128, 1u128));
+    let balance_before_ramp = robot
+        .increase_time(10)
+        .update_config_with_ramp(config_updates, Some(ramp_duration), Unwrap::Ok, &admin)
+        .query_distribution_acc_balances()[0]
+        .amount;
+    let ramp_start_time = runner.query_block_time_nanos() / 1_000_000_000;
+    assert_eq!(
+        robot.query_state().emission_ramp,
+        Some(EmissionRamp {
+            start_rate: Decimal256::from_ratio(emission_per_second, 1u128),
+            start_time: ramp_start_time,
+            end_time: ramp_start_time + ramp_duration,
+        })
+    );
+
+    // Halfway through the ramp the rate is 2000, so the average rate over the
+    // first half is 1500
+    robot.increase_time(50).distribute(Unwrap::Ok, &admin);
+    let balance_halfway = robot.query_distribution_acc_balances()[0].amount;
+    assert_eq!(
+        balance_halfway - balance_before_ramp,
+        Uint128::new(50 * 1_500)
+    );
+
+    // The second half of the ramp has an average rate of 2500, after which the
+    // rate stays at 3000
+    robot
+        .increase_time(100)
+        .distribute(Unwrap::Ok, &admin)
+        .assert_distribution_acc_balances_eq(&[coin(
+            (balance_halfway + Uint128::new(50 * 2_500 + 50 * 3_000)).u128(),
+            reward_token_denom,
+        )]);
+}
+
+#[test]
+fn update_config_enforces_emission_bounds() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100u128;
+    let init_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let rewards_start_time = init_time + 1_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+
+    // Narrowing the bounds is applied immediately
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.min_emission_per_second(Decimal256::from_ratio(50u128, 1u128));
+    config_updates.max_emission_per_second(Decimal256::from_ratio(200u128, 1u128));
+    robot.update_config(config_updates, Unwrap::Ok, &admin);
+
+    // The bounds must be valid
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.min_emission_per_second(Decimal256::from_ratio(150u128, 1u128));
+    config_updates.max_emission_per_second(Decimal256::from_ratio(120u128, 1u128));
+    robot.update_config(
+        config_updates,
+        Unwrap::Err("Minimum emission rate 150 is greater than maximum emission rate 120"),
+        &admin,
+    );
+
+    // The emission rate must be within the bounds
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::from_ratio(201u128, 1u128));
+    robot.update_config(
+        config_updates,
+        Unwrap::Err("Emission rate 201 is outside of the bounds [50, 200]"),
+        &admin,
+    );
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::from_ratio(49u128, 1u128));
+    robot.update_config(
+        config_updates,
+        Unwrap::Err("Emission rate 49 is outside of the bounds [50, 200]"),
+        &admin,
+    );
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::from_ratio(200u128, 1u128));
+    robot.update_config(config_updates, Unwrap::Ok, &admin);
+
+    // Widening the bounds must go through the timelock, even without a delay
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.max_emission_per_second(Decimal256::from_ratio(500u128, 1u128));
+    robot
+        .update_config(
+            config_updates.clone(),
+            Unwrap::Err("Config update is timelocked"),
+            &admin,
+        )
+        .propose_config_update(config_updates, Unwrap::Ok, &admin)
+        .apply_config_update(Unwrap::Ok, &admin);
+    assert_eq!(
+        robot.query_config().max_emission_per_second,
+        Decimal256::from_ratio(500u128, 1u128)
+    );
+
+    // Rates beyond the range of Decimal can be set
+    let high_rate = Decimal256::from_ratio(u128::MAX, 1u128);
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.max_emission_per_second(high_rate);
+    robot
+        .propose_config_update(config_updates, Unwrap::Ok, &admin)
+        .apply_config_update(Unwrap::Ok, &admin);
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(high_rate);
+    robot.update_config(config_updates, Unwrap::Ok, &admin);
+    assert_eq!(robot.query_config().emission_per_second, high_rate);
+}
+
+#[test]
+fn update_config_limits_emission_rate_change_within_window() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100u128;
+    let init_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let rewards_start_time = init_time + 1_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+
+    // Allow at most a 50% change per 100 seconds
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.max_rate_change(Decimal::percent(50));
+    config_updates.rate_change_window(100);
+    robot.update_config(config_updates, Unwrap::Ok, &admin);
+
+    // Changes add up within the window, compared to the rate at its start
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::from_ratio(140u128, 1u128));
+    robot.update_config(config_updates, Unwrap::Ok, &admin);
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::from_ratio(160u128, 1u128));
+    robot.update_config(
+        config_updates.clone(),
+        Unwrap::Err(
+            "Changing the emission rate from 100 to 160 exceeds the maximum change of 0.5 within 100 seconds",
+        ),
+        &admin,
+    );
+
+    // Once the window has passed, the change is compared to the current rate
+    robot
+        .increase_time(100)
+        .update_config(config_updates, Unwrap::Ok, &admin);
+    assert_eq!(
+        robot.query_config().emission_per_second,
+        Decimal256::from_ratio(160u128, 1u128)
+    );
+
+    // Loosening the limit must go through the timelock
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.max_rate_change(Decimal::percent(80));
+    robot.update_config(
+        config_updates,
+        Unwrap::Err("Config update is timelocked"),
+        &admin,
+    );
+}
+
+#[test]
+fn update_config_limits_emission_rate_change_over_rolling_window() {
+    let owned_runner = get_test_runner();
+    let runner = owned_runner.as_ref();
+    let admin = RewardDistributorRobot::default_account(&runner);
+    let treasury_addr = runner.init_account(&[]).unwrap();
+    let dependencies = LockedAstroportVaultRobot::instantiate_deps(&runner, &admin, DEPS_PATH);
+    let emission_per_second = 100u128;
+    let init_time = runner.query_block_time_nanos() / 1_000_000_000;
+    let rewards_start_time = init_time + 1_000;
+    let robot = RewardDistributorRobot::instantiate(
+        &runner,
+        &dependencies,
+        DEPS_PATH,
+        UNOPTIMIZED_PATH,
+        treasury_addr.address(),
+        TestRewardType::VaultToken,
+        &admin,
+        emission_per_second,
+        rewards_start_time,
+    );
+
+    // Allow at most a 50% change per 100 seconds
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.max_rate_change(Decimal::percent(50));
+    config_updates.rate_change_window(100);
+    robot.update_config(config_updates, Unwrap::Ok, &admin);
+
+    // The window rolls with every change, so changes made just after an earlier
+    // one cannot compound
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::from_ratio(140u128, 1u128));
+    robot
+        .increase_time(60)
+        .update_config(config_updates, Unwrap::Ok, &admin);
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::from_ratio(200u128, 1u128));
+    robot.increase_time(50).update_config(
+        config_updates.clone(),
+        Unwrap::Err(
+            "Changing the emission rate from 100 to 200 exceeds the maximum change of 0.5 within 100 seconds",
+        ),
+        &admin,
+    );
+    robot
+        .increase_time(50)
+        .update_config(config_updates, Unwrap::Ok, &admin);
+
+    // Decreases are limited relative to the highest rate within the window
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::from_ratio(150u128, 1u128));
+    robot.update_config(config_updates, Unwrap::Ok, &admin);
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::from_ratio(90u128, 1u128));
+    robot.update_config(
+        config_updates,
+        Unwrap::Err(
+            "Changing the emission rate from 200 to 90 exceeds the maximum change of 0.5 within 100 seconds",
+        ),
+        &admin,
+    );
+
+    // A zero rate does not block increases once the window has passed
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.max_rate_change(Decimal::one());
+    robot
+        .propose_config_update(config_updates, Unwrap::Ok, &admin)
+        .apply_config_update(Unwrap::Ok, &admin);
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::zero());
+    robot
+        .increase_time(100)
+        .update_config(config_updates, Unwrap::Ok, &admin);
+    let mut config_updates = ConfigUpdates::default();
+    config_updates.emission_per_second(Decimal256::from_ratio(50u128, 1u128));
+    robot
+        .increase_time(100)
+        .update_config(config_updates, Unwrap::Ok, &admin);
+    assert_eq!(
+        robot.query_config().emission_per_second,
+        Decimal256::from_ratio(50u128, 1u128)
+    );
+}
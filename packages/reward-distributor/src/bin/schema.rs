@@ -1,11 +1,15 @@
 use cosmwasm_schema::write_api;
 
-use neutron_astroport_reward_distributor::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use neutron_astroport_reward_distributor::msg::{
+    ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, SudoMsg,
+};
 
 fn main() {
     write_api! {
         instantiate: InstantiateMsg,
         execute: ExecuteMsg,
         query: QueryMsg,
+        sudo: SudoMsg,
+        migrate: MigrateMsg,
     }
 }
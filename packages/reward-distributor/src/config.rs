@@ -1,10 +1,12 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Api, StdResult, Uint128};
+use cosmwasm_std::{Addr, Api, Decimal, Decimal256, Uint128};
 use cw_address_like::AddressLike;
 use derive_builder::Builder;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::ContractError;
+
 #[cw_serde]
 #[derive(Builder)]
 #[builder(derive(Serialize, Deserialize, Debug, PartialEq, JsonSchema))]
@@ -12,11 +14,109 @@ use serde::{Deserialize, Serialize};
 pub struct ConfigBase<T: AddressLike> {
     /// The emission rate per second. This is the amount of tokens to be
     /// distributed per second, denominated in vault tokens of the reward vault.
-    pub emission_per_second: Uint128,
+    /// Fractions of tokens are carried over between distributions.
+    pub emission_per_second: Decimal256,
     /// The address that rewards are being distributed to
     pub distribution_addr: T,
     /// The unix timestamp at which rewards start being distributed
     pub rewards_start_time: u64,
+    /// The number of seconds that must pass between proposing a config update
+    /// and applying it. If zero, config updates are applied immediately.
+    /// Configs saved without it apply updates immediately.
+    #[serde(default)]
+    pub config_update_delay: u64,
+    /// Whether decreases of `emission_per_second` are exempt from the config
+    /// update delay
+    #[serde(default)]
+    pub instant_rate_decreases: bool,
+    /// The lowest `emission_per_second` that can be set
+    #[serde(default)]
+    pub min_emission_per_second: Decimal256,
+    /// The highest `emission_per_second` that can be set. Configs saved
+    /// without it have no upper bound.
+    #[serde(default = "default_max_emission_per_second")]
+    pub max_emission_per_second: Decimal256,
+    /// The maximum relative change of `emission_per_second` within
+    /// `rate_change_window`, compared to the rate at the start of the window
+    #[serde(default)]
+    pub max_rate_change: Decimal,
+    /// The length in seconds of the rolling window in which `max_rate_change`
+    /// applies. If zero, the rate change is not limited. Configs saved without
+    /// it do not limit the rate change.
+    #[serde(default)]
+    pub rate_change_window: u64,
+    /// Whether rewards are delivered as the reward tokens themselves or as the
+    /// assets underlying them. Configs saved without it deliver the underlying
+    /// assets.
+    #[serde(default)]
+    pub delivery: Delivery,
+    /// Whether rewards are pushed to the distribution address on `Distribute`,
+    /// or accrue until claimed by it. Configs saved without it push rewards.
+    #[serde(default)]
+    pub distribution_mode: DistributionMode,
+    /// The share of every delivery that is sent to `fee_recipient`, in basis
+    /// points. Rewards delivered as their underlying assets pay the fee in
+    /// those assets. Configs saved without it charge no fee.
+    #[serde(default)]
+    pub fee_bps: u16,
+    /// The address that the fee is sent to. Must be set if `fee_bps` is not
+    /// zero.
+    pub fee_recipient: Option<T>,
+    /// The code id that the distribution address must be a contract of. It
+    /// must match the distribution address's code when set. The code id is
+    /// then checked before every delivery, and distribution is paused if the
+    /// recipient has been migrated to other code.
+    pub recipient_code_id: Option<u64>,
+    /// The cw20-stake contract whose stakers share the rewards in staked mode.
+    /// Must be set in staked mode, and cannot be changed once set.
+    pub staking_addr: Option<T>,
+}
+
+fn default_max_emission_per_second() -> Decimal256 {
+    Decimal256::MAX
+}
+
+/// The fee in basis points that would take the entire delivery. The fee must
+/// be lower than this.
+pub const MAX_FEE_BPS: u16 = 10_000;
+
+/// How rewards are delivered to the distribution address
+#[cw_serde]
+#[derive(Copy, Default)]
+pub enum Delivery {
+    /// The reward tokens are sent as is, without redeeming vault tokens or
+    /// withdrawing liquidity
+    Raw,
+    /// The reward tokens are redeemed and withdrawn from the pool, and the
+    /// underlying assets are sent
+    #[default]
+    Underlying,
+}
+
+impl Delivery {
+    /// Returns the name of the delivery mode used in events
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Delivery::Raw => "raw",
+            Delivery::Underlying => "underlying",
+        }
+    }
+}
+
+/// How rewards reach the distribution address
+#[cw_serde]
+#[derive(Copy, Default)]
+pub enum DistributionMode {
+    /// Rewards are delivered to the distribution address on every `Distribute`
+    #[default]
+    Push,
+    /// Rewards accrue in the contract until the distribution address claims
+    /// them with `Claim`
+    Pull,
+    /// Rewards are shared between the stakers of `staking_addr` in proportion
+    /// to their stake, and claimed by each staker with `ClaimRewards`. Rewards
+    /// from a locked vault must be delivered raw in this mode.
+    Staked,
 }
 
 pub type ConfigUnchecked = ConfigBase<String>;
@@ -25,11 +125,56 @@ pub type ConfigUpdates = ConfigBaseBuilder<String>;
 
 impl ConfigUnchecked {
     /// Checks that the `ConfigUnchecked` is valid and returns a `Config`
-    pub fn check(self, api: &dyn Api) -> StdResult<Config> {
+    pub fn check(self, api: &dyn Api) -> Result<Config, ContractError> {
+        if self.min_emission_per_second > self.max_emission_per_second {
+            return Err(ContractError::InvalidEmissionBounds {
+                min: self.min_emission_per_second,
+                max: self.max_emission_per_second,
+            });
+        }
+        if self.emission_per_second < self.min_emission_per_second
+            || self.emission_per_second > self.max_emission_per_second
+        {
+            return Err(ContractError::EmissionRateOutOfBounds {
+                rate: self.emission_per_second,
+                min: self.min_emission_per_second,
+                max: self.max_emission_per_second,
+            });
+        }
+        if self.fee_bps >= MAX_FEE_BPS {
+            return Err(ContractError::InvalidFeeBps {
+                fee_bps: self.fee_bps,
+            });
+        }
+        if self.fee_bps > 0 && self.fee_recipient.is_none() {
+            return Err(ContractError::MissingFeeRecipient {});
+        }
+        if self.distribution_mode == DistributionMode::Staked && self.staking_addr.is_none() {
+            return Err(ContractError::MissingStakingAddr {});
+        }
+
         Ok(Config {
             emission_per_second: self.emission_per_second,
             distribution_addr: api.addr_validate(&self.distribution_addr)?,
             rewards_start_time: self.rewards_start_time,
+            config_update_delay: self.config_update_delay,
+            instant_rate_decreases: self.instant_rate_decreases,
+            min_emission_per_second: self.min_emission_per_second,
+            max_emission_per_second: self.max_emission_per_second,
+            max_rate_change: self.max_rate_change,
+            rate_change_window: self.rate_change_window,
+            delivery: self.delivery,
+            distribution_mode: self.distribution_mode,
+            fee_bps: self.fee_bps,
+            fee_recipient: self
+                .fee_recipient
+                .map(|addr| api.addr_validate(&addr))
+                .transpose()?,
+            recipient_code_id: self.recipient_code_id,
+            staking_addr: self
+                .staking_addr
+                .map(|addr| api.addr_validate(&addr))
+                .transpose()?,
         })
     }
 }
@@ -37,8 +182,9 @@ impl ConfigUnchecked {
 impl Config {
     /// Updates the existing config with the given updates. If a field is
     /// `None` in the `updates` then the old config is kept, else it is updated
-    /// to the new value.
-    pub fn update(&self, api: &dyn Api, updates: ConfigUpdates) -> StdResult<Config> {
+    /// to the new value. Errors if the emission rate is outside of the
+    /// updated bounds.
+    pub fn update(&self, api: &dyn Api, updates: ConfigUpdates) -> Result<Config, ContractError> {
         ConfigUnchecked {
             emission_per_second: updates
                 .emission_per_second
@@ -49,7 +195,153 @@ impl Config {
             rewards_start_time: updates
                 .rewards_start_time
                 .unwrap_or(self.rewards_start_time),
+            config_update_delay: updates
+                .config_update_delay
+                .unwrap_or(self.config_update_delay),
+            instant_rate_decreases: updates
+                .instant_rate_decreases
+                .unwrap_or(self.instant_rate_decreases),
+            min_emission_per_second: updates
+                .min_emission_per_second
+                .unwrap_or(self.min_emission_per_second),
+            max_emission_per_second: updates
+                .max_emission_per_second
+                .unwrap_or(self.max_emission_per_second),
+            max_rate_change: updates.max_rate_change.unwrap_or(self.max_rate_change),
+            rate_change_window: updates
+                .rate_change_window
+                .unwrap_or(self.rate_change_window),
+            delivery: updates.delivery.unwrap_or(self.delivery),
+            distribution_mode: updates.distribution_mode.unwrap_or(self.distribution_mode),
+            fee_bps: updates.fee_bps.unwrap_or(self.fee_bps),
+            fee_recipient: updates
+                .fee_recipient
+                .unwrap_or_else(|| self.fee_recipient.clone().map(Into::into)),
+            recipient_code_id: updates.recipient_code_id.unwrap_or(self.recipient_code_id),
+            staking_addr: updates
+                .staking_addr
+                .unwrap_or_else(|| self.staking_addr.clone().map(Into::into)),
         }
         .check(api)
     }
+
+    /// Returns the fee charged on delivering the given amount of reward tokens
+    pub fn fee_amount(&self, reward_amount: Uint128) -> Uint128 {
+        reward_amount.multiply_ratio(self.fee_bps, MAX_FEE_BPS)
+    }
+
+    /// Checks that changing the emission rate to `new_rate` does not exceed
+    /// `max_rate_change` compared to any rate in effect within the last
+    /// `rate_change_window` seconds, given the lowest and highest of those
+    /// rates. Decreases are limited relative to the highest rate and increases
+    /// relative to the lowest. As no relative change can be taken from a zero
+    /// rate, increases are limited relative to the highest rate if the lowest
+    /// is zero, and are not limited if all rates were zero.
+    pub fn check_rate_change(
+        &self,
+        min_rate: Decimal256,
+        max_rate: Decimal256,
+        new_rate: Decimal256,
+    ) -> Result<(), ContractError> {
+        if self.rate_change_window == 0 {
+            return Ok(());
+        }
+
+        let increase_reference = if min_rate.is_zero() {
+            max_rate
+        } else {
+            min_rate
+        };
+        let reference_rate = if new_rate < max_rate {
+            max_rate
+        } else if new_rate > increase_reference && !increase_reference.is_zero() {
+            increase_reference
+        } else {
+            return Ok(());
+        };
+
+        // If the maximum change overflows, any change is within it
+        let change = reference_rate.abs_diff(new_rate);
+        let exceeds_limit = reference_rate
+            .checked_mul(self.max_rate_change.into())
+            .map_or(false, |max_change| change > max_change);
+        if exceeds_limit {
+            return Err(ContractError::RateChangeTooLarge {
+                reference_rate,
+                new_rate,
+                max_rate_change: self.max_rate_change,
+                window: self.rate_change_window,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the given updates lower the minimum emission rate, raise
+    /// the maximum emission rate or loosen the rate change limit.
+    pub fn widens_bounds(&self, updates: &ConfigUpdates) -> bool {
+        let min_lowered = updates
+            .min_emission_per_second
+            .map_or(false, |min| min < self.min_emission_per_second);
+        let max_raised = updates
+            .max_emission_per_second
+            .map_or(false, |max| max > self.max_emission_per_second);
+        let rate_change_loosened = self.rate_change_window > 0
+            && (updates
+                .max_rate_change
+                .map_or(false, |change| change > self.max_rate_change)
+                || updates
+                    .rate_change_window
+                    .map_or(false, |window| window < self.rate_change_window));
+
+        min_lowered || max_raised || rate_change_loosened
+    }
+
+    /// Returns true if the given updates must be proposed and can only be
+    /// applied after the config update delay has passed. Updates that only
+    /// decrease `emission_per_second` are exempt if `instant_rate_decreases`
+    /// is set. Updates that widen the emission bounds are always timelocked,
+    /// even if the delay is zero.
+    pub fn is_timelocked(&self, updates: &ConfigUpdates) -> bool {
+        if self.widens_bounds(updates) {
+            return true;
+        }
+        if self.config_update_delay == 0 {
+            return false;
+        }
+
+        let is_rate_decrease_only = updates.only_updates_emission_rate()
+            && updates.emission_per_second < Some(self.emission_per_second);
+
+        !(self.instant_rate_decreases && is_rate_decrease_only)
+    }
+}
+
+impl ConfigUpdates {
+    /// Returns true if `emission_per_second` is the only field being updated
+    pub fn only_updates_emission_rate(&self) -> bool {
+        self.emission_per_second.is_some()
+            && *self
+                == ConfigUpdates {
+                    emission_per_second: self.emission_per_second,
+                    ..Default::default()
+                }
+    }
+
+    /// Removes `distribution_addr` from the updates and returns it. A new
+    /// distribution address is not applied with the other fields, as it must
+    /// accept first.
+    pub fn take_distribution_addr(&mut self) -> Option<String> {
+        self.distribution_addr.take()
+    }
+
+    /// Returns true if `distribution_addr` is the only field being updated
+    pub fn only_updates_distribution_addr(&self) -> bool {
+        self.distribution_addr.is_some()
+            && *self
+                == ConfigUpdates {
+                    distribution_addr: self.distribution_addr.clone(),
+                    ..Default::default()
+                }
+    }
 }
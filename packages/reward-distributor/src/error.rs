@@ -1,4 +1,4 @@
-use cosmwasm_std::{StdError, Uint128};
+use cosmwasm_std::{Decimal, Decimal256, StdError, Uint128};
 use cw_ownable::OwnershipError;
 
 #[derive(Debug, thiserror::Error)]
@@ -15,9 +15,114 @@ pub enum ContractError {
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Emission rate {rate} is outside of the bounds [{min}, {max}]")]
+    EmissionRateOutOfBounds {
+        rate: Decimal256,
+        min: Decimal256,
+        max: Decimal256,
+    },
+
+    #[error("Minimum emission rate {min} is greater than maximum emission rate {max}")]
+    InvalidEmissionBounds { min: Decimal256, max: Decimal256 },
+
+    #[error("Emitted rewards overflow")]
+    EmissionOverflow {},
+
+    #[error("Timestamp overflow")]
+    TimestampOverflow {},
+
+    #[error("Changing the emission rate from {reference_rate} to {new_rate} exceeds the maximum change of {max_rate_change} within {window} seconds")]
+    RateChangeTooLarge {
+        reference_rate: Decimal256,
+        new_rate: Decimal256,
+        max_rate_change: Decimal,
+        window: u64,
+    },
+
+    #[error("Distribution is paused")]
+    Paused {},
+
     #[error("Insufficient vault token balance. Vault token balance: {vault_token_balance}. Redeem amount: {redeem_amount}")]
     InsufficientVaultTokenBalance {
         vault_token_balance: Uint128,
         redeem_amount: Uint128,
     },
+
+    #[error(
+        "Insufficient spendable balance of {asset}. Spendable balance: {balance}. Amount: {amount}"
+    )]
+    InsufficientSpendableBalance {
+        asset: String,
+        balance: Uint128,
+        amount: Uint128,
+    },
+
+    #[error("Config update is timelocked. Propose it and apply it after {delay} seconds")]
+    ConfigUpdateTimelocked { delay: u64 },
+
+    #[error("No pending config update")]
+    NoPendingConfigUpdate {},
+
+    #[error("Pending config update can not be applied until {applicable_at}")]
+    ConfigUpdateNotApplicable { applicable_at: u64 },
+
+    #[error("Maximum slippage {max_slippage} must not be greater than 1")]
+    InvalidMaxSlippage { max_slippage: Decimal },
+
+    #[error("Destination pool asset {asset} is not received from redeeming the reward token")]
+    DestinationPoolAssetNotRedeemed { asset: String },
+
+    #[error("No reference rate set for destination pool asset {asset}")]
+    MissingReferenceRate { asset: String },
+
+    #[error("Fee of {fee_bps} bps must be lower than 10000 bps")]
+    InvalidFeeBps { fee_bps: u16 },
+
+    #[error("A fee recipient must be set to charge a fee")]
+    MissingFeeRecipient {},
+
+    #[error("Recipient {recipient} is not a contract of the pinned code id {expected}")]
+    RecipientCodeMismatch { recipient: String, expected: u64 },
+
+    #[error("There is no pending distribution address to accept")]
+    NoPendingRecipient {},
+
+    #[error("No assets held in escrow for {address}")]
+    NothingInEscrow { address: String },
+
+    #[error("A staking contract must be set to distribute to stakers")]
+    MissingStakingAddr {},
+
+    #[error("The staking contract cannot be changed once set")]
+    StakingAddrLocked {},
+
+    #[error("Rewards from a locked vault can only be claimed as raw vault tokens")]
+    LockedVaultClaim {},
+
+    #[error("Rewards from a locked vault can only be distributed to stakers as raw vault tokens")]
+    LockedVaultStaked {},
+
+    #[error("Cannot switch to raw delivery while unlocks of the reward vault are pending")]
+    UnlocksPending {},
+
+    #[error("Invalid Merkle hash {hash}, expected 32 hex-encoded bytes")]
+    InvalidMerkleHash { hash: String },
+
+    #[error("Invalid Merkle proof")]
+    InvalidMerkleProof {},
+
+    #[error("Leaf {index} of airdrop {airdrop_id} has already been claimed")]
+    LeafAlreadyClaimed { airdrop_id: u64, index: u64 },
+
+    #[error("Claim exceeds the remaining amount of airdrop {airdrop_id}")]
+    AirdropExhausted { airdrop_id: u64 },
+
+    #[error("Vesting cliff of {cliff} seconds must not be longer than the vesting duration of {duration} seconds")]
+    InvalidVestingSchedule { cliff: u64, duration: u64 },
+
+    #[error("No vested assets to withdraw")]
+    NothingVested {},
+
+    #[error("Unknown reply id: {id}")]
+    UnknownReplyId { id: u64 },
 }
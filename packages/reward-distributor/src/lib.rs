@@ -1,9 +1,11 @@
 pub mod config;
 pub mod error;
 pub mod msg;
+pub mod ramp;
 pub mod state;
 
 pub use config::*;
 pub use error::*;
 pub use msg::*;
+pub use ramp::*;
 pub use state::*;
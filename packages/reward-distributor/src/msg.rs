@@ -1,22 +1,76 @@
+use apollo_cw_asset::{AssetInfo, AssetInfoUnchecked, AssetList};
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{to_json_binary, CosmosMsg, Env, StdResult, Uint128, WasmMsg};
+use cosmwasm_std::{Addr, Decimal, Decimal256, Uint128};
 use cw_ownable::{cw_ownable_execute, cw_ownable_query};
+use cw_vault_standard::extensions::lockup::UnlockingPosition;
 
-use crate::{Config, ConfigUpdates, RewardType};
+use crate::{
+    Config, ConfigUpdates, Delivery, DestinationPool, DistributionMode, EmissionRamp,
+    MerkleAirdrop, PendingConfigUpdate, RewardType, Role, UnclaimedRewards, VestingPosition,
+    VestingSchedule,
+};
 
 /// An enum for the information needed to instantiate the contract depending on
 /// the type of reward token used.
 #[cw_serde]
 pub enum RewardInfo {
     /// The address of the vault if the reward token is a vault token
+    #[cfg(feature = "astroport")]
     AstroportVault(AstroportVault),
     /// The address of the Astroport pool if the reward token is an Astroport LP
     /// token
+    #[cfg(feature = "astroport")]
     AstroportPool(AstroportPool),
+    /// The LP token if the reward token is an LP token of a pool on any of the
+    /// supported DEXes
+    LpToken(LpToken),
     /// The denom of the native coin if the reward token is a native coin
     NativeCoin(String),
+    /// The address of the vault if the reward token is a vault token of any
+    /// cw-vault-standard vault. The redeemed base tokens are sent on as is.
+    GenericVault(String),
+    /// The subdenom of a tokenfactory denom to be created by the contract, if
+    /// the reward tokens are minted on distribution
+    Mint(MintReward),
 }
 
+#[cw_serde]
+pub struct MintReward {
+    /// The subdenom of the denom created by the contract, which becomes
+    /// `factory/{contract}/{subdenom}`. The denom creation fee must be sent
+    /// with the instantiate message.
+    pub subdenom: String,
+    /// The total supply of the denom above which no more rewards are minted
+    /// Rewards above the cap stay pending until they can be minted
+    pub max_supply: Option<Uint128>,
+}
+
+#[cw_serde]
+pub struct LpToken {
+    /// The LP token
+    pub lp_token: AssetInfoUnchecked,
+    /// The address of the Astroport liquidity manager. Must be set if the LP
+    /// token is an Astroport LP token.
+    pub liquidity_manager_addr: Option<String>,
+}
+
+#[cw_serde]
+pub struct DestinationPoolInfo {
+    /// The LP token of the pool to provide liquidity to
+    pub lp_token: AssetInfoUnchecked,
+    /// The address of the Astroport liquidity manager. Must be set if the LP
+    /// token is an Astroport LP token.
+    pub liquidity_manager_addr: Option<String>,
+    /// The maximum slippage accepted when providing liquidity, relative to the
+    /// amount of LP tokens expected at the reference rates
+    pub max_slippage: Decimal,
+    /// The amount of LP tokens expected per unit of each asset of the pool.
+    /// Used instead of the pool's current state to bound the LP tokens
+    /// received, so must be kept up to date by the owner.
+    pub lp_tokens_per_asset: Vec<(AssetInfoUnchecked, Decimal)>,
+}
+
+#[cfg(feature = "astroport")]
 #[cw_serde]
 pub struct AstroportPool {
     /// The address of the Astroport pool
@@ -25,10 +79,14 @@ pub struct AstroportPool {
     pub liquidity_manager_addr: String,
 }
 
+#[cfg(feature = "astroport")]
 #[cw_serde]
 pub struct AstroportVault {
     /// The address of the Astroport vault
     pub vault_addr: String,
+    /// The address of the Astroport pool that the vault holds liquidity in. If
+    /// not set, the pool is found through the vault's LP token.
+    pub pool_addr: Option<String>,
     /// The address of the Astroport liquidity manager
     pub liquidity_manager_addr: String,
 }
@@ -37,8 +95,11 @@ pub struct AstroportVault {
 pub struct InstantiateMsg {
     /// The account to be appointed the contract owner
     pub owner: String,
+    /// An account to be granted the guardian role, which can pause
+    /// distribution
+    pub guardian: Option<String>,
     /// The emission rate per second
-    pub emission_per_second: Uint128,
+    pub emission_per_second: Decimal256,
     /// The info needed to instantiate the contract depending on the type of
     /// reward token used
     pub reward_token_info: RewardInfo,
@@ -46,39 +107,172 @@ pub struct InstantiateMsg {
     pub distribution_addr: String,
     /// The unix timestamp at which rewards start being distributed
     pub rewards_start_time: u64,
+    /// The number of seconds that must pass between proposing a config update
+    /// and applying it
+    pub config_update_delay: u64,
+    /// Whether decreases of the emission rate are exempt from the config
+    /// update delay
+    pub instant_rate_decreases: bool,
+    /// The lowest emission rate that can be set
+    pub min_emission_per_second: Decimal256,
+    /// The highest emission rate that can be set
+    pub max_emission_per_second: Decimal256,
+    /// The maximum relative change of the emission rate within
+    /// `rate_change_window`
+    pub max_rate_change: Decimal,
+    /// The length in seconds of the rolling window in which `max_rate_change`
+    /// applies. If zero, the rate change is not limited.
+    pub rate_change_window: u64,
+    /// Whether rewards are delivered as the reward tokens themselves or as the
+    /// assets underlying them
+    pub delivery: Delivery,
+    /// The pool to provide the assets underlying the rewards to as liquidity.
+    /// If set, the LP tokens of this pool are distributed instead.
+    pub destination_pool: Option<DestinationPoolInfo>,
+    /// Whether rewards are pushed to the distribution address or accrue until
+    /// claimed by it
+    pub distribution_mode: DistributionMode,
+    /// The share of every delivery that is sent to `fee_recipient`, in basis
+    /// points
+    pub fee_bps: u16,
+    /// The address that the fee is sent to
+    pub fee_recipient: Option<String>,
+    /// The code id that the distribution address must be a contract of
+    pub recipient_code_id: Option<u64>,
+    /// The cw20-stake contract whose stakers share the rewards in staked mode
+    pub staking_addr: Option<String>,
 }
 
+#[cw_ownable_execute]
 #[cw_serde]
-/// The internal message variants that can be called by the contract itself
-pub enum InternalMsg {
-    /// Callback to be called after rewards have been redeemed from the vault to
-    /// send the underlying assets to the distribution address.
-    VaultTokensRedeemed {},
-    /// Callback to be called after LP tokens have been redeemed from the vault
-    /// to send the underlying
-    LpRedeemed {},
+pub enum ExecuteMsg {
+    /// Distributes rewards to the distribution address. If the reward vault is
+    /// locked, this starts unlocking the rewards and withdraws the unlocks
+    /// started by earlier distributions that have matured.
+    Distribute {},
+    /// Delivers the rewards accrued for the sender in pull mode. Can only be
+    /// called by the distribution address, or by a previous distribution
+    /// address with unclaimed rewards or unlocks started for it. Matured
+    /// unlocks of a locked reward vault are withdrawn to the sender. The
+    /// delivered assets are returned in the response data as a
+    /// `DistributeResponse`.
+    Claim {},
+    /// Accepts becoming the distribution address. Can only be called by the
+    /// pending distribution address proposed in a config update, or by its
+    /// admin if it is a contract.
+    AcceptRecipient {},
+    /// Retries sending the assets held in escrow for the distribution address.
+    /// Assets that still cannot be sent stay in escrow. Can be called by
+    /// anyone.
+    RetryDelivery {},
+    /// Sends the assets held in escrow for the sender to the sender
+    ClaimEscrow {},
+    /// Delivers the rewards accrued for the sender as a staker in staked mode.
+    /// The delivered assets are returned in the response data as a
+    /// `DistributeResponse`.
+    ClaimRewards {},
+    /// Updates the stake of a staker, after accruing its rewards in staked
+    /// mode for its previous stake. Can only be called by the staking
+    /// contract, as a cw20-stake stake change hook.
+    StakeChangeHook(StakeChangedHookMsg),
+    /// Registers an airdrop of up to `total_amount` reward tokens, claimed with
+    /// proofs against the hex-encoded sha256 `merkle_root`. The contract must
+    /// hold the reward tokens, on top of those owed to anyone else, unless they
+    /// are minted. The unclaimed remainder is not spent on other deliveries.
+    /// The airdrop id is returned in the
+    /// `airdrop_id` event attribute. Can only be called by the owner.
+    RegisterMerkleAirdrop {
+        merkle_root: String,
+        total_amount: Uint128,
+    },
+    /// Delivers the sender's `amount` of reward tokens from a Merkle airdrop.
+    /// The leaf is the sha256 hash of `"{index}:{address}:{amount}"`, and the
+    /// proof lists the hex-encoded sibling hashes from the leaf up. Each pair
+    /// of hashes is sorted before being hashed together. The delivered assets
+    /// are returned in the response data as a `DistributeResponse`.
+    /// Leaves claimed in part because of the max supply of minted rewards can
+    /// be claimed again for the rest of their amount.
+    ClaimMerkleAirdrop {
+        airdrop_id: u64,
+        index: u64,
+        amount: Uint128,
+        proof: Vec<String>,
+    },
+    /// Sets or unsets the vesting schedule of a recipient. Assets delivered to
+    /// a recipient with a vesting schedule are locked in a vesting position
+    /// instead of being sent. Only applies to later deliveries. Can be called
+    /// by the owner or a recipient manager.
+    SetVestingSchedule {
+        recipient: String,
+        schedule: Option<VestingSchedule>,
+    },
+    /// Sends the sender the assets of its vesting positions that have vested.
+    /// Only the `limit` oldest positions are withdrawn from, so that positions
+    /// that have accumulated can be withdrawn from over several calls.
+    WithdrawVested { limit: Option<u32> },
+    /// Update the contract's config. Errors if the update is timelocked, in
+    /// which case it must go through `ProposeConfigUpdate` instead. If
+    /// `ramp_duration` is set, the emission rate moves linearly from its
+    /// current value to the new one over that many seconds.
+    UpdateConfig {
+        updates: ConfigUpdates,
+        ramp_duration: Option<u64>,
+    },
+    /// Propose a config update that can be applied once the config update
+    /// delay has passed. Replaces any existing pending update.
+    ProposeConfigUpdate {
+        updates: ConfigUpdates,
+        ramp_duration: Option<u64>,
+    },
+    /// Apply the pending config update
+    ApplyConfigUpdate {},
+    /// Cancel the pending config update
+    CancelConfigUpdate {},
+    /// Grant a role to an address. Can only be called by the owner.
+    GrantRole { role: Role, address: String },
+    /// Revoke a role from an address. Can only be called by the owner.
+    RevokeRole { role: Role, address: String },
+    /// Pause distribution. Can be called by the owner or a guardian.
+    Pause {},
+    /// Unpause distribution. Can only be called by the owner.
+    Unpause {},
+    /// Set or unset the pool that the assets underlying the rewards are
+    /// provided to as liquidity. Can only be called by the owner.
+    SetDestinationPool {
+        destination_pool: Option<DestinationPoolInfo>,
+    },
+    /// Pause distribution and send the reward tokens that are not owed to
+    /// anyone to the recipient. Can only be called by the owner.
+    EmergencyWithdraw { recipient: String },
 }
 
-impl InternalMsg {
-    /// Creates a CosmosMsg::Wasm::Execute message from the internal message
-    pub fn into_cosmos_msg(&self, env: &Env) -> StdResult<CosmosMsg> {
-        Ok(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: env.contract.address.to_string(),
-            msg: to_json_binary(&ExecuteMsg::Internal(self.clone()))?,
-            funds: vec![],
-        }))
-    }
-}
+/// The message to migrate the contract. Rewrites state saved by earlier
+/// versions of the contract into the current format.
+#[cw_serde]
+pub struct MigrateMsg {}
 
-#[cw_ownable_execute]
+/// Messages that can only be sent by the chain, e.g. through governance. They
+/// mirror the owner's messages, so that governance can act even if the owner
+/// has renounced ownership or lost its keys.
 #[cw_serde]
-pub enum ExecuteMsg {
-    /// Distributes rewards to the distribution address
-    Distribute {},
-    /// Update the contract's config
-    UpdateConfig { updates: ConfigUpdates },
-    /// Callback handler that can only be called by the contract itself
-    Internal(InternalMsg),
+#[allow(clippy::large_enum_variant)]
+pub enum SudoMsg {
+    /// Update the contract's config. Unlike `ExecuteMsg::UpdateConfig`, this is
+    /// not subject to the config update delay.
+    UpdateConfig {
+        updates: ConfigUpdates,
+        ramp_duration: Option<u64>,
+    },
+    /// Transfer ownership of the contract to the given address immediately,
+    /// replacing the current owner and any pending ownership transfer
+    TransferOwnership { new_owner: String },
+    /// Pause distribution
+    Pause {},
+    /// Unpause distribution
+    Unpause {},
+    /// Pause distribution and send the reward tokens that are not owed to
+    /// anyone to the recipient
+    EmergencyWithdraw { recipient: String },
 }
 
 #[cw_ownable_query]
@@ -89,6 +283,122 @@ pub enum QueryMsg {
     /// Returns the config of the contract as well as non-configurable contract
     /// state
     State {},
+    #[returns(Option<PendingConfigUpdate>)]
+    /// Returns the pending config update, if any
+    PendingConfigUpdate {},
+    #[returns(Vec<Role>)]
+    /// Returns the roles granted to the given address
+    Roles { address: String },
+    #[returns(Vec<UnlockingPosition>)]
+    /// Returns the unlocking positions in the reward vault that have been
+    /// started by the contract but not yet withdrawn, paginated by lockup id
+    PendingUnlocks {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(Option<UnclaimedRewards>)]
+    /// Returns the rewards accrued in pull mode for the given previous
+    /// distribution address, that it has not claimed yet
+    UnclaimedRewards { address: String },
+    #[returns(Option<AssetList>)]
+    /// Returns the assets held in escrow for the given address, that could not
+    /// be delivered to it
+    Escrow { address: String },
+    #[returns(StakerRewardsResponse)]
+    /// Returns the stake of the given staker in staked mode and the rewards it
+    /// can claim as of the current block
+    StakerRewards { address: String },
+    #[returns(Option<MerkleAirdrop>)]
+    /// Returns the Merkle airdrop with the given id, if any
+    MerkleAirdrop { airdrop_id: u64 },
+    #[returns(bool)]
+    /// Returns whether the leaf with the given index of a Merkle airdrop has
+    /// been claimed
+    IsLeafClaimed { airdrop_id: u64, index: u64 },
+    #[returns(Option<VestingSchedule>)]
+    /// Returns the vesting schedule of the given recipient, if any
+    VestingSchedule { address: String },
+    #[returns(Vec<VestingPosition>)]
+    /// Returns the vesting positions of the given recipient that have not
+    /// been fully withdrawn, paginated by start time
+    VestingPositions {
+        address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+}
+
+/// The stake change hook sent by a cw20-stake contract. The staker's stake is
+/// queried from the staking contract. The amount is only used to find the
+/// stake before the change of a staker that has not been synced before.
+#[cw_serde]
+pub enum StakeChangedHookMsg {
+    Stake { addr: String, amount: Uint128 },
+    Unstake { addr: String, amount: Uint128 },
+}
+
+impl StakeChangedHookMsg {
+    /// Returns the address of the staker whose stake changed
+    pub fn staker(&self) -> &str {
+        match self {
+            StakeChangedHookMsg::Stake { addr, .. } | StakeChangedHookMsg::Unstake { addr, .. } => {
+                addr
+            }
+        }
+    }
+}
+
+/// The queries of a cw20-stake contract used to look up the stakes
+#[cw_serde]
+pub enum StakingQueryMsg {
+    StakedBalanceAtHeight {
+        address: String,
+        height: Option<u64>,
+    },
+    TotalStakedAtHeight {
+        height: Option<u64>,
+    },
+}
+
+/// The response to `StakingQueryMsg::StakedBalanceAtHeight`
+#[cw_serde]
+pub struct StakedBalanceAtHeightResponse {
+    pub balance: Uint128,
+    pub height: u64,
+}
+
+/// The response to `StakingQueryMsg::TotalStakedAtHeight`
+#[cw_serde]
+pub struct TotalStakedAtHeightResponse {
+    pub total: Uint128,
+    pub height: u64,
+}
+
+/// The response to a `StakerRewards` query
+#[cw_serde]
+pub struct StakerRewardsResponse {
+    /// The staker's stake, as of its last update
+    pub stake: Uint128,
+    /// The amount of reward tokens that the staker can claim
+    pub claimable: Uint128,
+}
+
+/// The response data of `Distribute` and of the claim messages, set once the
+/// rewards have been delivered
+#[cw_serde]
+pub struct DistributeResponse {
+    /// The unix timestamp from which the delivered rewards were accruing
+    pub period_start: u64,
+    /// The unix timestamp up to which the delivered rewards were accrued
+    pub period_end: u64,
+    /// The amount of reward tokens delivered, including the fee
+    pub reward_amount: Uint128,
+    /// The amount of reward tokens taken as the fee. Rewards delivered as
+    /// their underlying assets pay it in the assets redeemed from them.
+    pub fee_amount: Uint128,
+    /// The assets delivered to the recipient. Empty if the rewards have only
+    /// started unlocking from a locked reward vault.
+    pub delivered: AssetList,
 }
 
 #[cw_serde]
@@ -96,5 +406,14 @@ pub enum QueryMsg {
 pub struct StateResponse {
     pub config: Config,
     pub reward_token: RewardType,
+    pub reward_asset: AssetInfo,
     pub last_distributed: u64,
+    pub emission_ramp: Option<EmissionRamp>,
+    pub pending_rewards: Uint128,
+    pub paused: bool,
+    pub destination_pool: Option<DestinationPool>,
+    pub fees_collected: AssetList,
+    pub pending_recipient: Option<Addr>,
+    pub reward_index: Decimal256,
+    pub total_stake: Uint128,
 }
@@ -0,0 +1,112 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal256, Uint256};
+
+use crate::ContractError;
+
+/// A linear change of the emission rate, from `start_rate` at `start_time` to
+/// the configured `emission_per_second` at `end_time`.
+#[cw_serde]
+pub struct EmissionRamp {
+    /// The emission rate per second at the start of the ramp
+    pub start_rate: Decimal256,
+    /// The unix timestamp at which the ramp starts
+    pub start_time: u64,
+    /// The unix timestamp at which the ramp ends
+    pub end_time: u64,
+}
+
+impl EmissionRamp {
+    /// Returns the emission rate per second at the given time, where
+    /// `end_rate` is the rate the ramp is moving towards.
+    pub fn rate_at(&self, end_rate: Decimal256, time: u64) -> Decimal256 {
+        if time <= self.start_time {
+            return self.start_rate;
+        }
+        if time >= self.end_time {
+            return end_rate;
+        }
+
+        let elapsed = time - self.start_time;
+        let duration = self.end_time - self.start_time;
+        let change = Decimal256::from_ratio(elapsed, duration);
+        if end_rate >= self.start_rate {
+            self.start_rate + (end_rate - self.start_rate) * change
+        } else {
+            self.start_rate - (self.start_rate - end_rate) * change
+        }
+    }
+
+    /// Returns the amount emitted between `from` and `to`, where `end_rate` is
+    /// the rate the ramp is moving towards. The rate is `start_rate` before the
+    /// ramp and `end_rate` after it.
+    pub fn emitted_between(
+        &self,
+        end_rate: Decimal256,
+        from: u64,
+        to: u64,
+    ) -> Result<Decimal256, ContractError> {
+        if from >= to {
+            return Ok(Decimal256::zero());
+        }
+
+        // Before the ramp
+        let before_end = to.min(self.start_time);
+        let before = if from < before_end {
+            emitted_at_rate(self.start_rate, before_end - from)?
+        } else {
+            Decimal256::zero()
+        };
+
+        // During the ramp. The area under a linear segment is its length times
+        // the average of the rates at its ends. This is computed on the atomics
+        // of the rates as a single fraction, so that no rounding happens before
+        // the final division.
+        let ramp_start = from.max(self.start_time);
+        let ramp_end = to.min(self.end_time);
+        let during = if ramp_start < ramp_end {
+            let duration = Uint256::from(self.end_time - self.start_time);
+            let start_rate = self.start_rate.atomics();
+            let end_rate = end_rate.atomics();
+            let offsets = Uint256::from(ramp_start - self.start_time)
+                + Uint256::from(ramp_end - self.start_time);
+            let numerator = start_rate
+                .checked_mul(duration * Uint256::from(2u8))
+                .and_then(|start_area| {
+                    if end_rate >= start_rate {
+                        start_area.checked_add((end_rate - start_rate).checked_mul(offsets)?)
+                    } else {
+                        start_area.checked_sub((start_rate - end_rate).checked_mul(offsets)?)
+                    }
+                })
+                .map_err(|_| ContractError::EmissionOverflow {})?;
+            let emitted = numerator
+                .checked_multiply_ratio(
+                    Uint256::from(ramp_end - ramp_start),
+                    duration * Uint256::from(2u8),
+                )
+                .map_err(|_| ContractError::EmissionOverflow {})?;
+            Decimal256::new(emitted)
+        } else {
+            Decimal256::zero()
+        };
+
+        // After the ramp
+        let after_start = from.max(self.end_time);
+        let after = if after_start < to {
+            emitted_at_rate(end_rate, to - after_start)?
+        } else {
+            Decimal256::zero()
+        };
+
+        before
+            .checked_add(during)
+            .and_then(|emitted| emitted.checked_add(after))
+            .map_err(|_| ContractError::EmissionOverflow {})
+    }
+}
+
+/// Returns the amount emitted at a constant `rate` over `seconds`
+pub fn emitted_at_rate(rate: Decimal256, seconds: u64) -> Result<Decimal256, ContractError> {
+    rate.checked_mul(Decimal256::from_ratio(seconds, 1u8))
+        .map_err(|_| ContractError::EmissionOverflow {})
+}
@@ -1,10 +1,13 @@
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::StdError;
-use cw_dex::astroport::AstroportPool;
-use cw_storage_plus::Item;
+use cosmwasm_std::{Addr, Decimal, Decimal256, Empty, StdError, StdResult, Uint128};
+use cw_dex::Pool;
+use cw_storage_plus::{Item, Map};
 use cw_vault_standard::VaultContract;
 
-use crate::config::Config;
+use crate::config::{Config, ConfigUpdates};
+use crate::msg::DistributeResponse;
+use crate::ramp::EmissionRamp;
 
 /// An enum representing different types of reward tokens
 #[cw_serde]
@@ -13,25 +16,255 @@ pub enum RewardType {
     Vault {
         /// The vault contract
         vault: VaultContract,
-        /// The Astroport pool that the vault holds liquidity in
-        pool: AstroportPool,
+        /// The vaults wrapped by the reward vault, outermost first. Empty if
+        /// the base token of the reward vault is the LP token of the pool.
+        #[serde(default)]
+        inner_vaults: Vec<VaultContract>,
+        /// The pool that the innermost vault holds liquidity in
+        pool: Pool,
     },
-    /// The reward token is an Astroport LP token
-    LP(AstroportPool),
+    /// The reward token is an LP token
+    LP(Pool),
     /// The reward token is a native coin
     Coin(String),
+    /// The reward token is a vault token of a vault whose base token is
+    /// distributed directly
+    GenericVault {
+        /// The vault contract
+        vault: VaultContract,
+        /// The base token of the vault
+        base_token: AssetInfo,
+    },
+    /// The reward token is a tokenfactory denom created by the contract, which
+    /// is minted on distribution instead of being held
+    Mint {
+        /// The tokenfactory denom
+        denom: String,
+        /// The total supply of the denom above which no more rewards are minted
+        /// Rewards above the cap stay pending until they can be minted
+        max_supply: Option<Uint128>,
+    },
 }
 
 impl RewardType {
-    pub fn into_pool(self) -> Result<AstroportPool, StdError> {
+    pub fn into_pool(self) -> Result<Pool, StdError> {
         match self {
-            RewardType::Vault { vault: _, pool } => Ok(pool),
+            RewardType::Vault { pool, .. } => Ok(pool),
             RewardType::LP(pool) => Ok(pool),
             RewardType::Coin(_) => Err(StdError::generic_err(
                 "Cannot redeem vault tokens from coin reward",
             )),
+            RewardType::GenericVault { .. } => Err(StdError::generic_err(
+                "Generic vault reward has no pool to withdraw liquidity from",
+            )),
+            RewardType::Mint { .. } => Err(StdError::generic_err(
+                "Cannot redeem vault tokens from minted reward",
+            )),
+        }
+    }
+}
+
+/// A pool that the assets underlying the rewards are provided as liquidity to,
+/// on behalf of the distribution address
+#[cw_serde]
+pub struct DestinationPool {
+    /// The pool to provide liquidity to
+    pub pool: Pool,
+    /// The maximum slippage accepted when providing liquidity, relative to the
+    /// amount of LP tokens expected at the reference rates
+    pub max_slippage: Decimal,
+    /// The amount of LP tokens expected per unit of each asset of the pool
+    pub lp_tokens_per_asset: Vec<(AssetInfo, Decimal)>,
+}
+
+/// A config update that has been proposed and can be applied once the config
+/// update delay has passed
+#[cw_serde]
+pub struct PendingConfigUpdate {
+    /// The proposed config updates
+    pub updates: ConfigUpdates,
+    /// The number of seconds over which to ramp the emission rate to its new
+    /// value when the update is applied
+    pub ramp_duration: Option<u64>,
+    /// The unix timestamp from which the update can be applied
+    pub applicable_at: u64,
+}
+
+/// The roles that can be granted by the owner, in addition to the owner itself
+#[cw_serde]
+pub enum Role {
+    /// Can change the emission rate
+    Operator,
+    /// Can change the distribution address
+    RecipientManager,
+    /// Can pause distribution
+    Guardian,
+}
+
+impl Role {
+    /// All roles that can be granted
+    pub const ALL: [Role; 3] = [Role::Operator, Role::RecipientManager, Role::Guardian];
+
+    /// Returns the key under which the role is stored
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Operator => "operator",
+            Role::RecipientManager => "recipient_manager",
+            Role::Guardian => "guardian",
+        }
+    }
+}
+
+/// Rewards accrued in pull mode for a previous distribution address
+#[cw_serde]
+pub struct UnclaimedRewards {
+    /// The amount of reward tokens
+    pub amount: Uint128,
+    /// The unix timestamp from which the rewards were accruing
+    pub period_start: u64,
+    /// The unix timestamp up to which the rewards were accrued
+    pub period_end: u64,
+}
+
+/// A delivery of rewards whose assets are received in a reply
+#[cw_serde]
+pub struct DeliveryInProgress {
+    /// The address that the assets are sent to
+    pub recipient: Addr,
+    /// The unix timestamp from which the rewards were accruing
+    pub period_start: u64,
+    /// The unix timestamp up to which the rewards were accrued
+    pub period_end: u64,
+    /// The amount of reward tokens being delivered, including the fee
+    pub reward_amount: Uint128,
+    /// The amount of reward tokens taken as the fee
+    pub fee_amount: Uint128,
+}
+
+impl DeliveryInProgress {
+    /// Returns the response data for the delivery of the given assets
+    pub fn into_response(self, delivered: AssetList) -> DistributeResponse {
+        DistributeResponse {
+            period_start: self.period_start,
+            period_end: self.period_end,
+            reward_amount: self.reward_amount,
+            fee_amount: self.fee_amount,
+            delivered,
+        }
+    }
+}
+
+/// Transfers of delivered assets whose failures are handled in a reply, by
+/// holding the asset in escrow for its recipient
+#[cw_serde]
+pub struct PendingTransfers {
+    /// The recipient and the asset of each transfer, in the order of the
+    /// transfer messages
+    pub transfers: Vec<(Addr, Asset)>,
+}
+
+/// A staker's share of the rewards distributed in staked mode
+#[cw_serde]
+pub struct StakerInfo {
+    /// The staker's stake in the staking contract, as of its last update
+    pub stake: Uint128,
+    /// The reward index up to which the staker's rewards have been accrued
+    pub reward_index: Decimal256,
+    /// The rewards accrued by the staker and not claimed yet, including
+    /// fractions of reward tokens
+    pub accrued: Decimal256,
+    /// The unix timestamp from which the unclaimed rewards have been accruing
+    pub accruing_since: u64,
+}
+
+impl StakerInfo {
+    /// Accrues the staker's rewards for its stake up to the given reward index
+    pub fn accrue(&mut self, reward_index: Decimal256) -> StdResult<()> {
+        let index_diff = reward_index.checked_sub(self.reward_index)?;
+        let rewards = Decimal256::from_ratio(self.stake, 1u8).checked_mul(index_diff)?;
+        self.accrued = self.accrued.checked_add(rewards)?;
+        self.reward_index = reward_index;
+        Ok(())
+    }
+}
+
+/// An airdrop of reward tokens whose claims are proven against a Merkle root
+#[cw_serde]
+pub struct MerkleAirdrop {
+    /// The hex-encoded sha256 Merkle root of the claims
+    pub merkle_root: String,
+    /// The total amount of reward tokens that can be claimed
+    pub total_amount: Uint128,
+    /// The amount of reward tokens claimed so far
+    pub claimed_amount: Uint128,
+}
+
+/// A vesting schedule for the assets delivered to a recipient
+#[cw_serde]
+#[derive(Copy)]
+pub struct VestingSchedule {
+    /// The number of seconds after delivery before any assets vest
+    pub cliff: u64,
+    /// The number of seconds after delivery over which the assets vest
+    /// linearly
+    pub duration: u64,
+}
+
+/// Assets delivered to a recipient with a vesting schedule, that are released
+/// as they vest
+#[cw_serde]
+pub struct VestingPosition {
+    /// The unix timestamp at which the assets were delivered
+    pub start_time: u64,
+    /// The vesting schedule of the recipient at the time of delivery
+    pub schedule: VestingSchedule,
+    /// The assets delivered
+    pub assets: AssetList,
+    /// The assets withdrawn so far
+    pub withdrawn: AssetList,
+}
+
+impl VestingPosition {
+    /// Returns the assets that have vested at the given time and have not been
+    /// withdrawn yet
+    pub fn withdrawable(&self, current_time: u64) -> StdResult<AssetList> {
+        let elapsed = current_time.saturating_sub(self.start_time);
+        let mut withdrawable = AssetList::new();
+        if elapsed < self.schedule.cliff {
+            return Ok(withdrawable);
+        }
+
+        for asset in self.assets.iter() {
+            let vested = if elapsed >= self.schedule.duration {
+                asset.amount
+            } else {
+                asset.amount.multiply_ratio(elapsed, self.schedule.duration)
+            };
+            let withdrawn = self
+                .withdrawn
+                .find(&asset.info)
+                .map_or(Uint128::zero(), |withdrawn| withdrawn.amount);
+            if vested > withdrawn {
+                withdrawable.add(&Asset::new(asset.info.clone(), vested - withdrawn))?;
+            }
         }
+        Ok(withdrawable)
     }
+
+    /// Returns whether all assets have vested at the given time
+    pub fn is_fully_vested(&self, current_time: u64) -> bool {
+        let elapsed = current_time.saturating_sub(self.start_time);
+        elapsed >= self.schedule.cliff.max(self.schedule.duration)
+    }
+}
+
+/// An emission rate that was replaced by a rate change
+#[cw_serde]
+pub struct RateChangeCheckpoint {
+    /// The emission rate before the change
+    pub rate: Decimal256,
+    /// The unix timestamp of the change, up to which the rate was in effect
+    pub time: u64,
 }
 
 /// Stores the contract's config
@@ -42,3 +275,117 @@ pub const REWARD_TOKEN: Item<RewardType> = Item::new("reward_token");
 
 /// Stores the last timestamp that rewards were distributed
 pub const LAST_DISTRIBUTED: Item<u64> = Item::new("last_distributed");
+
+/// Stores the amount of reward tokens that have been emitted but not yet
+/// distributed
+pub const PENDING_REWARDS: Item<Uint128> = Item::new("pending_rewards");
+
+/// Stores the fraction of a reward token that has been emitted but not yet
+/// added to the pending rewards, to be carried over to the next distribution
+pub const EMISSION_REMAINDER: Item<Decimal> = Item::new("emission_remainder");
+
+/// Stores whether distribution is paused. Rewards keep being emitted while
+/// paused and are distributed once unpaused.
+pub const PAUSED: Item<bool> = Item::new("paused");
+
+/// Stores the roles granted to each address, keyed by address and role
+pub const ROLES: Map<(&Addr, &str), Empty> = Map::new("roles");
+
+/// Stores the ongoing ramp of the emission rate towards the configured
+/// `emission_per_second`, if any
+pub const EMISSION_RAMP: Item<EmissionRamp> = Item::new("emission_ramp");
+
+/// Stores the emission rates replaced within the last `rate_change_window`
+/// seconds, against which rate changes are limited by `max_rate_change`
+pub const RATE_CHANGE_HISTORY: Item<Vec<RateChangeCheckpoint>> = Item::new("rate_change_history");
+
+/// Stores the config update that is waiting for the config update delay to pass
+pub const PENDING_CONFIG_UPDATE: Item<PendingConfigUpdate> = Item::new("pending_config_update");
+
+/// Stores the ids of the unlocking positions in a locked reward vault that
+/// have been started but not yet withdrawn, with the recipient that the
+/// unlocked assets are delivered to
+pub const PENDING_UNLOCKS: Map<u64, Addr> = Map::new("pending_unlocks");
+
+/// Stores the native tokenfactory LP denoms of Astroport pairs, keyed by pair
+/// address. cw-dex only represents CW20 LP tokens of Astroport pairs, so the
+/// LP token of these pairs is looked up here instead.
+pub const ASTROPORT_NATIVE_LP_TOKENS: Map<&Addr, String> = Map::new("astroport_native_lp_tokens");
+
+/// Stores the pool that the redeemed assets are provided as liquidity to before
+/// being distributed, if any
+pub const DESTINATION_POOL: Item<DestinationPool> = Item::new("destination_pool");
+
+/// Stores the rewards accrued in pull mode for previous distribution
+/// addresses, that they have not claimed yet. These are not spent on
+/// deliveries to anyone else.
+pub const UNCLAIMED_REWARDS: Map<&Addr, UnclaimedRewards> = Map::new("unclaimed_rewards");
+
+/// Stores the unix timestamp from which the pending rewards have been accruing
+pub const PENDING_SINCE: Item<u64> = Item::new("pending_since");
+
+/// Stores the total amounts of the assets sent to the fee recipient as the fee
+pub const FEES_COLLECTED: Item<AssetList> = Item::new("fees_collected");
+
+/// Stores the address proposed as the new distribution address, until it
+/// accepts with `AcceptRecipient`
+pub const PENDING_RECIPIENT: Item<Addr> = Item::new("pending_recipient");
+
+/// Stores the amount of reward tokens distributed per unit of stake in staked
+/// mode, since staked mode was first used
+pub const REWARD_INDEX: Item<Decimal256> = Item::new("reward_index");
+
+/// Stores the total stake in the staking contract in staked mode. It is seeded
+/// from the staking contract when switching to staked mode, and follows the
+/// stake changes from then on.
+pub const TOTAL_STAKE: Item<Uint128> = Item::new("total_stake");
+
+/// Stores the reward tokens shared between the stakers in staked mode, that
+/// they have not claimed yet
+pub const STAKER_REWARDS: Item<Uint128> = Item::new("staker_rewards");
+
+/// Stores the stake and rewards of each staker. Stakers are tracked from their
+/// first stake change or claim, and accrue rewards from the start with the
+/// stake they had until then.
+pub const STAKERS: Map<&Addr, StakerInfo> = Map::new("stakers");
+
+/// Stores the Merkle airdrops registered by the owner, keyed by airdrop id
+pub const MERKLE_AIRDROPS: Map<u64, MerkleAirdrop> = Map::new("merkle_airdrops");
+
+/// Stores which leaves of each Merkle airdrop have been claimed, keyed by
+/// airdrop id and word index. Each word is a bitmap of 64 leaves.
+pub const CLAIMED_LEAVES: Map<(u64, u64), u64> = Map::new("claimed_leaves");
+
+/// Stores the amount claimed so far of leaves that were only claimed in part,
+/// because minting the full amount would have exceeded the max supply. Keyed
+/// by airdrop id and leaf index.
+pub const PARTIALLY_CLAIMED_LEAVES: Map<(u64, u64), Uint128> = Map::new("partially_claimed_leaves");
+
+/// Stores the vesting schedule of each recipient whose assets vest
+pub const VESTING_SCHEDULES: Map<&Addr, VestingSchedule> = Map::new("vesting_schedules");
+
+/// Stores the vesting positions of each recipient, keyed by recipient and
+/// start time. Deliveries to the same recipient in the same block share a
+/// position.
+pub const VESTING_POSITIONS: Map<(&Addr, u64), VestingPosition> = Map::new("vesting_positions");
+
+/// Stores the delivery of rewards currently in progress, for the reply
+/// handlers to send on the assets received and set the response data
+pub const DELIVERY_IN_PROGRESS: Item<DeliveryInProgress> = Item::new("delivery_in_progress");
+
+/// Stores the transfers of delivered assets currently in progress, for the
+/// reply handler to find the asset of a failed transfer
+pub const PENDING_TRANSFERS: Item<PendingTransfers> = Item::new("pending_transfers");
+
+/// Stores the assets that could not be delivered to a recipient, held in
+/// escrow until they are released with `RetryDelivery` or `ClaimEscrow`
+pub const ESCROW: Map<&Addr, AssetList> = Map::new("escrow");
+
+/// Stores the total of the assets held for recipients, in escrow or in vesting
+/// positions. These can not be spent by the contract.
+pub const HELD_ASSETS: Item<AssetList> = Item::new("held_assets");
+
+/// Stores the contract's balances of the assets expected to be received from a
+/// submessage, snapshotted right before the submessage is executed. Used in the
+/// reply handlers to only forward the balance difference.
+pub const BALANCE_SNAPSHOT: Item<Vec<Asset>> = Item::new("balance_snapshot");
@@ -1 +1,5 @@
+pub mod mock_cw20;
+pub mod mock_legacy_state;
+pub mod mock_staking;
+pub mod mock_vault;
 pub mod robot;
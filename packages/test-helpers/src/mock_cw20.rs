@@ -0,0 +1,122 @@
+//! A minimal CW20 token for testing recipients that reject deliveries.
+//! Transfers to addresses marked as rejected fail, as they would for a token
+//! that refuses to send to the recipient.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    from_json, to_json_binary, to_json_vec, Binary, Deps, DepsMut, Env, MessageInfo, Response,
+    StdError, StdResult, Storage, Uint128,
+};
+use cw20::{BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
+use cw_it::cw_multi_test::ContractWrapper;
+use cw_it::ContractType;
+
+const BALANCE_PREFIX: &[u8] = b"balance:";
+const REJECTED_PREFIX: &[u8] = b"rejected:";
+const TOTAL_SUPPLY_KEY: &[u8] = b"total_supply";
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// The addresses and the amounts of tokens issued to them
+    pub initial_balances: Vec<(String, Uint128)>,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Transfers tokens from the sender, as `Cw20ExecuteMsg::Transfer` does
+    Transfer { recipient: String, amount: Uint128 },
+    /// Sets whether transfers to the given address are rejected
+    SetRejected { address: String, rejected: bool },
+}
+
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    let mut total_supply = Uint128::zero();
+    for (address, amount) in msg.initial_balances {
+        save_balance(deps.storage, &address, amount)?;
+        total_supply += amount;
+    }
+    deps.storage
+        .set(TOTAL_SUPPLY_KEY, &to_json_vec(&total_supply)?);
+    Ok(Response::new())
+}
+
+pub fn execute(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::Transfer { recipient, amount } => {
+            if deps
+                .storage
+                .get(&[REJECTED_PREFIX, recipient.as_bytes()].concat())
+                .is_some()
+            {
+                return Err(StdError::generic_err(format!(
+                    "Transfers to {recipient} are rejected"
+                )));
+            }
+            let sender = info.sender.to_string();
+            let sender_balance = load_balance(deps.as_ref(), &sender)?.checked_sub(amount)?;
+            save_balance(deps.storage, &sender, sender_balance)?;
+            let recipient_balance = load_balance(deps.as_ref(), &recipient)? + amount;
+            save_balance(deps.storage, &recipient, recipient_balance)?;
+        }
+        ExecuteMsg::SetRejected { address, rejected } => {
+            let key = [REJECTED_PREFIX, address.as_bytes()].concat();
+            if rejected {
+                deps.storage.set(&key, &[1]);
+            } else {
+                deps.storage.remove(&key);
+            }
+        }
+    }
+    Ok(Response::new())
+}
+
+pub fn query(deps: Deps, _env: Env, msg: Cw20QueryMsg) -> StdResult<Binary> {
+    match msg {
+        Cw20QueryMsg::Balance { address } => to_json_binary(&BalanceResponse {
+            balance: load_balance(deps, &address)?,
+        }),
+        Cw20QueryMsg::TokenInfo {} => to_json_binary(&TokenInfoResponse {
+            name: "Mock token".to_string(),
+            symbol: "MOCK".to_string(),
+            decimals: 6,
+            total_supply: deps
+                .storage
+                .get(TOTAL_SUPPLY_KEY)
+                .map_or(Ok(Uint128::zero()), from_json)?,
+        }),
+        _ => Err(StdError::generic_err("Unsupported query")),
+    }
+}
+
+fn load_balance(deps: Deps, address: &str) -> StdResult<Uint128> {
+    deps.storage
+        .get(&[BALANCE_PREFIX, address.as_bytes()].concat())
+        .map_or(Ok(Uint128::zero()), from_json)
+}
+
+fn save_balance(storage: &mut dyn Storage, address: &str, balance: Uint128) -> StdResult<()> {
+    storage.set(
+        &[BALANCE_PREFIX, address.as_bytes()].concat(),
+        &to_json_vec(&balance)?,
+    );
+    Ok(())
+}
+
+/// Returns the contract code of the mock token to be able to upload it
+pub fn contract() -> ContractType {
+    ContractType::MultiTestContract(Box::new(ContractWrapper::new_with_empty(
+        execute,
+        instantiate,
+        query,
+    )))
+}
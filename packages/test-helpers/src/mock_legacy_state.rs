@@ -0,0 +1,48 @@
+//! A contract that saves the given raw storage entries on instantiation and
+//! migration, for setting up state saved by earlier versions of a contract
+//! before migrating it to the current version.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdError, StdResult};
+use cw_it::cw_multi_test::ContractWrapper;
+use cw_it::ContractType;
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// The storage keys and the raw values saved under them
+    pub entries: Vec<(String, Binary)>,
+}
+
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    for (key, value) in msg.entries {
+        deps.storage.set(key.as_bytes(), value.as_slice());
+    }
+    Ok(Response::new())
+}
+
+pub fn migrate(deps: DepsMut, _env: Env, msg: InstantiateMsg) -> StdResult<Response> {
+    for (key, value) in msg.entries {
+        deps.storage.set(key.as_bytes(), value.as_slice());
+    }
+    Ok(Response::new())
+}
+
+pub fn execute(_deps: DepsMut, _env: Env, _info: MessageInfo, _msg: Empty) -> StdResult<Response> {
+    Err(StdError::generic_err("Unsupported message"))
+}
+
+pub fn query(_deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+    Err(StdError::generic_err("Unsupported query"))
+}
+
+/// Returns the contract code of the mock contract to be able to upload it
+pub fn contract() -> ContractType {
+    ContractType::MultiTestContract(Box::new(
+        ContractWrapper::new_with_empty(execute, instantiate, query).with_migrate(migrate),
+    ))
+}
@@ -0,0 +1,187 @@
+//! A minimal cw20-stake style staking contract for testing staked mode. Stakes
+//! are native coins of a single denom, unstaking returns them immediately, and
+//! every stake change is sent to the registered hooks.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    coins, from_json, to_json_binary, to_json_vec, BankMsg, Binary, Deps, DepsMut, Env,
+    MessageInfo, Response, StdError, StdResult, Storage, Uint128, WasmMsg,
+};
+use cw_it::cw_multi_test::ContractWrapper;
+use cw_it::ContractType;
+use neutron_astroport_reward_distributor::msg::{
+    StakeChangedHookMsg, StakedBalanceAtHeightResponse, StakingQueryMsg,
+    TotalStakedAtHeightResponse,
+};
+
+const CONFIG_KEY: &[u8] = b"config";
+const HOOKS_KEY: &[u8] = b"hooks";
+const STAKE_PREFIX: &[u8] = b"stake:";
+const TOTAL_STAKE_KEY: &[u8] = b"total_stake";
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// The native denom that is staked
+    pub denom: String,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Stakes the native coins sent
+    Stake {},
+    /// Unstakes the given amount and sends it back
+    Unstake { amount: Uint128 },
+    /// Registers a contract to receive stake change hooks
+    AddHook { addr: String },
+}
+
+/// The message that hook receivers are called with, as sent by cw20-stake
+#[cw_serde]
+enum StakeChangedExecuteMsg {
+    StakeChangeHook(StakeChangedHookMsg),
+}
+
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    deps.storage.set(CONFIG_KEY, &to_json_vec(&msg)?);
+    deps.storage
+        .set(HOOKS_KEY, &to_json_vec(&Vec::<String>::new())?);
+    Ok(Response::new())
+}
+
+pub fn execute(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> StdResult<Response> {
+    let config = load_config(deps.as_ref())?;
+    let staker = info.sender.to_string();
+    let stake = load_stake(deps.as_ref(), &staker)?;
+    let total_stake = load_total_stake(deps.as_ref())?;
+
+    let (hook_msg, res) = match msg {
+        ExecuteMsg::Stake {} => {
+            let amount = info
+                .funds
+                .iter()
+                .find(|coin| coin.denom == config.denom)
+                .map(|coin| coin.amount)
+                .unwrap_or_default();
+            save_stake(deps.storage, &staker, stake + amount)?;
+            save_total_stake(deps.storage, total_stake + amount)?;
+            (
+                StakeChangedHookMsg::Stake {
+                    addr: staker,
+                    amount,
+                },
+                Response::new(),
+            )
+        }
+        ExecuteMsg::Unstake { amount } => {
+            save_stake(deps.storage, &staker, stake.checked_sub(amount)?)?;
+            save_total_stake(deps.storage, total_stake.checked_sub(amount)?)?;
+            let res = Response::new().add_message(BankMsg::Send {
+                to_address: staker.clone(),
+                amount: coins(amount.u128(), config.denom),
+            });
+            (
+                StakeChangedHookMsg::Unstake {
+                    addr: staker,
+                    amount,
+                },
+                res,
+            )
+        }
+        ExecuteMsg::AddHook { addr } => {
+            let mut hooks = load_hooks(deps.as_ref())?;
+            hooks.push(addr);
+            deps.storage.set(HOOKS_KEY, &to_json_vec(&hooks)?);
+            return Ok(Response::new());
+        }
+    };
+
+    let hook_msgs = load_hooks(deps.as_ref())?
+        .into_iter()
+        .map(|hook| {
+            Ok(WasmMsg::Execute {
+                contract_addr: hook,
+                msg: to_json_binary(&StakeChangedExecuteMsg::StakeChangeHook(hook_msg.clone()))?,
+                funds: vec![],
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(res.add_messages(hook_msgs))
+}
+
+pub fn query(deps: Deps, env: Env, msg: StakingQueryMsg) -> StdResult<Binary> {
+    match msg {
+        StakingQueryMsg::StakedBalanceAtHeight { address, .. } => {
+            to_json_binary(&StakedBalanceAtHeightResponse {
+                balance: load_stake(deps, &address)?,
+                height: env.block.height,
+            })
+        }
+        StakingQueryMsg::TotalStakedAtHeight { .. } => {
+            to_json_binary(&TotalStakedAtHeightResponse {
+                total: load_total_stake(deps)?,
+                height: env.block.height,
+            })
+        }
+    }
+}
+
+fn load_config(deps: Deps) -> StdResult<InstantiateMsg> {
+    from_json(
+        deps.storage
+            .get(CONFIG_KEY)
+            .ok_or_else(|| StdError::not_found("config"))?,
+    )
+}
+
+fn load_hooks(deps: Deps) -> StdResult<Vec<String>> {
+    from_json(
+        deps.storage
+            .get(HOOKS_KEY)
+            .ok_or_else(|| StdError::not_found("hooks"))?,
+    )
+}
+
+fn load_stake(deps: Deps, staker: &str) -> StdResult<Uint128> {
+    deps.storage
+        .get(&[STAKE_PREFIX, staker.as_bytes()].concat())
+        .map_or(Ok(Uint128::zero()), from_json)
+}
+
+fn save_stake(storage: &mut dyn Storage, staker: &str, stake: Uint128) -> StdResult<()> {
+    storage.set(
+        &[STAKE_PREFIX, staker.as_bytes()].concat(),
+        &to_json_vec(&stake)?,
+    );
+    Ok(())
+}
+
+fn load_total_stake(deps: Deps) -> StdResult<Uint128> {
+    deps.storage
+        .get(TOTAL_STAKE_KEY)
+        .map_or(Ok(Uint128::zero()), from_json)
+}
+
+fn save_total_stake(storage: &mut dyn Storage, total_stake: Uint128) -> StdResult<()> {
+    storage.set(TOTAL_STAKE_KEY, &to_json_vec(&total_stake)?);
+    Ok(())
+}
+
+/// Returns the contract code of the mock staking contract to be able to upload
+/// it
+pub fn contract() -> ContractType {
+    ContractType::MultiTestContract(Box::new(ContractWrapper::new_with_empty(
+        execute,
+        instantiate,
+        query,
+    )))
+}
@@ -0,0 +1,93 @@
+//! A minimal cw-vault-standard vault for testing vaults that wrap other vaults.
+//! Vault tokens are issued 1:1 for base tokens from the vault's own balance of
+//! the vault token, so the vault must be funded with vault tokens before use.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    coins, from_json, to_json_binary, to_json_vec, BankMsg, Binary, Deps, DepsMut, Env,
+    MessageInfo, Response, StdError, StdResult,
+};
+use cw_it::cw_multi_test::ContractWrapper;
+use cw_it::ContractType;
+use cw_vault_standard::{
+    VaultInfoResponse, VaultStandardExecuteMsg, VaultStandardInfoResponse, VaultStandardQueryMsg,
+};
+
+const CONFIG_KEY: &[u8] = b"config";
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// The native denom accepted for deposits
+    pub base_token: String,
+    /// The native denom issued for deposits
+    pub vault_token: String,
+}
+
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    deps.storage.set(CONFIG_KEY, &to_json_vec(&msg)?);
+    Ok(Response::new())
+}
+
+pub fn execute(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: VaultStandardExecuteMsg,
+) -> StdResult<Response> {
+    let config = load_config(deps.as_ref())?;
+    let (amount, recipient, denom) = match msg {
+        VaultStandardExecuteMsg::Deposit { amount, recipient } => {
+            (amount, recipient, config.vault_token)
+        }
+        VaultStandardExecuteMsg::Redeem { recipient, amount } => {
+            (amount, recipient, config.base_token)
+        }
+        VaultStandardExecuteMsg::VaultExtension(_) => {
+            return Err(StdError::generic_err("Unsupported message"))
+        }
+    };
+
+    Ok(Response::new().add_message(BankMsg::Send {
+        to_address: recipient.unwrap_or_else(|| info.sender.to_string()),
+        amount: coins(amount.u128(), denom),
+    }))
+}
+
+pub fn query(deps: Deps, _env: Env, msg: VaultStandardQueryMsg) -> StdResult<Binary> {
+    let config = load_config(deps)?;
+    match msg {
+        VaultStandardQueryMsg::VaultStandardInfo {} => to_json_binary(&VaultStandardInfoResponse {
+            version: 1,
+            extensions: vec![],
+        }),
+        VaultStandardQueryMsg::Info {} => to_json_binary(&VaultInfoResponse {
+            base_token: config.base_token,
+            vault_token: config.vault_token,
+        }),
+        VaultStandardQueryMsg::ConvertToShares { amount }
+        | VaultStandardQueryMsg::ConvertToAssets { amount } => to_json_binary(&amount),
+        _ => Err(StdError::generic_err("Unsupported query")),
+    }
+}
+
+fn load_config(deps: Deps) -> StdResult<InstantiateMsg> {
+    from_json(
+        deps.storage
+            .get(CONFIG_KEY)
+            .ok_or_else(|| StdError::not_found("config"))?,
+    )
+}
+
+/// Returns the contract code of the mock vault to be able to upload it
+pub fn contract() -> ContractType {
+    ContractType::MultiTestContract(Box::new(ContractWrapper::new_with_empty(
+        execute,
+        instantiate,
+        query,
+    )))
+}
@@ -1,6 +1,7 @@
 use std::str::FromStr;
 
-use cosmwasm_std::{Addr, Coin, Coins, Decimal, Uint128};
+use apollo_cw_asset::AssetList;
+use cosmwasm_std::{Addr, Coin, Coins, Decimal, Decimal256, Uint128};
 use cw20::Cw20ExecuteMsg;
 use cw_dex::astroport::AstroportPool;
 use cw_it::astroport::robot::AstroportTestRobot;
@@ -13,6 +14,7 @@ use cw_it::test_tube::{Account, Module, SigningAccount, Wasm};
 use cw_it::traits::CwItRunner;
 use cw_it::{ContractType, TestRunner};
 use cw_ownable::Ownership;
+use cw_vault_standard::extensions::lockup::UnlockingPosition;
 use locked_astroport_vault::helpers::INITIAL_VAULT_TOKENS_PER_BASE_TOKEN;
 use locked_astroport_vault_test_helpers::cw_vault_standard_test_helpers::traits::CwVaultStandardRobot;
 use locked_astroport_vault_test_helpers::robot::{
@@ -23,7 +25,10 @@ use neutron_astroport_reward_distributor::InstantiateMsg;
 
 #[cfg(feature = "osmosis-test-tube")]
 use cw_it::Artifact;
-use reward_distributor::{Config, ConfigUpdates, QueryMsg, RewardInfo};
+use reward_distributor::{
+    Config, ConfigUpdates, Delivery, DistributionMode, PendingConfigUpdate, QueryMsg, RewardInfo,
+    Role,
+};
 
 pub const REWARD_DISTRIBUTOR_WASM_NAME: &str = "neutron_astroport_reward_distributor_contract.wasm";
 
@@ -53,8 +58,19 @@ pub struct RewardDistributorRobot<'a> {
 #[derive(Clone, Debug)]
 pub enum TestRewardType {
     VaultToken,
+    /// A vault token of a vault with the given lock duration in seconds
+    LockedVaultToken(u64),
+    /// A vault token of the reward vault used as a generic vault, distributing
+    /// the CW20 LP tokens directly
+    GenericVaultToken,
     LpToken,
     NativeCoin(String),
+    /// A tokenfactory denom with the given subdenom, minted by the contract up
+    /// to the optional max supply
+    Mint {
+        subdenom: String,
+        max_supply: Option<Uint128>,
+    },
 }
 
 /// A trait with helper functions for testing the reward distributor contract.
@@ -62,13 +78,16 @@ impl<'a> RewardDistributorRobot<'a> {
     /// Returns the contract code to be able to upload the contract
     pub fn contract(runner: &TestRunner, _artifacts_dir: &str) -> ContractType {
         match runner {
-            TestRunner::MultiTest(_) => {
-                ContractType::MultiTestContract(Box::new(ContractWrapper::new_with_empty(
+            TestRunner::MultiTest(_) => ContractType::MultiTestContract(Box::new(
+                ContractWrapper::new_with_empty(
                     neutron_astroport_reward_distributor_contract::contract::execute,
                     neutron_astroport_reward_distributor_contract::contract::instantiate,
                     neutron_astroport_reward_distributor_contract::contract::query,
-                )))
-            }
+                )
+                .with_reply(neutron_astroport_reward_distributor_contract::contract::reply)
+                .with_sudo(neutron_astroport_reward_distributor_contract::contract::sudo)
+                .with_migrate(neutron_astroport_reward_distributor_contract::contract::migrate),
+            )),
             #[cfg(feature = "osmosis-test-tube")]
             TestRunner::OsmosisTestApp(_) => {
                 let path = format!("{}/{}", _artifacts_dir, REWARD_DISTRIBUTOR_WASM_NAME);
@@ -101,28 +120,36 @@ impl<'a> RewardDistributorRobot<'a> {
         rewards_start_time: u64,
     ) -> Self {
         // Create vault for reward pool
+        let lock_duration = match reward_type {
+            TestRewardType::LockedVaultToken(lock_duration) => lock_duration,
+            _ => 0,
+        };
         let (reward_vault_robot, axl_ntrn_pool, _astro_ntrn_pool) =
-            LockedAstroportVaultRobot::new_unlocked_axlr_ntrn_vault(
+            LockedAstroportVaultRobot::new_axlr_ntrn_vault(
                 runner,
                 LockedAstroportVaultRobot::contract(runner, dependency_artifacts_dir),
                 Coin::from_str(DENOM_CREATION_FEE).unwrap(),
                 vault_treasury_addr,
                 Decimal::percent(5),
+                lock_duration,
                 vault_dependencies,
                 admin,
             );
 
         let reward_token_info = match &reward_type {
-            TestRewardType::VaultToken => RewardInfo::AstroportVault(
-                neutron_astroport_reward_distributor::msg::AstroportVault {
-                    vault_addr: reward_vault_robot.vault_addr().to_string(),
-                    liquidity_manager_addr: reward_vault_robot
-                        .astroport_contracts()
-                        .liquidity_manager
-                        .address
-                        .clone(),
-                },
-            ),
+            TestRewardType::VaultToken | TestRewardType::LockedVaultToken(_) => {
+                RewardInfo::AstroportVault(
+                    neutron_astroport_reward_distributor::msg::AstroportVault {
+                        vault_addr: reward_vault_robot.vault_addr().to_string(),
+                        pool_addr: None,
+                        liquidity_manager_addr: reward_vault_robot
+                            .astroport_contracts()
+                            .liquidity_manager
+                            .address
+                            .clone(),
+                    },
+                )
+            }
             TestRewardType::LpToken => RewardInfo::AstroportPool(
                 neutron_astroport_reward_distributor::msg::AstroportPool {
                     pool_addr: axl_ntrn_pool.pair_addr.to_string(),
@@ -134,6 +161,21 @@ impl<'a> RewardDistributorRobot<'a> {
                 },
             ),
             TestRewardType::NativeCoin(denom) => RewardInfo::NativeCoin(denom.clone()),
+            TestRewardType::GenericVaultToken => {
+                RewardInfo::GenericVault(reward_vault_robot.vault_addr())
+            }
+            TestRewardType::Mint {
+                subdenom,
+                max_supply,
+            } => RewardInfo::Mint(neutron_astroport_reward_distributor::msg::MintReward {
+                subdenom: subdenom.clone(),
+                max_supply: *max_supply,
+            }),
+        };
+        // The contract pays the fee to create the denom of minted rewards
+        let funds = match &reward_type {
+            TestRewardType::Mint { .. } => vec![Coin::from_str(DENOM_CREATION_FEE).unwrap()],
+            _ => vec![],
         };
 
         // Upload and instantiate reward distributor contract
@@ -142,13 +184,27 @@ impl<'a> RewardDistributorRobot<'a> {
         let distribution_acc = runner.init_account(&[]).unwrap();
         let msg: InstantiateMsg = InstantiateMsg {
             distribution_addr: distribution_acc.address(),
-            emission_per_second: emission_per_second.into(),
+            emission_per_second: Decimal256::from_ratio(emission_per_second.into(), 1u128),
             owner: admin.address(),
+            guardian: None,
             reward_token_info,
             rewards_start_time,
+            config_update_delay: 0,
+            instant_rate_decreases: false,
+            min_emission_per_second: Decimal256::zero(),
+            max_emission_per_second: Decimal256::MAX,
+            max_rate_change: Decimal::zero(),
+            rate_change_window: 0,
+            delivery: Delivery::Underlying,
+            distribution_mode: DistributionMode::Push,
+            fee_bps: 0,
+            fee_recipient: None,
+            recipient_code_id: None,
+            staking_addr: None,
+            destination_pool: None,
         };
         let contract_addr = Wasm::new(runner)
-            .instantiate(code_id, &msg, Some(&admin.address()), None, &[], admin)
+            .instantiate(code_id, &msg, Some(&admin.address()), None, &funds, admin)
             .unwrap()
             .data
             .address;
@@ -171,7 +227,9 @@ impl<'a> RewardDistributorRobot<'a> {
         signer: &SigningAccount,
     ) -> &Self {
         match &self.reward_type {
-            TestRewardType::VaultToken => {
+            TestRewardType::VaultToken
+            | TestRewardType::LockedVaultToken(_)
+            | TestRewardType::GenericVaultToken => {
                 // If the reward token is a vault token, we need to deposit base
                 // tokens to the vault and then deposit the vault tokens to the
                 // reward distributor.
@@ -185,7 +243,7 @@ impl<'a> RewardDistributorRobot<'a> {
                         signer,
                         &self.reward_distributor_addr,
                         amount * INITIAL_VAULT_TOKENS_PER_BASE_TOKEN,
-                        &self.reward_vault_robot.vault_token(),
+                        self.reward_vault_robot.vault_token(),
                     );
             }
             TestRewardType::LpToken => {
@@ -203,6 +261,8 @@ impl<'a> RewardDistributorRobot<'a> {
             TestRewardType::NativeCoin(denom) => {
                 self.send_native_tokens(signer, &self.reward_distributor_addr, amount, denom);
             }
+            // Minted rewards are not deposited
+            TestRewardType::Mint { .. } => {}
         }
         self
     }
@@ -218,6 +278,130 @@ impl<'a> RewardDistributorRobot<'a> {
         self
     }
 
+    /// Calls `ExecuteMsg::Claim` on the reward distributor contract to claim
+    /// the rewards accrued for the signer in pull mode
+    pub fn claim(&self, unwrap_choice: Unwrap, signer: &SigningAccount) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::Claim {};
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    pub fn accept_recipient(&self, unwrap_choice: Unwrap, signer: &SigningAccount) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::AcceptRecipient {};
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    /// Calls `ExecuteMsg::ClaimRewards` on the reward distributor contract to
+    /// claim the rewards accrued for the signer as a staker
+    pub fn claim_rewards(&self, unwrap_choice: Unwrap, signer: &SigningAccount) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::ClaimRewards {};
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    /// Calls `ExecuteMsg::RegisterMerkleAirdrop` on the reward distributor
+    /// contract
+    pub fn register_merkle_airdrop(
+        &self,
+        merkle_root: impl Into<String>,
+        total_amount: impl Into<Uint128>,
+        unwrap_choice: Unwrap,
+        signer: &SigningAccount,
+    ) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::RegisterMerkleAirdrop {
+            merkle_root: merkle_root.into(),
+            total_amount: total_amount.into(),
+        };
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    /// Calls `ExecuteMsg::ClaimMerkleAirdrop` on the reward distributor
+    /// contract to claim the signer's leaf of a Merkle airdrop
+    pub fn claim_merkle_airdrop(
+        &self,
+        airdrop_id: u64,
+        index: u64,
+        amount: impl Into<Uint128>,
+        proof: Vec<String>,
+        unwrap_choice: Unwrap,
+        signer: &SigningAccount,
+    ) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::ClaimMerkleAirdrop {
+            airdrop_id,
+            index,
+            amount: amount.into(),
+            proof,
+        };
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    /// Calls `ExecuteMsg::SetVestingSchedule` on the reward distributor
+    /// contract
+    pub fn set_vesting_schedule(
+        &self,
+        recipient: impl Into<String>,
+        schedule: Option<reward_distributor::VestingSchedule>,
+        unwrap_choice: Unwrap,
+        signer: &SigningAccount,
+    ) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::SetVestingSchedule {
+            recipient: recipient.into(),
+            schedule,
+        };
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    /// Calls `ExecuteMsg::WithdrawVested` on the reward distributor contract
+    /// to withdraw the signer's vested assets
+    pub fn withdraw_vested(&self, unwrap_choice: Unwrap, signer: &SigningAccount) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::WithdrawVested { limit: None };
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    pub fn retry_delivery(&self, unwrap_choice: Unwrap, signer: &SigningAccount) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::RetryDelivery {};
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    pub fn claim_escrow(&self, unwrap_choice: Unwrap, signer: &SigningAccount) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::ClaimEscrow {};
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
     /// Increases the test runner's block time by the given number of seconds
     pub fn increase_time(&self, seconds: u64) -> &Self {
         self.runner.increase_time(seconds).unwrap();
@@ -231,7 +415,60 @@ impl<'a> RewardDistributorRobot<'a> {
         unwrap_choice: Unwrap,
         signer: &SigningAccount,
     ) -> &Self {
-        let msg = reward_distributor::msg::ExecuteMsg::UpdateConfig { updates };
+        self.update_config_with_ramp(updates, None, unwrap_choice, signer)
+    }
+
+    /// Updates the contract's config, ramping the emission rate to its new
+    /// value over `ramp_duration` seconds
+    pub fn update_config_with_ramp(
+        &self,
+        updates: ConfigUpdates,
+        ramp_duration: Option<u64>,
+        unwrap_choice: Unwrap,
+        signer: &SigningAccount,
+    ) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::UpdateConfig {
+            updates,
+            ramp_duration,
+        };
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    /// Proposes a config update to be applied after the config update delay
+    pub fn propose_config_update(
+        &self,
+        updates: ConfigUpdates,
+        unwrap_choice: Unwrap,
+        signer: &SigningAccount,
+    ) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::ProposeConfigUpdate {
+            updates,
+            ramp_duration: None,
+        };
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    /// Applies the pending config update
+    pub fn apply_config_update(&self, unwrap_choice: Unwrap, signer: &SigningAccount) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::ApplyConfigUpdate {};
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    /// Cancels the pending config update
+    pub fn cancel_config_update(&self, unwrap_choice: Unwrap, signer: &SigningAccount) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::CancelConfigUpdate {};
         unwrap_choice.unwrap(
             self.wasm()
                 .execute(&self.reward_distributor_addr, &msg, &[], signer),
@@ -239,6 +476,127 @@ impl<'a> RewardDistributorRobot<'a> {
         self
     }
 
+    /// Grants a role to the given address
+    pub fn grant_role(
+        &self,
+        role: Role,
+        address: impl Into<String>,
+        unwrap_choice: Unwrap,
+        signer: &SigningAccount,
+    ) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::GrantRole {
+            role,
+            address: address.into(),
+        };
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    /// Revokes a role from the given address
+    pub fn revoke_role(
+        &self,
+        role: Role,
+        address: impl Into<String>,
+        unwrap_choice: Unwrap,
+        signer: &SigningAccount,
+    ) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::RevokeRole {
+            role,
+            address: address.into(),
+        };
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    /// Pauses distribution
+    pub fn pause(&self, unwrap_choice: Unwrap, signer: &SigningAccount) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::Pause {};
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    /// Unpauses distribution
+    pub fn unpause(&self, unwrap_choice: Unwrap, signer: &SigningAccount) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::Unpause {};
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    /// Sets the pool that the redeemed assets are provided to as liquidity
+    pub fn set_destination_pool(
+        &self,
+        destination_pool: Option<reward_distributor::msg::DestinationPoolInfo>,
+        unwrap_choice: Unwrap,
+        signer: &SigningAccount,
+    ) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::SetDestinationPool { destination_pool };
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    /// Pauses distribution and withdraws the reward tokens not owed to anyone
+    pub fn emergency_withdraw(
+        &self,
+        recipient: impl Into<String>,
+        unwrap_choice: Unwrap,
+        signer: &SigningAccount,
+    ) -> &Self {
+        let msg = reward_distributor::msg::ExecuteMsg::EmergencyWithdraw {
+            recipient: recipient.into(),
+        };
+        unwrap_choice.unwrap(
+            self.wasm()
+                .execute(&self.reward_distributor_addr, &msg, &[], signer),
+        );
+        self
+    }
+
+    /// Calls the sudo entry point of the reward distributor contract, as done
+    /// by chain governance. Only supported on multi-test.
+    pub fn sudo(&self, msg: &reward_distributor::msg::SudoMsg, unwrap_choice: Unwrap) -> &Self {
+        match self.runner {
+            TestRunner::MultiTest(runner) => {
+                unwrap_choice.unwrap(
+                    runner
+                        .app
+                        .wasm_sudo(Addr::unchecked(&self.reward_distributor_addr), msg),
+                );
+            }
+            _ => panic!("Sudo is only supported on multi-test"),
+        }
+        self
+    }
+
+    /// Returns the code id of the given contract. Only supported on multi-test.
+    pub fn query_code_id(&self, contract_addr: impl Into<String>) -> u64 {
+        match self.runner {
+            TestRunner::MultiTest(runner) => {
+                runner
+                    .app
+                    .wrap()
+                    .query_wasm_contract_info(contract_addr)
+                    .unwrap()
+                    .code_id
+            }
+            _ => panic!("Querying code ids is only supported on multi-test"),
+        }
+    }
+
     /// Updates the contract's ownership
     pub fn update_ownership(
         &self,
@@ -264,6 +622,104 @@ impl<'a> RewardDistributorRobot<'a> {
             .unwrap()
     }
 
+    /// Queries the reward distributor contract for its pending config update
+    pub fn query_pending_config_update(&self) -> Option<PendingConfigUpdate> {
+        let query_msg = reward_distributor::msg::QueryMsg::PendingConfigUpdate {};
+        self.wasm()
+            .query(&self.reward_distributor_addr, &query_msg)
+            .unwrap()
+    }
+
+    /// Queries the unlocking positions started by the reward distributor
+    pub fn query_pending_unlocks(&self) -> Vec<UnlockingPosition> {
+        let query_msg = reward_distributor::msg::QueryMsg::PendingUnlocks {
+            start_after: None,
+            limit: None,
+        };
+        self.wasm()
+            .query(&self.reward_distributor_addr, &query_msg)
+            .unwrap()
+    }
+
+    /// Queries the assets held in escrow for the given address
+    pub fn query_escrow(&self, address: impl Into<String>) -> Option<AssetList> {
+        let query_msg = reward_distributor::msg::QueryMsg::Escrow {
+            address: address.into(),
+        };
+        self.wasm()
+            .query(&self.reward_distributor_addr, &query_msg)
+            .unwrap()
+    }
+
+    /// Queries the stake and claimable rewards of the given staker
+    pub fn query_staker_rewards(
+        &self,
+        address: impl Into<String>,
+    ) -> reward_distributor::msg::StakerRewardsResponse {
+        let query_msg = reward_distributor::msg::QueryMsg::StakerRewards {
+            address: address.into(),
+        };
+        self.wasm()
+            .query(&self.reward_distributor_addr, &query_msg)
+            .unwrap()
+    }
+
+    /// Queries the Merkle airdrop with the given id
+    pub fn query_merkle_airdrop(
+        &self,
+        airdrop_id: u64,
+    ) -> Option<reward_distributor::MerkleAirdrop> {
+        let query_msg = reward_distributor::msg::QueryMsg::MerkleAirdrop { airdrop_id };
+        self.wasm()
+            .query(&self.reward_distributor_addr, &query_msg)
+            .unwrap()
+    }
+
+    /// Queries whether the given leaf of a Merkle airdrop has been claimed
+    pub fn query_is_leaf_claimed(&self, airdrop_id: u64, index: u64) -> bool {
+        let query_msg = reward_distributor::msg::QueryMsg::IsLeafClaimed { airdrop_id, index };
+        self.wasm()
+            .query(&self.reward_distributor_addr, &query_msg)
+            .unwrap()
+    }
+
+    /// Queries the vesting positions of the given recipient
+    pub fn query_vesting_positions(
+        &self,
+        address: impl Into<String>,
+    ) -> Vec<reward_distributor::VestingPosition> {
+        let query_msg = reward_distributor::msg::QueryMsg::VestingPositions {
+            address: address.into(),
+            start_after: None,
+            limit: None,
+        };
+        self.wasm()
+            .query(&self.reward_distributor_addr, &query_msg)
+            .unwrap()
+    }
+
+    pub fn query_unclaimed_rewards(
+        &self,
+        address: impl Into<String>,
+    ) -> Option<reward_distributor::UnclaimedRewards> {
+        let query_msg = reward_distributor::msg::QueryMsg::UnclaimedRewards {
+            address: address.into(),
+        };
+        self.wasm()
+            .query(&self.reward_distributor_addr, &query_msg)
+            .unwrap()
+    }
+
+    /// Queries the roles granted to the given address
+    pub fn query_roles(&self, address: impl Into<String>) -> Vec<Role> {
+        let query_msg = reward_distributor::msg::QueryMsg::Roles {
+            address: address.into(),
+        };
+        self.wasm()
+            .query(&self.reward_distributor_addr, &query_msg)
+            .unwrap()
+    }
+
     /// Queries the distribution account for its native token balances
     pub fn query_distribution_acc_balances(&self) -> Vec<Coin> {
         // self.query_balances(&self.distribution_acc.address())